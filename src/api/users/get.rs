@@ -34,6 +34,10 @@ impl Endpoint for E {
   fn method(&self) -> Method {
     Method::GET
   }
+
+  fn conditional(&self) -> bool {
+    true
+  }
   
   async fn ctx(
     &self,
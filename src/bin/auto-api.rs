@@ -1,25 +1,55 @@
+use std::{net::SocketAddr, sync::Arc};
+
+use auto_api::{cache::ResponseCache, compression::CompressionOptions, cors::CorsOptions, metrics::Metrics, otel::{Otel, OtelExporter}};
 use axum::{response::Html, routing::get, Json};
 
 #[tokio::main]
 async fn main() {
-  let registry = auto_api::api::registry();
+  tracing_subscriber::fmt()
+    .with_env_filter(tracing_subscriber::EnvFilter::try_from_default_env().unwrap_or_else(|_| "info".into()))
+    .init();
+
+  let mut registry = auto_api::api::registry();
+  registry.metrics = Some(Arc::new(Metrics::default()));
+  registry.cors = Some(Arc::new(CorsOptions::default()));
+  registry.compression = Some(Arc::new(CompressionOptions::default()));
+  registry.cache = Some(Arc::new(ResponseCache::default()));
+
+  // flushes the pending spans when main returns
+  let mut _otel_guard = None;
+  if let Ok(endpoint) = std::env::var("OTEL_EXPORTER_OTLP_ENDPOINT") {
+    let otel = Otel::new("auto-api", OtelExporter::Otlp { endpoint }).expect("otel exporter init");
+    _otel_guard = Some(otel.shutdown_guard());
+    registry.otel = Some(Arc::new(otel));
+  }
+
+  // serve schema generated example outputs instead of running the endpoints
+  let mock = std::env::args().skip(1).any(|arg| arg == "--mock");
 
   let openapi = registry.openapi_spec();
-  let api = registry.axum_router();
+  let api = if mock { registry.mock_router() } else { registry.axum_router() };
 
   let app = axum::Router::new()
     .route("/", get(Html(redoc())))
     .route("/swagger", get(Html(swagger())))
     .route("/openapi.json", get(Json(openapi)))
-    .nest("/", api);
+    .merge(api);
 
   let listener = tokio::net::TcpListener::bind("127.0.0.1:6985")
     .await
     .expect("tcp listener bind");
 
-  println!("listening on http://127.0.0.1:6985");
+  if mock {
+    println!("listening on http://127.0.0.1:6985 (mock mode)");
+  } else {
+    println!("listening on http://127.0.0.1:6985");
+  }
 
-  axum::serve(listener, app)
+  // the peer address is used as the default rate limit key
+  axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>())
+    .with_graceful_shutdown(async {
+      tokio::signal::ctrl_c().await.ok();
+    })
     .await
     .expect("axum serve");
 }
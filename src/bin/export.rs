@@ -1,16 +1,206 @@
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
-#[tokio::main]
-async fn main() {
+use auto_api::diff::{diff, has_breaking};
+use auto_api::export::{check, write, ExportOptions, Format, Status, Target};
+
+const USAGE: &str = "usage:
+  export [--out <dir>] [--format <format>[=<path>]]... [--all] [--check] [--schema-base <url>]
+  export diff [<previous openapi.json>]
+
+options:
+  --out <dir>                  directory for relative and default paths, defaults to ./generated
+  --format <format>[=<path>]   a target to export, can be repeated, defaults to openapi-json and ts
+  --all                        export every format with its default file name
+  --check                      write nothing and fail if any target is missing or stale,
+                               the files are compared byte for byte so they must not be reformatted
+  --schema-base <url>          prefix of the `$id` of json-schema-files documents, defaults to none
+
+formats:
+  openapi-json (openapi.json), openapi-yaml (openapi.yaml), ts (api.ts),
+  ts-client (client.ts), json-schema (schemas.json), json-schema-files (schemas/),
+  zod (zod.ts), python (python/), kotlin (kotlin/), swift (swift/)";
+
+struct Args {
+  out: PathBuf,
+  formats: Vec<String>,
+  all: bool,
+  check: bool,
+  options: ExportOptions,
+}
+
+fn parse_args(args: &[String]) -> Result<Args, String> {
+  let mut parsed = Args {
+    out: Path::new(env!("CARGO_MANIFEST_DIR")).join("generated"),
+    formats: vec![],
+    all: false,
+    check: false,
+    options: ExportOptions::default(),
+  };
+
+  let mut args = args.iter();
+  while let Some(arg) = args.next() {
+    let (name, inline) = match arg.split_once('=') {
+      Some((name, value)) if name.starts_with("--") => (name, Some(value.to_string())),
+      _ => (arg.as_str(), None),
+    };
+
+    let mut value = || match inline.clone().or_else(|| args.next().cloned()) {
+      Some(value) => Ok(value),
+      None => Err(format!("missing value for {name}")),
+    };
+
+    match name {
+      "--out" => parsed.out = PathBuf::from(value()?),
+      "--format" => parsed.formats.push(value()?),
+      "--all" => parsed.all = true,
+      "--check" => parsed.check = true,
+      "--schema-base" => parsed.options.schema_base = value()?,
+      other => return Err(format!("unknown argument `{other}`")),
+    }
+  }
+
+  Ok(parsed)
+}
+
+fn main() {
   let registry = auto_api::api::registry();
+  let args = std::env::args().skip(1).collect::<Vec<_>>();
+
+  if args.iter().any(|arg| arg == "-h" || arg == "--help") {
+    println!("{USAGE}");
+    return;
+  }
+
+  if args.first().map(String::as_str) == Some("diff") {
+    // compare against a previously exported spec, the committed one by default
+    let old_path = match args.get(1) {
+      Some(path) => PathBuf::from(path),
+      None => Path::new(env!("CARGO_MANIFEST_DIR")).join("generated/openapi.json"),
+    };
+    let old = std::fs::read_to_string(&old_path).expect("error reading previous openapi spec");
+    let old = serde_json::from_str(&old).expect("error parsing previous openapi spec");
+
+    let changes = diff(&old, &registry.openapi_spec());
+    if changes.is_empty() {
+      println!("no changes against {}", old_path.display());
+      return;
+    }
+
+    for change in &changes {
+      println!("{change}");
+    }
+
+    if has_breaking(&changes) {
+      eprintln!("breaking changes found against {}", old_path.display());
+      std::process::exit(1);
+    }
+    return;
+  }
+
+  let args = match parse_args(&args) {
+    Ok(args) => args,
+    Err(err) => {
+      eprintln!("{err}\n\n{USAGE}");
+      std::process::exit(2);
+    }
+  };
+
+  let mut targets = vec![];
+  if args.all {
+    targets.extend(Format::all().into_iter().map(|format| {
+      let path = args.out.join(format.default_file_name());
+      Target::new(format, path)
+    }));
+  }
+  for format in &args.formats {
+    match Target::parse(format, &args.out) {
+      Ok(target) => targets.push(target),
+      Err(err) => {
+        eprintln!("{err}");
+        std::process::exit(2);
+      }
+    }
+  }
+  if targets.is_empty() {
+    targets = [Format::OpenApiJson, Format::Ts]
+      .into_iter()
+      .map(|format| {
+        let path = args.out.join(format.default_file_name());
+        Target::new(format, path)
+      })
+      .collect();
+  }
+
+  if args.check {
+    let mut failed = false;
+    for (target, status) in check(&registry, &targets, &args.options) {
+      let label = match status {
+        Status::UpToDate => "up to date",
+        Status::Stale => "stale",
+        Status::Missing => "missing",
+      };
+      failed |= status != Status::UpToDate;
+      println!("{} {label}: {}", target.format.name(), target.path.display());
+    }
+    if failed {
+      eprintln!("generated files are out of date, run `just export`");
+      std::process::exit(1);
+    }
+    return;
+  }
+
+  if let Err(err) = write(&registry, &targets, &args.options) {
+    eprintln!("error writing exports: {err}");
+    std::process::exit(1);
+  }
+
+  for target in &targets {
+    println!("{} written to {}", target.format.name(), target.path.display());
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn parse(args: &[&str]) -> Result<Args, String> {
+    parse_args(&args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>())
+  }
+
+  #[test]
+  fn defaults() {
+    let args = parse(&[]).unwrap();
+    assert!(args.out.ends_with("generated"));
+    assert!(args.formats.is_empty());
+    assert!(!args.all && !args.check);
+    assert_eq!(args.options.schema_base, "");
+  }
+
+  #[test]
+  fn formats() {
+    let args = parse(&["--format", "ts=web/api.ts", "--format=openapi-json", "--format=zod=src/zod.ts", "--out", "/tmp/out"]).unwrap();
+    assert_eq!(args.formats, ["ts=web/api.ts", "openapi-json", "zod=src/zod.ts"]);
+    assert_eq!(args.out, Path::new("/tmp/out"));
+
+    let targets = args.formats.iter().map(|format| Target::parse(format, &args.out).unwrap()).collect::<Vec<_>>();
+    assert_eq!(targets, [
+      Target::new(Format::Ts, "/tmp/out/web/api.ts"),
+      Target::new(Format::OpenApiJson, "/tmp/out/openapi.json"),
+      Target::new(Format::Zod, "/tmp/out/src/zod.ts"),
+    ]);
+  }
 
-  let openapi_spec = registry.openapi_spec();
-  let openapi_path = Path::new(env!("CARGO_MANIFEST_DIR")).join("generated/openapi.json");
-  std::fs::write(&openapi_path, serde_json::to_string_pretty(&openapi_spec).unwrap()).expect("error writing openapi spec");
-  println!("openapi spec written to {}", openapi_path.display());
+  #[test]
+  fn flags() {
+    let args = parse(&["--all", "--check", "--schema-base=https://example.test/schemas/"]).unwrap();
+    assert!(args.all && args.check);
+    assert_eq!(args.options.schema_base, "https://example.test/schemas/");
+  }
 
-  let ts_defs = registry.ts_definitions();
-  let ts_path = Path::new(env!("CARGO_MANIFEST_DIR")).join("generated/api.ts");
-  std::fs::write(&ts_path, &ts_defs).expect("error writing ts definitions");
-  println!("ts definitions written to {}", ts_path.display());
-}
\ No newline at end of file
+  #[test]
+  fn errors() {
+    assert_eq!(parse(&["--format"]).err().unwrap(), "missing value for --format");
+    assert_eq!(parse(&["--verbose"]).err().unwrap(), "unknown argument `--verbose`");
+    assert_eq!(parse(&["openapi-json"]).err().unwrap(), "unknown argument `openapi-json`");
+  }
+}
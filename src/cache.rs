@@ -0,0 +1,324 @@
+use std::{
+  collections::hash_map::RandomState,
+  hash::BuildHasher,
+  num::NonZeroUsize,
+  sync::Mutex,
+  time::{Duration, Instant},
+};
+
+use axum::{
+  async_trait,
+  body::Bytes,
+  http::{header::CACHE_CONTROL, HeaderMap, HeaderValue, Method},
+};
+use lru::LruCache;
+use serde::Serialize;
+
+/// Who can cache the response, sent in the `Cache-Control` header
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum CacheVisibility {
+  /// Shared caches (CDNs, proxies) can store the response
+  Public,
+  /// Only the client can store the response, use it when the output depends on the `Ctx` \
+  /// The server stores it only per [`crate::endpoint::Endpoint::cache_vary`] key
+  Private,
+  /// The response is cached in the server only, clients must not store it
+  ServerOnly,
+}
+
+/// Caching policy of an endpoint, see [`crate::endpoint::Endpoint::cache`]
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct CachePolicy {
+  /// How long an output is reused
+  pub ttl: Duration,
+  pub visibility: CacheVisibility,
+}
+
+impl CachePolicy {
+  pub const fn public(ttl: Duration) -> Self {
+    Self { ttl, visibility: CacheVisibility::Public }
+  }
+
+  pub const fn private(ttl: Duration) -> Self {
+    Self { ttl, visibility: CacheVisibility::Private }
+  }
+
+  pub const fn server_only(ttl: Duration) -> Self {
+    Self { ttl, visibility: CacheVisibility::ServerOnly }
+  }
+
+  /// The `Cache-Control` header value consistent with this policy
+  pub fn cache_control(&self) -> HeaderValue {
+    let value = match self.visibility {
+      CacheVisibility::Public => format!("public, max-age={}", self.ttl.as_secs()),
+      CacheVisibility::Private => format!("private, max-age={}", self.ttl.as_secs()),
+      CacheVisibility::ServerOnly => String::from("no-store"),
+    };
+    HeaderValue::from_str(&value).expect("invalid cache-control header")
+  }
+
+  pub fn write_headers(&self, headers: &mut HeaderMap) {
+    if !headers.contains_key(CACHE_CONTROL) {
+      headers.insert(CACHE_CONTROL, self.cache_control());
+    }
+  }
+}
+
+/// Storage for serialized endpoint outputs
+#[async_trait]
+pub trait CacheStore: Send + Sync + 'static {
+  async fn get(&self, key: &str) -> Option<Bytes>;
+  async fn set(&self, key: &str, value: Bytes, ttl: Duration);
+  async fn remove(&self, key: &str);
+  /// Remove every entry whose key starts with `prefix`
+  async fn remove_prefix(&self, prefix: &str);
+  async fn clear(&self);
+}
+
+#[derive(Debug)]
+struct MemoryCacheEntry {
+  value: Bytes,
+  expires: Instant,
+}
+
+type Shard = Mutex<LruCache<String, MemoryCacheEntry>>;
+
+/// An in process LRU [`CacheStore`] bounded by number of entries
+///
+/// The entries are spread over independently locked shards by key hash,
+/// each evicting its own least recently used entries
+#[derive(Debug)]
+pub struct MemoryCache {
+  shards: Box<[Shard]>,
+  hasher: RandomState,
+}
+
+impl Default for MemoryCache {
+  fn default() -> Self {
+    Self::new(10_000)
+  }
+}
+
+impl MemoryCache {
+  const MAX_SHARDS: usize = 16;
+
+  /// Panics if `capacity` is zero, don't register a [`ResponseCache`] to disable caching
+  pub fn new(capacity: usize) -> Self {
+    let capacity = NonZeroUsize::new(capacity).expect("memory cache capacity must be greater than zero");
+    // small caches keep a single shard so the capacity is an exact bound
+    let count = if capacity.get() < 1024 { 1 } else { Self::MAX_SHARDS };
+    let shards = (0..count)
+      .map(|i| {
+        let shard_capacity = capacity.get() / count + usize::from(i < capacity.get() % count);
+        Mutex::new(LruCache::new(NonZeroUsize::new(shard_capacity).unwrap()))
+      })
+      .collect();
+
+    Self { shards, hasher: RandomState::new() }
+  }
+
+  fn shard(&self, key: &str) -> &Shard {
+    let i = self.hasher.hash_one(key) as usize % self.shards.len();
+    &self.shards[i]
+  }
+
+  pub fn len(&self) -> usize {
+    self.shards.iter().map(|shard| shard.lock().unwrap().len()).sum()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.len() == 0
+  }
+}
+
+#[async_trait]
+impl CacheStore for MemoryCache {
+  async fn get(&self, key: &str) -> Option<Bytes> {
+    let mut shard = self.shard(key).lock().unwrap();
+    // moves the entry to the front as the most recently used
+    let entry = shard.get(key)?;
+    if entry.expires <= Instant::now() {
+      shard.pop(key);
+      return None;
+    }
+    Some(entry.value.clone())
+  }
+
+  async fn set(&self, key: &str, value: Bytes, ttl: Duration) {
+    let entry = MemoryCacheEntry { value, expires: Instant::now() + ttl };
+    self.shard(key).lock().unwrap().put(key.to_string(), entry);
+  }
+
+  async fn remove(&self, key: &str) {
+    self.shard(key).lock().unwrap().pop(key);
+  }
+
+  async fn remove_prefix(&self, prefix: &str) {
+    for shard in self.shards.iter() {
+      let mut shard = shard.lock().unwrap();
+      let keys = shard.iter().map(|(key, _)| key).filter(|key| key.starts_with(prefix)).cloned().collect::<Vec<_>>();
+      for key in keys {
+        shard.pop(&key);
+      }
+    }
+  }
+
+  async fn clear(&self) {
+    for shard in self.shards.iter() {
+      shard.lock().unwrap().clear();
+    }
+  }
+}
+
+/// Server side cache of endpoint outputs, keyed by method, path, params, query and the endpoint vary key
+pub struct ResponseCache {
+  pub store: Box<dyn CacheStore>,
+}
+
+impl Default for ResponseCache {
+  fn default() -> Self {
+    Self::new(MemoryCache::default())
+  }
+}
+
+impl ResponseCache {
+  pub fn new(store: impl CacheStore) -> Self {
+    Self { store: Box::new(store) }
+  }
+
+  /// Prefix shared by every key of an endpoint
+  pub fn endpoint_prefix(method: &Method, path: &str) -> String {
+    format!("{method} {path}|")
+  }
+
+  /// The key of a cached output, `None` if the inputs can't be serialized
+  pub fn key<P: Serialize, Q: Serialize>(
+    method: &Method,
+    path: &str,
+    params: &P,
+    query: &Q,
+    vary: Option<&str>,
+  ) -> Option<String> {
+    let params = serde_json::to_string(params).ok()?;
+    let query = serde_json::to_string(query).ok()?;
+    let vary = vary.unwrap_or("");
+    Some(format!("{}{params}|{query}|{vary}", Self::endpoint_prefix(method, path)))
+  }
+
+  /// Invalidate every cached output of the endpoint registered at `method` and `path`
+  pub async fn invalidate_endpoint(&self, method: &Method, path: &str) {
+    self.store.remove_prefix(&Self::endpoint_prefix(method, path)).await;
+  }
+
+  /// Invalidate a single cached output
+  pub async fn invalidate<P: Serialize, Q: Serialize>(
+    &self,
+    method: &Method,
+    path: &str,
+    params: &P,
+    query: &Q,
+    vary: Option<&str>,
+  ) {
+    if let Some(key) = Self::key(method, path, params, query, vary) {
+      self.store.remove(&key).await;
+    }
+  }
+
+  /// Invalidate every cached output of the endpoint with these params, for any query and vary key
+  pub async fn invalidate_params<P: Serialize>(&self, method: &Method, path: &str, params: &P) {
+    if let Ok(params) = serde_json::to_string(params) {
+      let prefix = format!("{}{params}|", Self::endpoint_prefix(method, path));
+      self.store.remove_prefix(&prefix).await;
+    }
+  }
+
+  pub async fn clear(&self) {
+    self.store.clear().await;
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const TTL: Duration = Duration::from_secs(60);
+
+  async fn get(store: &dyn CacheStore, key: &str) -> Option<String> {
+    let value = store.get(key).await?;
+    Some(String::from_utf8(value.to_vec()).unwrap())
+  }
+
+  #[tokio::test]
+  async fn eviction() {
+    let cache = MemoryCache::new(2);
+    cache.set("a", Bytes::from("1"), TTL).await;
+    cache.set("b", Bytes::from("2"), TTL).await;
+
+    // reading a makes b the least recently used
+    assert_eq!(get(&cache, "a").await.as_deref(), Some("1"));
+    cache.set("c", Bytes::from("3"), TTL).await;
+    assert_eq!(cache.len(), 2);
+    assert_eq!(get(&cache, "b").await.as_deref(), None);
+    assert_eq!(get(&cache, "a").await.as_deref(), Some("1"));
+    assert_eq!(get(&cache, "c").await.as_deref(), Some("3"));
+
+    // overwriting doesn't evict
+    cache.set("c", Bytes::from("4"), TTL).await;
+    assert_eq!(get(&cache, "a").await.as_deref(), Some("1"));
+    assert_eq!(get(&cache, "c").await.as_deref(), Some("4"));
+  }
+
+  #[tokio::test]
+  async fn sharded_capacity() {
+    let cache = MemoryCache::new(2000);
+    for i in 0..5000 {
+      cache.set(&i.to_string(), Bytes::new(), TTL).await;
+    }
+    assert_eq!(cache.len(), 2000);
+    assert_eq!(get(&cache, "4999").await.as_deref(), Some(""));
+  }
+
+  #[tokio::test]
+  async fn ttl() {
+    let cache = MemoryCache::new(10);
+    cache.set("short", Bytes::from("1"), Duration::from_millis(10)).await;
+    cache.set("long", Bytes::from("2"), TTL).await;
+    assert_eq!(get(&cache, "short").await.as_deref(), Some("1"));
+
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    assert_eq!(get(&cache, "short").await.as_deref(), None);
+    assert_eq!(get(&cache, "long").await.as_deref(), Some("2"));
+    assert_eq!(cache.len(), 1);
+  }
+
+  #[tokio::test]
+  async fn vary_keys() {
+    let cache = ResponseCache::new(MemoryCache::new(10));
+    let key = |params: &str, vary| ResponseCache::key(&Method::GET, "/users/:id", &params, &(), vary).unwrap();
+
+    let alice = key("1", Some("alice"));
+    let bob = key("1", Some("bob"));
+    assert_ne!(alice, bob);
+    assert_ne!(alice, key("1", None));
+
+    cache.store.set(&alice, Bytes::from("alice"), TTL).await;
+    cache.store.set(&bob, Bytes::from("bob"), TTL).await;
+    cache.store.set(&key("2", Some("alice")), Bytes::from("other"), TTL).await;
+    assert_eq!(get(cache.store.as_ref(), &alice).await.as_deref(), Some("alice"));
+    assert_eq!(get(cache.store.as_ref(), &bob).await.as_deref(), Some("bob"));
+
+    cache.invalidate(&Method::GET, "/users/:id", &"1", &(), Some("bob")).await;
+    assert_eq!(get(cache.store.as_ref(), &bob).await.as_deref(), None);
+    assert_eq!(get(cache.store.as_ref(), &alice).await.as_deref(), Some("alice"));
+
+    // every vary key of the params
+    cache.store.set(&bob, Bytes::from("bob"), TTL).await;
+    cache.invalidate_params(&Method::GET, "/users/:id", &"1").await;
+    assert_eq!(get(cache.store.as_ref(), &alice).await.as_deref(), None);
+    assert_eq!(get(cache.store.as_ref(), &bob).await.as_deref(), None);
+    assert_eq!(get(cache.store.as_ref(), &key("2", Some("alice"))).await.as_deref(), Some("other"));
+
+    cache.invalidate_endpoint(&Method::GET, "/users/:id").await;
+    assert_eq!(get(cache.store.as_ref(), &key("2", Some("alice"))).await.as_deref(), None);
+  }
+}
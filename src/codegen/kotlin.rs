@@ -0,0 +1,184 @@
+use std::path::PathBuf;
+
+use super::{camel, pascal, screaming, GeneratedFile, Generator};
+use crate::ir::{Api, DefKind, Field, Type};
+
+/// kotlinx.serialization data classes in a single `Api.kt`
+#[derive(Debug, Clone)]
+pub struct Kotlin {
+  /// The package of the generated file
+  pub package: String,
+}
+
+impl Default for Kotlin {
+  fn default() -> Self {
+    Self { package: String::from("api") }
+  }
+}
+
+impl Generator for Kotlin {
+  fn name(&self) -> &'static str {
+    "kotlin"
+  }
+
+  fn generate(&self, api: &Api) -> Vec<GeneratedFile> {
+    vec![GeneratedFile { path: PathBuf::from("Api.kt"), contents: render(api, &self.package) }]
+  }
+}
+
+const KEYWORDS: &[&str] = &[
+  "as", "break", "class", "continue", "do", "else", "false", "for", "fun", "if", "in", "interface", "is", "null",
+  "object", "package", "return", "super", "this", "throw", "true", "try", "typealias", "typeof", "val", "var", "when",
+  "while",
+];
+
+fn render(api: &Api, package: &str) -> String {
+  let mut out = format!(
+    r#"// this file is auto generated by its Rust definition, do not edit manually
+
+@file:OptIn(ExperimentalSerializationApi::class)
+
+package {package}
+
+import kotlinx.serialization.ExperimentalSerializationApi
+import kotlinx.serialization.SerialName
+import kotlinx.serialization.Serializable
+import kotlinx.serialization.json.JsonClassDiscriminator
+import kotlinx.serialization.json.JsonElement
+"#
+  );
+
+  for (name, def) in &api.types {
+    out.push('\n');
+    out.push_str(&kdoc(def.doc.as_deref(), 0));
+    match &def.kind {
+      DefKind::Struct(fields) => out.push_str(&class(name, fields, &[], None)),
+      DefKind::Enum(values) => {
+        out.push_str(&format!("@Serializable\nenum class {name} {{\n"));
+        for value in values {
+          out.push_str(&format!("    @SerialName({})\n    {},\n", quote(value), identifier(&screaming(value))));
+        }
+        out.push_str("}\n");
+      }
+      DefKind::Tagged { tag, shared, variants } => {
+        out.push_str(&format!("@Serializable\n@JsonClassDiscriminator({})\nsealed interface {name} {{\n", quote(tag)));
+        for field in shared {
+          out.push_str(&kdoc(field.doc.as_deref(), 1));
+          out.push_str(&format!("    val {}: {}\n", identifier(&camel(&field.name)), field_ty(field)));
+        }
+        out.push_str("}\n");
+
+        for (value, fields) in variants {
+          out.push('\n');
+          let overrides = shared.iter().map(|field| field.name.as_str()).collect::<Vec<_>>();
+          let fields = shared.iter().chain(fields).cloned().collect::<Vec<_>>();
+          out.push_str(&class(&format!("{name}{}", pascal(value)), &fields, &overrides, Some((name, value))));
+        }
+      }
+    }
+  }
+
+  out.push('\n');
+  alias(&mut out, "ErrorPayload", &api.error.ty);
+  for operation in &api.operations {
+    for (suffix, input) in operation.inputs() {
+      if let Some(input) = input {
+        alias(&mut out, &format!("{}{suffix}", operation.name), &input.ty);
+      }
+    }
+    alias(&mut out, &format!("{}Output", operation.name), &operation.output.ty);
+  }
+
+  out
+}
+
+/// A data class, `overrides` are the fields of the sealed interface of a `variant`
+fn class(name: &str, fields: &[Field], overrides: &[&str], variant: Option<(&str, &str)>) -> String {
+  let mut out = String::from("@Serializable\n");
+  if let Some((_, value)) = variant {
+    out.push_str(&format!("@SerialName({})\n", quote(value)));
+  }
+
+  // data classes need at least one property
+  if fields.is_empty() {
+    out.push_str(&format!("class {name}"));
+  } else {
+    out.push_str(&format!("data class {name}(\n"));
+    for field in fields {
+      let property = identifier(&camel(&field.name));
+      out.push_str(&kdoc(field.doc.as_deref(), 1));
+      if camel(&field.name) != field.name {
+        out.push_str(&format!("    @SerialName({})\n", quote(&field.name)));
+      }
+      let modifier = if overrides.contains(&field.name.as_str()) { "override " } else { "" };
+      let default = if field_ty(field).ends_with('?') && !field.required { " = null" } else { "" };
+      out.push_str(&format!("    {modifier}val {property}: {}{default},\n", field_ty(field)));
+    }
+    out.push(')');
+  }
+
+  if let Some((parent, _)) = variant {
+    out.push_str(&format!(" : {parent}"));
+  }
+  out.push('\n');
+  out
+}
+
+fn alias(out: &mut String, name: &str, ty: &Type) {
+  if *ty != Type::Named(name.to_string()) {
+    out.push_str(&format!("typealias {name} = {}\n", self::ty(ty)));
+  }
+}
+
+/// Optional fields are nullable with a `null` default
+fn field_ty(field: &Field) -> String {
+  match (&field.ty, field.required) {
+    (Type::Nullable(_), _) | (_, true) => ty(&field.ty),
+    (ty, false) => format!("{}?", self::ty(ty)),
+  }
+}
+
+fn ty(ty: &Type) -> String {
+  match ty {
+    Type::Any | Type::Union(_) => String::from("JsonElement"),
+    Type::Bool => String::from("Boolean"),
+    Type::Int => String::from("Long"),
+    Type::Float => String::from("Double"),
+    Type::String | Type::Literal(_) => String::from("String"),
+    Type::Nullable(ty) => match self::ty(ty) {
+      ty if ty.ends_with('?') => ty,
+      ty => format!("{ty}?"),
+    },
+    Type::List(ty) => format!("List<{}>", self::ty(ty)),
+    Type::Map(ty) => format!("Map<String, {}>", self::ty(ty)),
+    Type::Named(name) => name.clone(),
+  }
+}
+
+fn identifier(name: &str) -> String {
+  match KEYWORDS.contains(&name) || name.starts_with(|c: char| c.is_ascii_digit()) {
+    true => format!("`{name}`"),
+    false => name.to_string(),
+  }
+}
+
+/// A string literal, `$` starts a template in kotlin strings
+fn quote(value: &str) -> String {
+  super::quote(value).replace('$', "\\$")
+}
+
+fn kdoc(doc: Option<&str>, indent: usize) -> String {
+  let doc = match doc {
+    // kotlin block comments nest
+    Some(doc) => doc.replace("*/", "*&#47;").replace("/*", "&#47;*"),
+    None => return String::new(),
+  };
+  let pad = "    ".repeat(indent);
+  match doc.lines().count() {
+    1 => format!("{pad}/** {doc} */\n"),
+    _ => {
+      let lines = doc.lines().map(|line| format!("{pad} *{}{line}\n", if line.is_empty() { "" } else { " " }));
+      format!("{pad}/**\n{}{pad} */\n", lines.collect::<String>())
+    }
+  }
+}
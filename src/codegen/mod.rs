@@ -0,0 +1,189 @@
+pub mod kotlin;
+pub mod openapi;
+pub mod python;
+pub mod swift;
+pub mod ts;
+
+use std::{path::PathBuf, sync::Arc};
+
+use crate::ir::Api;
+
+/// A file produced by a [`Generator`], `path` is relative to the output directory
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct GeneratedFile {
+  pub path: PathBuf,
+  pub contents: String,
+}
+
+/// Generates code for another language from the [`Api`] of a registry, eg: models and a client
+///
+/// Implement it for in-house formats and export it as a [`crate::export::Format::Generated`] target,
+/// `--check` works the same as for the built-in formats \
+/// The TS and OpenAPI formats are generators too, see [`ts::Ts`] and [`openapi::OpenApi`]
+pub trait Generator {
+  /// The name used to select the generator and its default directory, eg: `python`
+  fn name(&self) -> &'static str;
+
+  /// The generated files, the output must be deterministic for a given api
+  fn generate(&self, api: &Api) -> Vec<GeneratedFile>;
+}
+
+impl std::fmt::Debug for dyn Generator {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.debug_tuple("Generator").field(&self.name()).finish()
+  }
+}
+
+/// Generators are identified by their name
+impl PartialEq for dyn Generator {
+  fn eq(&self, other: &Self) -> bool {
+    self.name() == other.name()
+  }
+}
+
+impl Eq for dyn Generator {}
+
+/// The built-in generators
+pub fn generators() -> Vec<Arc<dyn Generator>> {
+  vec![Arc::new(python::Python), Arc::new(kotlin::Kotlin::default()), Arc::new(swift::Swift)]
+}
+
+/// `PascalCase` of a name in any case, eg: `RESOURCE_NOT_FOUND` => `ResourceNotFound`, `Pagination Limit` => `PaginationLimit`
+pub fn pascal(name: &str) -> String {
+  let mut out = String::new();
+  for word in name.split(|c: char| !c.is_ascii_alphanumeric()) {
+    let mut chars = word.chars();
+    if let Some(first) = chars.next() {
+      out.push(first.to_ascii_uppercase());
+      let rest = chars.as_str();
+      match rest.chars().all(|c| !c.is_ascii_lowercase()) {
+        true => out.push_str(&rest.to_ascii_lowercase()),
+        false => out.push_str(rest),
+      }
+    }
+  }
+  match out.starts_with(|c: char| c.is_ascii_digit()) {
+    true => format!("T{out}"),
+    false => out,
+  }
+}
+
+/// `camelCase` of a name in any case, eg: `request_id` => `requestId`
+pub fn camel(name: &str) -> String {
+  let pascal = pascal(name);
+  let mut chars = pascal.chars();
+  match chars.next() {
+    Some(first) => first.to_ascii_lowercase().to_string() + chars.as_str(),
+    None => pascal,
+  }
+}
+
+/// `snake_case` of a name in any case, eg: `GetUsersId` => `get_users_id`
+pub fn snake(name: &str) -> String {
+  let mut out = String::new();
+  for c in pascal(name).chars() {
+    if c.is_ascii_uppercase() && !out.is_empty() {
+      out.push('_');
+    }
+    out.push(c.to_ascii_lowercase());
+  }
+  out
+}
+
+/// `SCREAMING_SNAKE_CASE` of a name in any case, eg: enum constants
+pub fn screaming(name: &str) -> String {
+  snake(name).to_ascii_uppercase()
+}
+
+/// A string literal, json escapes are valid in the generated languages
+pub(crate) fn quote(value: &str) -> String {
+  serde_json::to_string(value).expect("error quoting string")
+}
+
+#[cfg(test)]
+mod tests {
+  use indexmap::IndexMap;
+  use serde_json::json;
+
+  use super::*;
+  use crate::ir::{Def, DefKind, Field, Output, Type};
+
+  /// A struct with keyword and non identifier field names and an enum with keyword values
+  fn api() -> Api {
+    let fields = vec![
+      Field::new("class", Type::String, true),
+      Field::new("content-type", Type::String, false),
+      Field::new("2fa", Type::Bool, true),
+    ];
+    let types = IndexMap::from([
+      (String::from("Headers"), Def { doc: None, kind: DefKind::Struct(fields) }),
+      (String::from("Keyword"), Def { doc: None, kind: DefKind::Enum(vec![String::from("in"), String::from("true")]) }),
+    ]);
+    let error = Output {
+      ty: Type::Named(String::from("Headers")),
+      schema: json!({}),
+      openapi: schemars::Schema::default(),
+      typescript: String::from("Headers"),
+    };
+    Api { types, operations: vec![], error }
+  }
+
+  fn generate(generator: &dyn Generator) -> String {
+    generator.generate(&api()).remove(0).contents
+  }
+
+  #[test]
+  fn cases() {
+    assert_eq!(pascal("RESOURCE_NOT_FOUND"), "ResourceNotFound");
+    assert_eq!(pascal("Pagination Limit"), "PaginationLimit");
+    assert_eq!(pascal("2fa"), "T2fa");
+    assert_eq!(camel("content-type"), "contentType");
+    assert_eq!(snake("GetUsersId"), "get_users_id");
+    assert_eq!(screaming("content-type"), "CONTENT_TYPE");
+  }
+
+  #[test]
+  fn python_identifiers() {
+    let python = generate(&python::Python);
+    for expected in [
+      "    model_config = ConfigDict(populate_by_name=True)\n",
+      "    class_: str = Field(alias=\"class\")\n",
+      "    content_type: Optional[str] = Field(default=None, alias=\"content-type\")\n",
+      "    field_2fa: bool = Field(alias=\"2fa\")\n",
+      "    IN = \"in\"\n",
+      "    TRUE = \"true\"\n",
+    ] {
+      assert!(python.contains(expected), "missing `{expected}` in\n{python}");
+    }
+  }
+
+  #[test]
+  fn kotlin_identifiers() {
+    let kotlin = generate(&kotlin::Kotlin::default());
+    for expected in [
+      "    val `class`: String,\n",
+      "    @SerialName(\"content-type\")\n    val contentType: String? = null,\n",
+      "    @SerialName(\"2fa\")\n    val t2fa: Boolean,\n",
+      "    @SerialName(\"in\")\n    IN,\n",
+    ] {
+      assert!(kotlin.contains(expected), "missing `{expected}` in\n{kotlin}");
+    }
+  }
+
+  #[test]
+  fn swift_identifiers() {
+    let swift = generate(&swift::Swift);
+    for expected in [
+      "    public var `class`: String\n",
+      "    public var contentType: String?\n",
+      "        case `class`\n",
+      "        case contentType = \"content-type\"\n",
+      "        case t2fa = \"2fa\"\n",
+      "        self.class = `class`\n",
+      "    case `in` = \"in\"\n",
+      "    case `true` = \"true\"\n",
+    ] {
+      assert!(swift.contains(expected), "missing `{expected}` in\n{swift}");
+    }
+  }
+}
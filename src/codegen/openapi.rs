@@ -0,0 +1,237 @@
+use std::path::PathBuf;
+
+use serde_json::{json, Value};
+
+use super::{GeneratedFile, Generator};
+use crate::ir::Api;
+
+/// The OpenAPI 3.0 spec in `openapi.json`, or `openapi.yaml` if `yaml` is set
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OpenApi {
+  pub yaml: bool,
+}
+
+impl Generator for OpenApi {
+  fn name(&self) -> &'static str {
+    match self.yaml {
+      true => "openapi-yaml",
+      false => "openapi-json",
+    }
+  }
+
+  fn generate(&self, api: &Api) -> Vec<GeneratedFile> {
+    let (path, contents) = match self.yaml {
+      true => ("openapi.yaml", crate::yaml::to_string(&spec(api))),
+      false => ("openapi.json", crate::export::json_text(&spec(api))),
+    };
+    vec![GeneratedFile { path: PathBuf::from(path), contents }]
+  }
+}
+
+/// The spec of every operation, see [`crate::registry::Registry::openapi_spec`]
+pub fn spec(api: &Api) -> Value {
+  let schemas = json!({
+    "ErrorPayload": api.error.openapi,
+  });
+
+  let mut paths = json!({});
+
+  for operation in &api.operations {
+    let mut endpoint = json!({});
+
+    if let Some(summary) = &operation.summary {
+      endpoint["summary"] = json!(summary);
+    }
+    if let Some(description) = &operation.description {
+      endpoint["description"] = json!(description);
+    }
+    if operation.deprecated {
+      endpoint["deprecated"] = json!(true);
+    }
+
+    let mut parameters = vec![];
+
+    if let Some(params) = &operation.params {
+      let properties = match params.openapi.as_value()["properties"].as_object() {
+        Some(properties) => properties,
+        None => panic!("params schema properties must be an object"),
+      };
+
+      for (name, param) in properties {
+        parameters.push(json!({
+          "in": "path",
+          "name": name,
+          "required": true,
+          "schema": param,
+        }));
+      }
+    }
+
+    if let Some(query) = &operation.query {
+      let properties = match query.openapi.as_value()["properties"].as_object() {
+        Some(properties) => properties,
+        None => panic!("query schema properties must be an object"),
+      };
+
+      let required = query.openapi.as_value()["required"].as_array().cloned().unwrap_or_default();
+      for (name, param) in properties {
+        parameters.push(json!({
+          "in": "query",
+          "name": name,
+          "required": required.iter().any(|field| field == name),
+          "style": "deepObject",
+          "schema": param,
+        }));
+      }
+    }
+
+    if let Some(policy) = &operation.idempotency {
+      parameters.push(json!({
+        "in": "header",
+        "name": "Idempotency-Key",
+        "required": policy.required,
+        "description": format!("Retries with the same key and payload replay the first response for {} seconds", policy.ttl.as_secs()),
+        "schema": { "type": "string", "minLength": 1, "maxLength": 255 },
+      }));
+    }
+
+    if !parameters.is_empty() {
+      endpoint["parameters"] = json!(parameters);
+    }
+
+    if let Some(payload) = &operation.payload {
+      endpoint["requestBody"] = json!({
+        "content": {
+          "application/json": {
+            "schema": payload.openapi,
+          }
+        }
+      })
+    }
+
+    endpoint["responses"] = json!({
+      "200": {
+        "description": "A successful response",
+        "content": {
+          "application/json": {
+            "schema": operation.output.openapi,
+          }
+        }
+      },
+
+      "4XX": error_response("A client error"),
+      "5XX": error_response("A server error"),
+    });
+
+    if let Some(timeout) = operation.timeout {
+      endpoint["responses"]["504"] =
+        error_response(&format!("The request took longer than {} seconds", timeout.as_secs_f64()));
+    }
+
+    if let Some(rate_limit) = operation.rate_limit {
+      let mut response = error_response(&format!(
+        "Too many requests, the limit is {} requests every {} seconds",
+        rate_limit.requests,
+        rate_limit.per.as_secs_f64()
+      ));
+      response["headers"] = json!({
+        "Retry-After": {
+          "description": "Seconds until a new request is allowed",
+          "schema": { "type": "integer" },
+        },
+        "RateLimit-Limit": {
+          "description": "Requests allowed in the current window",
+          "schema": { "type": "integer" },
+        },
+        "RateLimit-Remaining": {
+          "description": "Requests remaining in the current window",
+          "schema": { "type": "integer" },
+        },
+        "RateLimit-Reset": {
+          "description": "Seconds until the window is reset",
+          "schema": { "type": "integer" },
+        },
+      });
+      endpoint["responses"]["429"] = response;
+    }
+
+    let path = crate::path::to_openapi(&operation.path);
+    paths[path][operation.method.to_ascii_lowercase()] = endpoint;
+  }
+
+  let openapi = json!("3.0.3");
+
+  let info = json!({
+    "title": "Some API",
+    "summary": "This is the Some API summary",
+    "description": "This is the Some API description",
+    "termsOfService": "http://example.test/terms/",
+    "contact": {
+      "name": "Some API Support",
+      "url": "http://example.test",
+      "email": "support@example.test",
+    },
+    // version of the API
+    "version": "0.1.0",
+    "license": {
+      "name": "Apache 2.0",
+      "identifier": "Apache-2.0",
+      // url is mutually exclusive with identifier
+      // "url": "https://www.apache.org/licenses/LICENSE-2.0.html",
+    },
+  });
+
+  // can interpolate variables in `servers` see https://swagger.io/specification/
+
+  let servers = json!([
+    {
+      "url": "/",
+      "description": "This server",
+    },
+
+    // {
+    //   "url": "https://{username}.gigantic-server.com:{port}/{basePath}",
+    //   "description": "The production API server",
+    //   "variables": {
+    //     "username": {
+    //       "default": "demo",
+    //       "description": "this value is assigned by the service provider, in this example `gigantic-server.com`"
+    //     },
+    //     "port": {
+    //       "enum": [
+    //         "8443",
+    //         "443"
+    //       ],
+    //       "default": "8443"
+    //     },
+    //     "basePath": {
+    //       "default": "v2"
+    //     }
+    //   }
+    // }
+  ]);
+
+  json!({
+    "openapi": openapi,
+    "info": info,
+    "servers": servers,
+    "paths": paths,
+    "components": {
+      "schemas": schemas,
+    }
+  })
+}
+
+/// A response with the error payload
+fn error_response(description: &str) -> Value {
+  json!({
+    "description": description,
+    "content": {
+      "application/json": {
+        "schema": {
+          "$ref": "#/components/schemas/ErrorPayload",
+        }
+      }
+    }
+  })
+}
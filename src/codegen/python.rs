@@ -0,0 +1,288 @@
+use std::path::PathBuf;
+
+use super::{quote, screaming, snake, GeneratedFile, Generator};
+use crate::ir::{Api, DefKind, Field, Type};
+
+/// Pydantic models and an httpx client in a single `api.py`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Python;
+
+impl Generator for Python {
+  fn name(&self) -> &'static str {
+    "python"
+  }
+
+  fn generate(&self, api: &Api) -> Vec<GeneratedFile> {
+    vec![GeneratedFile { path: PathBuf::from("api.py"), contents: render(api) }]
+  }
+}
+
+const KEYWORDS: &[&str] = &[
+  "False", "None", "True", "and", "as", "assert", "async", "await", "break", "class", "continue", "def", "del", "elif",
+  "else", "except", "finally", "for", "from", "global", "if", "import", "in", "is", "lambda", "nonlocal", "not", "or",
+  "pass", "raise", "return", "try", "while", "with", "yield",
+];
+
+fn render(api: &Api) -> String {
+  let mut out = String::from(
+    r#"# this file is auto generated by its Rust definition, do not edit manually
+
+from __future__ import annotations
+
+import re
+from enum import Enum
+from typing import Annotated, Any, Literal, Optional, Union
+from urllib.parse import quote
+
+import httpx
+from pydantic import BaseModel, ConfigDict, Field, TypeAdapter
+"#,
+  );
+
+  for (name, def) in &api.types {
+    out.push_str("\n\n");
+    match &def.kind {
+      DefKind::Struct(fields) => out.push_str(&class(name, def.doc.as_deref(), fields)),
+      DefKind::Enum(values) => {
+        out.push_str(&format!("class {name}(str, Enum):\n"));
+        out.push_str(&docstring(def.doc.as_deref(), 1, true));
+        for value in values {
+          out.push_str(&format!("    {} = {}\n", identifier(&screaming(value)), quote(value)));
+        }
+      }
+      DefKind::Tagged { tag, shared, variants } => {
+        let mut names = vec![];
+        for (value, fields) in variants {
+          let variant = format!("{name}{}", super::pascal(value));
+          let tag = Field::new(tag.clone(), Type::Literal(value.clone()), true);
+          let fields = std::iter::once(tag).chain(shared.iter().cloned()).chain(fields.iter().cloned()).collect::<Vec<_>>();
+          out.push_str(&class(&variant, None, &fields));
+          out.push_str("\n\n");
+          names.push(variant);
+        }
+        out.push_str(&format!(
+          "{name} = Annotated[Union[{}], Field(discriminator={})]\n",
+          names.join(", "),
+          quote(tag)
+        ));
+        out.push_str(&docstring(def.doc.as_deref(), 0, false));
+      }
+    }
+  }
+
+  out.push_str("\n\n");
+  alias(&mut out, "ErrorPayload", &api.error.ty);
+  for operation in &api.operations {
+    for (suffix, input) in operation.inputs() {
+      if let Some(input) = input {
+        alias(&mut out, &format!("{}{suffix}", operation.name), &input.ty);
+      }
+    }
+    alias(&mut out, &format!("{}Output", operation.name), &operation.output.ty);
+  }
+
+  out.push_str(CLIENT);
+
+  for operation in &api.operations {
+    let mut args = vec![String::from("self")];
+    let mut call = vec![quote(&operation.method), quote(&operation.path), format!("{}Output", operation.name)];
+    let inputs = [("params", "Params", &operation.params), ("query", "Query", &operation.query), ("payload", "Payload", &operation.payload)];
+    let mut keyword = false;
+    for (arg, suffix, input) in inputs {
+      if let Some(input) = input {
+        let ty = format!("{}{suffix}", operation.name);
+        match input.required {
+          true if !keyword => args.push(format!("{arg}: {ty}")),
+          _ => {
+            if !keyword {
+              args.push(String::from("*"));
+              keyword = true;
+            }
+            match input.required {
+              true => args.push(format!("{arg}: {ty}")),
+              false => args.push(format!("{arg}: Optional[{ty}] = None")),
+            }
+          }
+        }
+        call.push(format!("{arg}={arg}"));
+      }
+    }
+
+    out.push_str(&format!(
+      "\n    def {}({}) -> {}Output:\n",
+      snake(&operation.name),
+      args.join(", "),
+      operation.name
+    ));
+    let mut doc = operation.doc().unwrap_or_default();
+    if operation.deprecated {
+      doc = format!("{doc}\n\nDeprecated").trim_start().to_string();
+    }
+    out.push_str(&docstring((!doc.is_empty()).then_some(doc.as_str()), 2, false));
+    out.push_str(&format!("        return self._request({})\n", call.join(", ")));
+  }
+
+  out
+}
+
+fn class(name: &str, doc: Option<&str>, fields: &[Field]) -> String {
+  let mut out = format!("class {name}(BaseModel):\n");
+  out.push_str(&docstring(doc, 1, true));
+
+  let aliased = fields.iter().any(|field| identifier(&field.name) != field.name);
+  if aliased {
+    out.push_str("    model_config = ConfigDict(populate_by_name=True)\n\n");
+  }
+
+  for field in fields {
+    let attribute = identifier(&field.name);
+    let ty = match (&field.ty, field.required) {
+      (Type::Nullable(_) | Type::Any, _) | (_, true) => ty(&field.ty),
+      (ty, false) => format!("Optional[{}]", self::ty(ty)),
+    };
+    let default = match (field.required, attribute != field.name) {
+      (true, false) => String::new(),
+      (true, true) => format!(" = Field(alias={})", quote(&field.name)),
+      (false, false) => String::from(" = None"),
+      (false, true) => format!(" = Field(default=None, alias={})", quote(&field.name)),
+    };
+    out.push_str(&format!("    {attribute}: {ty}{default}\n"));
+    out.push_str(&docstring(field.doc.as_deref(), 1, false));
+  }
+
+  if fields.is_empty() {
+    out.push_str("    pass\n");
+  }
+  out
+}
+
+fn alias(out: &mut String, name: &str, ty: &Type) {
+  if *ty != Type::Named(name.to_string()) {
+    out.push_str(&format!("{name} = {}\n", self::ty(ty)));
+  }
+}
+
+fn ty(ty: &Type) -> String {
+  match ty {
+    Type::Any => String::from("Any"),
+    Type::Bool => String::from("bool"),
+    Type::Int => String::from("int"),
+    Type::Float => String::from("float"),
+    Type::String => String::from("str"),
+    Type::Literal(value) => format!("Literal[{}]", quote(value)),
+    Type::Nullable(ty) => format!("Optional[{}]", self::ty(ty)),
+    Type::List(ty) => format!("list[{}]", self::ty(ty)),
+    Type::Map(ty) => format!("dict[str, {}]", self::ty(ty)),
+    Type::Union(types) => format!("Union[{}]", types.iter().map(self::ty).collect::<Vec<_>>().join(", ")),
+    Type::Named(name) => name.clone(),
+  }
+}
+
+/// A valid attribute name for a json name
+fn identifier(name: &str) -> String {
+  let mut out = name.chars().map(|c| if c.is_ascii_alphanumeric() || c == '_' { c } else { '_' }).collect::<String>();
+  // pydantic treats attributes with a leading underscore as private
+  if out.is_empty() || out.starts_with(|c: char| c.is_ascii_digit() || c == '_') {
+    out.insert_str(0, "field_");
+  }
+  if KEYWORDS.contains(&out.as_str()) {
+    out.push('_');
+  }
+  out
+}
+
+/// A docstring at `indent` levels, classes get a blank line after it
+fn docstring(doc: Option<&str>, indent: usize, class: bool) -> String {
+  let doc = match doc {
+    // backslashes and quotes would escape or end the docstring
+    Some(doc) => {
+      let mut doc = doc.replace('\\', "\\\\").replace("\"\"\"", "\\\"\\\"\\\"");
+      if doc.ends_with('"') {
+        doc.insert(doc.len() - 1, '\\');
+      }
+      doc
+    }
+    None => return String::new(),
+  };
+  let pad = "    ".repeat(indent);
+  let mut out = match doc.lines().count() {
+    1 => format!("{pad}\"\"\"{doc}\"\"\"\n"),
+    _ => {
+      let lines = doc.lines().map(|line| if line.is_empty() { String::new() } else { format!("{pad}{line}") });
+      format!("{pad}\"\"\"\n{}\n{pad}\"\"\"\n", lines.collect::<Vec<_>>().join("\n"))
+    }
+  };
+  if class {
+    out.push('\n');
+  }
+  out
+}
+
+const CLIENT: &str = r#"
+
+class ApiError(Exception):
+    """An error response, `payload` holds the error kind and message"""
+
+    def __init__(self, status: int, payload: ErrorPayload) -> None:
+        super().__init__(f"request failed with status {status}")
+        self.status = status
+        self.payload = payload
+
+
+def _dump(value: Any) -> Any:
+    return TypeAdapter(type(value)).dump_python(value, mode="json", by_alias=True, exclude_none=True)
+
+
+def _fill_path(path: str, params: dict[str, Any]) -> str:
+    def replace(match: re.Match[str]) -> str:
+        # wildcards keep their slashes
+        safe = "/" if match.group(0).startswith("*") else ""
+        return quote(str(params[match.group(1)]), safe=safe)
+
+    return re.sub(r"[:*]([A-Za-z0-9_]+)", replace, path)
+
+
+def _query_items(value: Any, key: str = "") -> list[tuple[str, str]]:
+    """Nested values use the bracket notation parsed by the server, eg: `filter[name]=x&ids[0]=1`"""
+    if value is None:
+        return []
+    if isinstance(value, dict):
+        items = [_query_items(item, f"{key}[{name}]" if key else name) for name, item in value.items()]
+        return [item for nested in items for item in nested]
+    if isinstance(value, list):
+        items = [_query_items(item, f"{key}[{i}]") for i, item in enumerate(value)]
+        return [item for nested in items for item in nested]
+    if isinstance(value, bool):
+        return [(key, "true" if value else "false")]
+    return [(key, str(value))]
+
+
+class Client:
+    """A client for every endpoint of the API"""
+
+    def __init__(self, base_url: str, client: Optional[httpx.Client] = None) -> None:
+        self.base_url = base_url.rstrip("/")
+        self.client = client or httpx.Client()
+
+    def _request(
+        self,
+        method: str,
+        path: str,
+        output: Any,
+        params: Any = None,
+        query: Any = None,
+        payload: Any = None,
+    ) -> Any:
+        url = self.base_url + _fill_path(path, _dump(params) if params is not None else {})
+        res = self.client.request(
+            method,
+            url,
+            params=_query_items(_dump(query)) if query is not None else None,
+            json=_dump(payload) if payload is not None else None,
+        )
+        if res.is_error:
+            raise ApiError(res.status_code, TypeAdapter(ErrorPayload).validate_python(res.json()))
+        if res.status_code == 204 or method == "HEAD":
+            return None
+        return TypeAdapter(output).validate_python(res.json())
+"#;
@@ -0,0 +1,252 @@
+use std::path::PathBuf;
+
+use super::{camel, GeneratedFile, Generator};
+use crate::ir::{Api, DefKind, Field, Type};
+
+/// Codable structs and enums in a single `Api.swift`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Swift;
+
+impl Generator for Swift {
+  fn name(&self) -> &'static str {
+    "swift"
+  }
+
+  fn generate(&self, api: &Api) -> Vec<GeneratedFile> {
+    vec![GeneratedFile { path: PathBuf::from("Api.swift"), contents: render(api) }]
+  }
+}
+
+const KEYWORDS: &[&str] = &[
+  "Any", "Self", "Type", "as", "associatedtype", "break", "case", "catch", "class", "continue", "default", "defer",
+  "deinit", "do", "else", "enum", "extension", "fallthrough", "false", "fileprivate", "for", "func", "guard", "if",
+  "import", "in", "init", "inout", "internal", "is", "let", "nil", "open", "operator", "private", "protocol", "public",
+  "repeat", "rethrows", "return", "self", "static", "struct", "subscript", "super", "switch", "throw", "throws", "true",
+  "try", "typealias", "var", "where", "while",
+];
+
+fn render(api: &Api) -> String {
+  let mut out = String::from(
+    "// this file is auto generated by its Rust definition, do not edit manually\n\nimport Foundation\n",
+  );
+
+  for (name, def) in &api.types {
+    out.push('\n');
+    out.push_str(&doc(def.doc.as_deref(), 0));
+    match &def.kind {
+      DefKind::Struct(fields) => out.push_str(&structure(name, fields)),
+      DefKind::Enum(values) => {
+        out.push_str(&format!("public enum {name}: String, Codable, Hashable, CaseIterable {{\n"));
+        for value in values {
+          out.push_str(&format!("    case {} = {}\n", identifier(&camel(value)), quote(value)));
+        }
+        out.push_str("}\n");
+      }
+      DefKind::Tagged { tag, shared, variants } => {
+        out.push_str(&tagged(name, tag, variants.iter().map(|(value, _)| value.as_str()).collect()));
+        for (value, fields) in variants {
+          out.push('\n');
+          let fields = shared.iter().chain(fields).cloned().collect::<Vec<_>>();
+          out.push_str(&structure(&format!("{name}{}", super::pascal(value)), &fields));
+        }
+      }
+    }
+  }
+
+  out.push('\n');
+  alias(&mut out, "ErrorPayload", &api.error.ty);
+  for operation in &api.operations {
+    for (suffix, input) in operation.inputs() {
+      if let Some(input) = input {
+        alias(&mut out, &format!("{}{suffix}", operation.name), &input.ty);
+      }
+    }
+    alias(&mut out, &format!("{}Output", operation.name), &operation.output.ty);
+  }
+
+  if api.uses(|ty| matches!(ty, Type::Any | Type::Union(_))) {
+    out.push_str(JSON_VALUE);
+  }
+
+  out
+}
+
+fn structure(name: &str, fields: &[Field]) -> String {
+  let mut out = format!("public struct {name}: Codable, Hashable {{\n");
+  for field in fields {
+    out.push_str(&doc(field.doc.as_deref(), 1));
+    out.push_str(&format!("    public var {}: {}\n", identifier(&camel(&field.name)), field_ty(field)));
+  }
+
+  if fields.iter().any(|field| camel(&field.name) != field.name) {
+    out.push_str("\n    enum CodingKeys: String, CodingKey {\n");
+    for field in fields {
+      match camel(&field.name) == field.name {
+        true => out.push_str(&format!("        case {}\n", identifier(&field.name))),
+        false => out.push_str(&format!("        case {} = {}\n", identifier(&camel(&field.name)), quote(&field.name))),
+      }
+    }
+    out.push_str("    }\n");
+  }
+
+  // the memberwise init of a public struct is internal
+  let args = fields
+    .iter()
+    .map(|field| {
+      let default = if field_ty(field).ends_with('?') { " = nil" } else { "" };
+      format!("{}: {}{default}", identifier(&camel(&field.name)), field_ty(field))
+    })
+    .collect::<Vec<_>>();
+  out.push_str(&format!("\n    public init({}) {{\n", args.join(", ")));
+  for field in fields {
+    let property = camel(&field.name);
+    out.push_str(&format!("        self.{property} = {}\n", identifier(&property)));
+  }
+  out.push_str("    }\n}\n");
+  out
+}
+
+/// An enum with a case per variant, the tag is decoded first then the variant struct from the same object
+fn tagged(name: &str, tag: &str, values: Vec<&str>) -> String {
+  let case = |value: &str| identifier(&camel(value));
+  let mut out = format!("public enum {name}: Codable, Hashable {{\n");
+  for value in &values {
+    out.push_str(&format!("    case {}({name}{})\n", case(value), super::pascal(value)));
+  }
+
+  out.push_str(&format!(
+    "\n    private enum TagKeys: String, CodingKey {{\n        case tag = {}\n    }}\n",
+    quote(tag)
+  ));
+
+  out.push_str("\n    public init(from decoder: Decoder) throws {\n");
+  out.push_str("        let container = try decoder.container(keyedBy: TagKeys.self)\n");
+  out.push_str("        switch try container.decode(String.self, forKey: .tag) {\n");
+  for value in &values {
+    out.push_str(&format!(
+      "        case {}: self = .{}(try {name}{}(from: decoder))\n",
+      quote(value),
+      case(value),
+      super::pascal(value)
+    ));
+  }
+  out.push_str(&format!(
+    "        case let other: throw DecodingError.dataCorruptedError(forKey: .tag, in: container, debugDescription: \"unknown {} \\(other)\")\n",
+    tag.replace(['"', '\\'], "")
+  ));
+  out.push_str("        }\n    }\n");
+
+  out.push_str("\n    public func encode(to encoder: Encoder) throws {\n");
+  out.push_str("        var container = encoder.container(keyedBy: TagKeys.self)\n");
+  out.push_str("        switch self {\n");
+  for value in &values {
+    out.push_str(&format!("        case .{}(let value):\n", case(value)));
+    out.push_str(&format!("            try container.encode({}, forKey: .tag)\n", quote(value)));
+    out.push_str("            try value.encode(to: encoder)\n");
+  }
+  out.push_str("        }\n    }\n}\n");
+  out
+}
+
+fn alias(out: &mut String, name: &str, ty: &Type) {
+  if *ty != Type::Named(name.to_string()) {
+    out.push_str(&format!("public typealias {name} = {}\n", self::ty(ty)));
+  }
+}
+
+fn field_ty(field: &Field) -> String {
+  match (&field.ty, field.required) {
+    (Type::Nullable(_), _) | (_, true) => ty(&field.ty),
+    (ty, false) => format!("{}?", self::ty(ty)),
+  }
+}
+
+fn ty(ty: &Type) -> String {
+  match ty {
+    Type::Any | Type::Union(_) => String::from("JSONValue"),
+    Type::Bool => String::from("Bool"),
+    Type::Int => String::from("Int64"),
+    Type::Float => String::from("Double"),
+    Type::String | Type::Literal(_) => String::from("String"),
+    Type::Nullable(ty) => match self::ty(ty) {
+      ty if ty.ends_with('?') => ty,
+      ty => format!("{ty}?"),
+    },
+    Type::List(ty) => format!("[{}]", self::ty(ty)),
+    Type::Map(ty) => format!("[String: {}]", self::ty(ty)),
+    Type::Named(name) => name.clone(),
+  }
+}
+
+fn identifier(name: &str) -> String {
+  match KEYWORDS.contains(&name) {
+    true => format!("`{name}`"),
+    false => name.to_string(),
+  }
+}
+
+/// A string literal, swift uses `\u{...}` for unicode escapes
+fn quote(value: &str) -> String {
+  let mut out = String::from("\"");
+  for c in value.chars() {
+    match c {
+      '"' => out.push_str("\\\""),
+      '\\' => out.push_str("\\\\"),
+      '\n' => out.push_str("\\n"),
+      '\r' => out.push_str("\\r"),
+      '\t' => out.push_str("\\t"),
+      c if c.is_control() => out.push_str(&format!("\\u{{{:x}}}", c as u32)),
+      c => out.push(c),
+    }
+  }
+  out.push('"');
+  out
+}
+
+fn doc(doc: Option<&str>, indent: usize) -> String {
+  let pad = "    ".repeat(indent);
+  doc
+    .map(|doc| doc.lines().map(|line| format!("{pad}///{}{line}\n", if line.is_empty() { "" } else { " " })).collect())
+    .unwrap_or_default()
+}
+
+const JSON_VALUE: &str = r#"
+/// Any json value, used for untyped values and untagged unions
+public enum JSONValue: Codable, Hashable {
+    case null
+    case bool(Bool)
+    case number(Double)
+    case string(String)
+    case array([JSONValue])
+    case object([String: JSONValue])
+
+    public init(from decoder: Decoder) throws {
+        let container = try decoder.singleValueContainer()
+        if container.decodeNil() {
+            self = .null
+        } else if let value = try? container.decode(Bool.self) {
+            self = .bool(value)
+        } else if let value = try? container.decode(Double.self) {
+            self = .number(value)
+        } else if let value = try? container.decode(String.self) {
+            self = .string(value)
+        } else if let value = try? container.decode([JSONValue].self) {
+            self = .array(value)
+        } else {
+            self = .object(try container.decode([String: JSONValue].self))
+        }
+    }
+
+    public func encode(to encoder: Encoder) throws {
+        var container = encoder.singleValueContainer()
+        switch self {
+        case .null: try container.encodeNil()
+        case .bool(let value): try container.encode(value)
+        case .number(let value): try container.encode(value)
+        case .string(let value): try container.encode(value)
+        case .array(let value): try container.encode(value)
+        case .object(let value): try container.encode(value)
+        }
+    }
+}
+"#;
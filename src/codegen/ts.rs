@@ -0,0 +1,195 @@
+use std::path::PathBuf;
+
+use serde_json::json;
+use shape::{Shape, ToTypescript};
+
+use super::{GeneratedFile, Generator};
+use crate::ir::Api;
+use crate::ts::{documented, jsdoc, type_doc};
+
+/// The `Api` definitions used by typed clients in `api.ts`, with a small fetch based client in `client.ts` if `client` is set
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Ts {
+  pub client: bool,
+}
+
+impl Generator for Ts {
+  fn name(&self) -> &'static str {
+    match self.client {
+      true => "ts-client",
+      false => "ts",
+    }
+  }
+
+  fn generate(&self, api: &Api) -> Vec<GeneratedFile> {
+    let (path, contents) = match self.client {
+      true => ("client.ts", format!("{}\n\n{CLIENT}", definitions(api))),
+      false => ("api.ts", definitions(api)),
+    };
+    vec![GeneratedFile { path: PathBuf::from(path), contents: crate::export::text(&contents) }]
+  }
+}
+
+/// The `Api` object with the method, path and types of every endpoint, see [`crate::registry::Registry::ts_definitions`]
+pub fn definitions(api: &Api) -> String {
+  let mut def = String::new();
+
+  #[derive(Shape)]
+  #[allow(clippy::upper_case_acronyms)]
+  #[allow(unused)]
+  enum Method {
+    HEAD,
+    GET,
+    POST,
+    PUT,
+    PATCH,
+    DELETE,
+    OPTIONS,
+    CONNECT,
+    TRACE,
+  }
+
+  let ts_method = Method::shape(&shape::ShapeOptions::for_serialize()).to_typescript();
+
+  let error_payload_src = documented(&api.error.typescript, api.error.openapi.as_value(), 0);
+  let error_payload_doc = type_doc(api.error.openapi.as_value()).map(|doc| jsdoc(&doc, 0)).unwrap_or_default();
+
+  def.push_str(&format!(
+r#"/// this file is auto generated by its Rust definition, do not edit manually
+
+export type Method = {ts_method};
+type _Method = Method;
+
+export type Empty = Record<string, never>;
+
+export type Endpoint<
+  Method extends _Method,
+  Path extends string,
+  Params,
+  Query,
+  Payload,
+  Output,
+> = {{
+  method: Method;
+  path: Path;
+  // this $ types are never constructed, only used as a template
+  $params?: Params;
+  $query?: Query;
+  $payload?: Payload;
+  $output?: Output;
+}};
+
+type ApiDefinition = Partial<Record<string, Partial<Record<Method, Endpoint<any, any, any, any, any, any>>>>>;
+
+{error_payload_doc}export type ErrorPayload = {error_payload_src};
+
+export const Api = {{"#));
+
+  let mut operations = api.operations.iter().peekable();
+  while let Some(operation) = operations.next() {
+    let quoted_path = serde_json::to_string(&json!(operation.path)).unwrap();
+    def.push_str(&format!("\n  {quoted_path}: {{"));
+
+    // the operations of a path are next to each other
+    for operation in std::iter::once(operation).chain(std::iter::from_fn(|| operations.next_if(|next| next.path == operation.path))) {
+      let quoted_method = serde_json::to_string(&json!(operation.method)).unwrap();
+
+      let mut doc = operation.summary.iter().chain(&operation.description).cloned().collect::<Vec<_>>();
+      // the output type is inlined in the endpoint, its own doc is kept here
+      if let Some(output) = operation.output.openapi.as_value().get("description").and_then(|v| v.as_str()) {
+        doc.push(format!("@returns {output}"));
+      }
+      if operation.deprecated {
+        doc.push(String::from("@deprecated"));
+      }
+      if !doc.is_empty() {
+        def.push('\n');
+        def.push_str(jsdoc(&doc.join("\n\n"), 2).trim_end());
+      }
+
+      let arg = |input: Option<&crate::ir::Input>| match input {
+        Some(input) => documented(&input.typescript, input.openapi.as_value(), 3),
+        None => String::from("Empty"),
+      };
+
+      def.push_str(&format!(
+        "\n    {quoted_method}: {{ method: {quoted_method}, path: {quoted_path} }} as Endpoint<\n      {quoted_method},\n      {quoted_path},\n      {},\n      {},\n      {},\n      {}\n    >,",
+        arg(operation.params.as_ref()),
+        arg(operation.query.as_ref()),
+        arg(operation.payload.as_ref()),
+        documented(&operation.output.typescript, operation.output.openapi.as_value(), 3),
+      ));
+    }
+    def.push_str("\n  },");
+  }
+
+  def.push_str("\n} satisfies ApiDefinition;\n");
+  def
+}
+
+const CLIENT: &str = r#"export type ApiResult<Output> =
+  | { ok: true; status: number; data: Output }
+  | { ok: false; status: number; error: ErrorPayload["error"] };
+
+export type CallOptions<E> = E extends Endpoint<any, any, infer Params, infer Query, infer Payload, any>
+  ? (Params extends Empty ? { params?: Params } : { params: Params })
+    & (Query extends Empty ? { query?: Query } : { query: Query })
+    & (Payload extends Empty ? { payload?: Payload } : { payload: Payload })
+    & { headers?: Record<string, string>; signal?: AbortSignal }
+  : never;
+
+type OutputOf<E> = E extends Endpoint<any, any, any, any, any, infer Output> ? Output : never;
+
+const fillPath = (path: string, params: Record<string, unknown> = {}): string =>
+  path.replace(/[:*]([A-Za-z0-9_]+)/g, (_, name: string) => {
+    const value = String(params[name]);
+    return path.includes(`*${name}`) ? value.split("/").map(encodeURIComponent).join("/") : encodeURIComponent(value);
+  });
+
+// nested values use the bracket notation parsed by the server, eg: `filter[name]=x&ids[0]=1`
+const queryString = (query: Record<string, unknown> = {}): string => {
+  const parts: string[] = [];
+  const add = (key: string, value: unknown) => {
+    if (value === undefined || value === null) {
+      return;
+    }
+    if (Array.isArray(value)) {
+      value.forEach((item, i) => add(`${key}[${i}]`, item));
+    } else if (typeof value === "object") {
+      for (const [name, item] of Object.entries(value)) {
+        add(`${key}[${encodeURIComponent(name)}]`, item);
+      }
+    } else {
+      parts.push(`${key}=${encodeURIComponent(String(value))}`);
+    }
+  };
+  for (const [name, value] of Object.entries(query)) {
+    add(encodeURIComponent(name), value);
+  }
+  return parts.length === 0 ? "" : `?${parts.join("&")}`;
+};
+
+export const createClient = (baseUrl: string, fetchImpl: typeof fetch = fetch) =>
+  async <E extends Endpoint<any, any, any, any, any, any>>(
+    endpoint: E,
+    options: CallOptions<E>,
+  ): Promise<ApiResult<OutputOf<E>>> => {
+    const { params, query, payload, headers, signal } = options as {
+      params?: Record<string, unknown>;
+      query?: Record<string, unknown>;
+      payload?: unknown;
+      headers?: Record<string, string>;
+      signal?: AbortSignal;
+    };
+    const res = await fetchImpl(baseUrl + fillPath(endpoint.path, params) + queryString(query), {
+      method: endpoint.method,
+      headers: payload === undefined ? headers : { "content-type": "application/json", ...headers },
+      body: payload === undefined ? undefined : JSON.stringify(payload),
+      signal,
+    });
+    const body = res.status === 204 || endpoint.method === "HEAD" ? undefined : await res.json();
+    return res.ok
+      ? { ok: true, status: res.status, data: body as OutputOf<E> }
+      : { ok: false, status: res.status, error: (body as ErrorPayload).error };
+  };
+"#;
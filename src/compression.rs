@@ -0,0 +1,374 @@
+use std::io::{Read, Write};
+
+use axum::{
+  body::{Body, HttpBody},
+  extract::Request,
+  http::{
+    header::{ACCEPT_ENCODING, CONTENT_ENCODING, CONTENT_LENGTH, ETAG, VARY},
+    HeaderMap, HeaderValue,
+  },
+  response::Response,
+};
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum Encoding {
+  Gzip,
+  Deflate,
+  Br,
+  Zstd,
+}
+
+impl Encoding {
+  pub fn as_str(&self) -> &'static str {
+    match self {
+      Encoding::Gzip => "gzip",
+      Encoding::Deflate => "deflate",
+      Encoding::Br => "br",
+      Encoding::Zstd => "zstd",
+    }
+  }
+
+  pub fn from_token(token: &str) -> Option<Self> {
+    match token.trim().to_ascii_lowercase().as_str() {
+      "gzip" | "x-gzip" => Some(Encoding::Gzip),
+      "deflate" => Some(Encoding::Deflate),
+      "br" => Some(Encoding::Br),
+      "zstd" => Some(Encoding::Zstd),
+      _ => None,
+    }
+  }
+
+  pub fn compress(&self, data: &[u8], level: CompressionLevel) -> std::io::Result<Vec<u8>> {
+    match self {
+      Encoding::Gzip => {
+        let mut encoder = flate2::write::GzEncoder::new(vec![], level.flate2());
+        encoder.write_all(data)?;
+        encoder.finish()
+      }
+
+      Encoding::Deflate => {
+        let mut encoder = flate2::write::ZlibEncoder::new(vec![], level.flate2());
+        encoder.write_all(data)?;
+        encoder.finish()
+      }
+
+      Encoding::Br => {
+        let mut out = vec![];
+        {
+          let mut encoder = brotli::CompressorWriter::new(&mut out, 4096, level.brotli(), 22);
+          encoder.write_all(data)?;
+        }
+        Ok(out)
+      }
+
+      Encoding::Zstd => zstd::stream::encode_all(data, level.zstd()),
+    }
+  }
+
+  /// Decompress `data` failing with [`DecompressError::MaxSizeExceeded`] as soon as the output is bigger than `max_size`
+  pub fn decompress(&self, data: &[u8], max_size: usize) -> Result<Vec<u8>, DecompressError> {
+    let reader: Box<dyn Read + '_> = match self {
+      Encoding::Gzip => Box::new(flate2::read::MultiGzDecoder::new(data)),
+      Encoding::Deflate => Box::new(flate2::read::ZlibDecoder::new(data)),
+      Encoding::Br => Box::new(brotli::Decompressor::new(data, 4096)),
+      Encoding::Zstd => Box::new(zstd::stream::read::Decoder::new(data)?),
+    };
+
+    // read at most one byte past the limit, so zip bombs are never fully inflated
+    let mut out = vec![];
+    reader.take(max_size as u64 + 1).read_to_end(&mut out)?;
+    if out.len() > max_size {
+      return Err(DecompressError::MaxSizeExceeded(max_size));
+    }
+
+    Ok(out)
+  }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum DecompressError {
+  #[error("decompressed body max size of {0} bytes exceeded")]
+  MaxSizeExceeded(usize),
+  #[error(transparent)]
+  Io(#[from] std::io::Error),
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum CompressionLevel {
+  Fastest,
+  Default,
+  Best,
+}
+
+impl CompressionLevel {
+  fn flate2(&self) -> flate2::Compression {
+    match self {
+      CompressionLevel::Fastest => flate2::Compression::fast(),
+      CompressionLevel::Default => flate2::Compression::default(),
+      CompressionLevel::Best => flate2::Compression::best(),
+    }
+  }
+
+  fn brotli(&self) -> u32 {
+    match self {
+      CompressionLevel::Fastest => 1,
+      CompressionLevel::Default => 4,
+      CompressionLevel::Best => 11,
+    }
+  }
+
+  fn zstd(&self) -> i32 {
+    match self {
+      CompressionLevel::Fastest => 1,
+      CompressionLevel::Default => 3,
+      CompressionLevel::Best => 19,
+    }
+  }
+}
+
+#[derive(Debug, Clone)]
+pub struct CompressionOptions {
+  /// Supported response encodings, in order of preference when the client weights them equally
+  pub encodings: Vec<Encoding>,
+  /// Responses smaller than this are sent uncompressed
+  pub min_size: usize,
+  pub level: CompressionLevel,
+}
+
+impl Default for CompressionOptions {
+  fn default() -> Self {
+    Self {
+      encodings: vec![Encoding::Zstd, Encoding::Br, Encoding::Gzip],
+      min_size: 1024,
+      level: CompressionLevel::Default,
+    }
+  }
+}
+
+impl CompressionOptions {
+  /// Choose the response encoding from the `Accept-Encoding` request header
+  pub fn negotiate(&self, headers: &HeaderMap) -> Option<Encoding> {
+    let mut best: Option<(Encoding, f32, usize)> = None;
+    let mut wildcard: Option<f32> = None;
+    let mut rejected = vec![];
+
+    for value in headers.get_all(ACCEPT_ENCODING) {
+      let value = match value.to_str() {
+        Ok(value) => value,
+        Err(_) => continue,
+      };
+
+      for item in value.split(',') {
+        let mut params = item.split(';');
+        let token = params.next().unwrap_or("").trim();
+        let q = params
+          .filter_map(|param| param.trim().strip_prefix("q="))
+          .filter_map(|q| q.trim().parse::<f32>().ok())
+          .next()
+          .unwrap_or(1.0);
+
+        if token == "*" {
+          wildcard = Some(q);
+          continue;
+        }
+
+        let encoding = match Encoding::from_token(token) {
+          Some(encoding) => encoding,
+          None => continue,
+        };
+
+        if q <= 0.0 {
+          rejected.push(encoding);
+          continue;
+        }
+
+        if let Some(order) = self.encodings.iter().position(|e| *e == encoding) {
+          let better = match best {
+            None => true,
+            Some((_, best_q, best_order)) => q > best_q || (q == best_q && order < best_order),
+          };
+          if better {
+            best = Some((encoding, q, order));
+          }
+        }
+      }
+    }
+
+    if let Some((encoding, _, _)) = best {
+      return Some(encoding);
+    }
+
+    match wildcard {
+      Some(q) if q > 0.0 => self.encodings.iter().find(|e| !rejected.contains(e)).copied(),
+      _ => None,
+    }
+  }
+}
+
+/// The encoding of a request body from its `Content-Encoding` header
+///
+/// `Ok(None)` for identity, `Err` with the header value if the encoding is not supported
+pub fn content_encoding(headers: &HeaderMap) -> Result<Option<Encoding>, String> {
+  let value = match headers.get(CONTENT_ENCODING) {
+    Some(value) => value.to_str().map_err(|_| String::from("<invalid>"))?,
+    None => return Ok(None),
+  };
+
+  let value = value.trim();
+  if value.is_empty() || value.eq_ignore_ascii_case("identity") {
+    return Ok(None);
+  }
+
+  match Encoding::from_token(value) {
+    Some(encoding) => Ok(Some(encoding)),
+    None => Err(value.to_string()),
+  }
+}
+
+/// Run `handler` and compress the response body according to the request `Accept-Encoding`
+pub async fn compressed<F: std::future::Future<Output = Response>>(
+  options: Option<&CompressionOptions>,
+  req: Request,
+  handler: impl FnOnce(Request) -> F,
+) -> Response {
+  let options = match options {
+    Some(options) => options,
+    None => return handler(req).await,
+  };
+
+  let encoding = options.negotiate(req.headers());
+  let mut res = handler(req).await;
+  res.headers_mut().append(VARY, HeaderValue::from_static("accept-encoding"));
+
+  let encoding = match encoding {
+    Some(encoding) => encoding,
+    None => return res,
+  };
+
+  if res.headers().contains_key(CONTENT_ENCODING) {
+    return res;
+  }
+
+  // our responses are always fully buffered json, streaming bodies are left untouched
+  match res.body().size_hint().exact() {
+    Some(len) if len as usize >= options.min_size => {}
+    _ => return res,
+  }
+
+  let (mut parts, body) = res.into_parts();
+  let bytes = match axum::body::to_bytes(body, usize::MAX).await {
+    Ok(bytes) => bytes,
+    Err(err) => {
+      tracing::warn!("error buffering response body for compression: {err}");
+      return Response::from_parts(parts, Body::empty());
+    }
+  };
+
+  // brotli and zstd at their best levels are too slow for the async executor
+  let level = options.level;
+  let input = bytes.clone();
+  let result = tokio::task::spawn_blocking(move || encoding.compress(&input, level))
+    .await
+    .unwrap_or_else(|err| Err(std::io::Error::other(err)));
+
+  match result {
+    Ok(compressed) => {
+      parts.headers.insert(CONTENT_ENCODING, HeaderValue::from_static(encoding.as_str()));
+      parts.headers.insert(CONTENT_LENGTH, HeaderValue::from(compressed.len()));
+      // the compressed bytes differ from the ones the strong etag was computed from
+      if let Some(etag) = parts.headers.get(ETAG).and_then(|v| v.to_str().ok()) {
+        if etag.starts_with('"') {
+          if let Ok(weak) = HeaderValue::from_str(&format!("W/{etag}")) {
+            parts.headers.insert(ETAG, weak);
+          }
+        }
+      }
+      Response::from_parts(parts, Body::from(compressed))
+    }
+    Err(err) => {
+      tracing::warn!("error compressing response body with {}: {err}", encoding.as_str());
+      Response::from_parts(parts, Body::from(bytes))
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn negotiate(accept_encoding: &str) -> Option<Encoding> {
+    let mut headers = HeaderMap::new();
+    headers.insert(ACCEPT_ENCODING, HeaderValue::from_str(accept_encoding).unwrap());
+    CompressionOptions::default().negotiate(&headers)
+  }
+
+  #[test]
+  fn negotiation() {
+    assert_eq!(CompressionOptions::default().negotiate(&HeaderMap::new()), None);
+    assert_eq!(negotiate("gzip"), Some(Encoding::Gzip));
+    assert_eq!(negotiate("X-GZIP"), Some(Encoding::Gzip));
+    assert_eq!(negotiate("deflate"), None);
+    assert_eq!(negotiate("identity"), None);
+
+    // equal weights use the server preference, otherwise the highest q wins
+    assert_eq!(negotiate("gzip, br, zstd"), Some(Encoding::Zstd));
+    assert_eq!(negotiate("gzip;q=1, br;q=0.8, zstd;q=0.5"), Some(Encoding::Gzip));
+    assert_eq!(negotiate("gzip; q=0.2, br ;q=0.9"), Some(Encoding::Br));
+
+    // q=0 rejects an encoding, also when matched by the wildcard
+    assert_eq!(negotiate("gzip;q=0"), None);
+    assert_eq!(negotiate("*, zstd;q=0"), Some(Encoding::Br));
+    assert_eq!(negotiate("*;q=0"), None);
+    assert_eq!(negotiate("*;q=0, gzip"), Some(Encoding::Gzip));
+
+    assert_eq!(negotiate("identity;q=0, gzip"), Some(Encoding::Gzip));
+    assert_eq!(negotiate("identity;q=0, *"), Some(Encoding::Zstd));
+    assert_eq!(negotiate("identity;q=0"), None);
+  }
+
+  #[test]
+  fn decompression_limit() {
+    let data = vec![b'a'; 64 * 1024];
+    for encoding in [Encoding::Gzip, Encoding::Deflate, Encoding::Br, Encoding::Zstd] {
+      let compressed = encoding.compress(&data, CompressionLevel::Fastest).unwrap();
+      assert!(compressed.len() < 1024);
+
+      assert_eq!(encoding.decompress(&compressed, data.len()).unwrap(), data);
+      assert!(matches!(
+        encoding.decompress(&compressed, data.len() - 1),
+        Err(DecompressError::MaxSizeExceeded(max)) if max == data.len() - 1
+      ));
+    }
+
+    assert!(matches!(Encoding::Gzip.decompress(b"not gzip", 1024), Err(DecompressError::Io(_))));
+  }
+
+  async fn compress(body: Vec<u8>, etag: &'static str) -> Response {
+    let options = CompressionOptions::default();
+    let req = Request::builder().header(ACCEPT_ENCODING, "gzip").body(Body::empty()).unwrap();
+    compressed(Some(&options), req, |_| async move {
+      Response::builder().header(ETAG, etag).body(Body::from(body)).unwrap()
+    })
+    .await
+  }
+
+  #[tokio::test]
+  async fn weakens_etag() {
+    let body = vec![b'a'; 2048];
+    let res = compress(body.clone(), "\"abc\"").await;
+    assert_eq!(res.headers()[CONTENT_ENCODING], "gzip");
+    assert_eq!(res.headers()[ETAG], "W/\"abc\"");
+    assert_eq!(res.headers()[VARY], "accept-encoding");
+
+    let bytes = axum::body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+    assert_eq!(Encoding::Gzip.decompress(&bytes, body.len()).unwrap(), body);
+
+    let res = compress(body, "W/\"abc\"").await;
+    assert_eq!(res.headers()[ETAG], "W/\"abc\"");
+
+    // below min_size the body and its strong etag are untouched
+    let res = compress(b"{}".to_vec(), "\"abc\"").await;
+    assert_eq!(res.headers().get(CONTENT_ENCODING), None);
+    assert_eq!(res.headers()[ETAG], "\"abc\"");
+  }
+}
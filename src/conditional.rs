@@ -0,0 +1,320 @@
+use std::{fmt::Write, time::{Duration, SystemTime}};
+
+use axum::{
+  body::Body,
+  http::{
+    header::{ETAG, IF_MATCH, IF_MODIFIED_SINCE, IF_NONE_MATCH, IF_UNMODIFIED_SINCE, LAST_MODIFIED},
+    HeaderMap, HeaderValue, StatusCode,
+  },
+  response::Response,
+};
+use sha2::{Digest, Sha256};
+
+use crate::error::{ApiError, ApiErrorKind};
+
+/// The validators of a representation, used to evaluate conditional requests
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct Validators {
+  // always a quoted entity tag, eg: `"v12"` or `W/"v12"`
+  etag: Option<String>,
+  last_modified: Option<SystemTime>,
+}
+
+/// An entity tag with characters not allowed in the `ETag` header
+#[derive(Debug, Clone, Eq, PartialEq, thiserror::Error)]
+#[error("invalid entity tag `{0}`, only visible ascii characters other than `\"` are allowed")]
+pub struct InvalidEtag(pub String);
+
+impl Validators {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Set a strong entity tag, eg: `v12` is sent as `"v12"`, already quoted tags are kept as is
+  pub fn with_etag(mut self, etag: &str) -> Result<Self, InvalidEtag> {
+    self.etag = Some(quote(etag)?);
+    Ok(self)
+  }
+
+  /// Set a weak entity tag, eg: `v12` is sent as `W/"v12"`
+  pub fn with_weak_etag(mut self, etag: &str) -> Result<Self, InvalidEtag> {
+    self.etag = Some(format!("W/{}", quote(etag)?));
+    Ok(self)
+  }
+
+  pub fn with_last_modified(mut self, last_modified: SystemTime) -> Self {
+    self.last_modified = Some(last_modified);
+    self
+  }
+
+  /// The quoted entity tag
+  pub fn etag(&self) -> Option<&str> {
+    self.etag.as_deref()
+  }
+
+  pub fn last_modified(&self) -> Option<SystemTime> {
+    self.last_modified
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.etag.is_none() && self.last_modified.is_none()
+  }
+
+  /// Use `etag` when no entity tag was set, eg: the hash of the representation
+  pub(crate) fn or_etag(&mut self, etag: impl FnOnce() -> String) {
+    if self.etag.is_none() {
+      self.etag = Some(etag());
+    }
+  }
+
+  /// Add the `ETag` and `Last-Modified` headers
+  pub fn write_headers(&self, headers: &mut HeaderMap) {
+    if let Some(etag) = &self.etag {
+      if let Ok(value) = HeaderValue::from_str(etag) {
+        headers.insert(ETAG, value);
+      }
+    }
+
+    if let Some(last_modified) = self.last_modified {
+      if let Ok(value) = HeaderValue::from_str(&httpdate::fmt_http_date(last_modified)) {
+        headers.insert(LAST_MODIFIED, value);
+      }
+    }
+  }
+}
+
+/// A strong ETag computed from the serialized representation
+pub fn etag_for(body: &[u8]) -> String {
+  let hash = Sha256::digest(body);
+  let mut etag = String::from("\"");
+  // 128 bits are plenty to identify a representation
+  for byte in &hash[..16] {
+    let _ = write!(etag, "{byte:02x}");
+  }
+  etag.push('"');
+  etag
+}
+
+fn quote(etag: &str) -> Result<String, InvalidEtag> {
+  let opaque = etag
+    .strip_prefix('"')
+    .and_then(|etag| etag.strip_suffix('"'))
+    .unwrap_or(etag);
+
+  // etagc = %x21 / %x23-7E, obs-text is not accepted by header values
+  match opaque.bytes().all(|b| b == 0x21 || (0x23..=0x7e).contains(&b)) {
+    true => Ok(format!("\"{opaque}\"")),
+    false => Err(InvalidEtag(etag.to_string())),
+  }
+}
+
+fn is_weak(etag: &str) -> bool {
+  etag.starts_with("W/")
+}
+
+fn opaque(etag: &str) -> &str {
+  etag.strip_prefix("W/").unwrap_or(etag)
+}
+
+/// Parse a list of entity tags, `None` if the header is `*`
+fn etag_list(headers: &HeaderMap, name: axum::http::HeaderName) -> Option<Vec<String>> {
+  let mut list = vec![];
+  for value in headers.get_all(name) {
+    let value = match value.to_str() {
+      Ok(value) => value,
+      Err(_) => continue,
+    };
+
+    for item in value.split(',') {
+      let item = item.trim();
+      if item == "*" {
+        return None;
+      }
+      if !item.is_empty() {
+        list.push(item.to_string());
+      }
+    }
+  }
+  Some(list)
+}
+
+fn http_date(headers: &HeaderMap, name: axum::http::HeaderName) -> Option<SystemTime> {
+  headers
+    .get(name)
+    .and_then(|v| v.to_str().ok())
+    .and_then(|v| httpdate::parse_http_date(v).ok())
+}
+
+// http dates have a resolution of one second
+fn truncate(time: SystemTime) -> SystemTime {
+  match time.duration_since(SystemTime::UNIX_EPOCH) {
+    Ok(since) => SystemTime::UNIX_EPOCH + Duration::from_secs(since.as_secs()),
+    Err(_) => time,
+  }
+}
+
+/// Evaluate `If-None-Match` and `If-Modified-Since` for a GET or HEAD request \
+/// Returns `true` if the client representation is fresh and a 304 should be sent
+pub fn is_not_modified(request: &HeaderMap, validators: &Validators) -> bool {
+  if request.contains_key(IF_NONE_MATCH) {
+    let etag = match &validators.etag {
+      Some(etag) => etag,
+      None => return false,
+    };
+
+    // If-None-Match uses the weak comparison
+    return match etag_list(request, IF_NONE_MATCH) {
+      None => true,
+      Some(list) => list.iter().any(|item| opaque(item) == opaque(etag)),
+    };
+  }
+
+  // If-Modified-Since is ignored when If-None-Match is present
+  match (http_date(request, IF_MODIFIED_SINCE), validators.last_modified) {
+    (Some(since), Some(last_modified)) => truncate(last_modified) <= since,
+    _ => false,
+  }
+}
+
+/// Evaluate `If-Match` and `If-Unmodified-Since` for a state changing request
+/// against the `current` validators of the target resource
+pub fn check_preconditions(request: &HeaderMap, current: &Validators) -> Result<(), ApiError> {
+  let failed = |message: &str| ApiError {
+    status: StatusCode::PRECONDITION_FAILED.as_u16(),
+    kind: ApiErrorKind::PreconditionFailed,
+    message: message.to_string(),
+  };
+
+  if request.contains_key(IF_MATCH) {
+    // If-Match uses the strong comparison
+    let matches = match (etag_list(request, IF_MATCH), &current.etag) {
+      (None, _) => true,
+      (Some(_), None) => false,
+      (Some(list), Some(etag)) => {
+        !is_weak(etag) && list.iter().any(|item| !is_weak(item) && item == etag)
+      }
+    };
+
+    if !matches {
+      return Err(failed("the resource was modified, If-Match precondition failed"));
+    }
+
+    return Ok(());
+  }
+
+  if let (Some(since), Some(last_modified)) = (http_date(request, IF_UNMODIFIED_SINCE), current.last_modified) {
+    if truncate(last_modified) > since {
+      return Err(failed("the resource was modified, If-Unmodified-Since precondition failed"));
+    }
+  }
+
+  Ok(())
+}
+
+/// Whether the request carries preconditions that need the current validators of the resource
+pub fn has_preconditions(request: &HeaderMap) -> bool {
+  request.contains_key(IF_MATCH) || request.contains_key(IF_UNMODIFIED_SINCE)
+}
+
+/// A 304 response carrying the validators
+pub fn not_modified(validators: &Validators) -> Response {
+  let mut res = Response::new(Body::empty());
+  *res.status_mut() = StatusCode::NOT_MODIFIED;
+  validators.write_headers(res.headers_mut());
+  res
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn headers(list: &[(axum::http::HeaderName, &str)]) -> HeaderMap {
+    list.iter().map(|(name, value)| (name.clone(), HeaderValue::from_str(value).unwrap())).collect()
+  }
+
+  fn strong(etag: &str) -> Validators {
+    Validators::new().with_etag(etag).unwrap()
+  }
+
+  fn weak(etag: &str) -> Validators {
+    Validators::new().with_weak_etag(etag).unwrap()
+  }
+
+  #[test]
+  fn etags() {
+    assert_eq!(strong("v1").etag(), Some("\"v1\""));
+    assert_eq!(strong("\"v1\"").etag(), Some("\"v1\""));
+    assert_eq!(weak("v1").etag(), Some("W/\"v1\""));
+    assert_eq!(Validators::new().with_etag("a b"), Err(InvalidEtag(String::from("a b"))));
+    assert!(Validators::new().with_etag("a\"b").is_err());
+    assert_eq!(etag_for(b"{}").len(), 34);
+  }
+
+  #[test]
+  fn if_none_match_weak_comparison() {
+    let request = headers(&[(IF_NONE_MATCH, "\"v0\", W/\"v1\"")]);
+    assert!(is_not_modified(&request, &strong("v1")));
+    assert!(is_not_modified(&request, &weak("v1")));
+    assert!(!is_not_modified(&request, &strong("v2")));
+    assert!(!is_not_modified(&request, &Validators::new()));
+
+    assert!(is_not_modified(&headers(&[(IF_NONE_MATCH, "\"v1\"")]), &weak("v1")));
+  }
+
+  #[test]
+  fn if_none_match_any() {
+    let request = headers(&[(IF_NONE_MATCH, "*")]);
+    assert!(is_not_modified(&request, &strong("v1")));
+    assert!(is_not_modified(&request, &weak("v1")));
+    assert!(!is_not_modified(&request, &Validators::new()));
+  }
+
+  #[test]
+  fn if_modified_since() {
+    let modified = SystemTime::UNIX_EPOCH + Duration::from_millis(1_700_000_000_500);
+    let validators = strong("v1").with_last_modified(modified);
+    let date = |time| httpdate::fmt_http_date(time);
+
+    let request = headers(&[(IF_MODIFIED_SINCE, &date(modified))]);
+    assert!(is_not_modified(&request, &validators));
+
+    let request = headers(&[(IF_MODIFIED_SINCE, &date(modified - Duration::from_secs(1)))]);
+    assert!(!is_not_modified(&request, &validators));
+
+    // If-None-Match takes precedence
+    let request = headers(&[(IF_NONE_MATCH, "\"v2\""), (IF_MODIFIED_SINCE, &date(modified))]);
+    assert!(!is_not_modified(&request, &validators));
+  }
+
+  #[test]
+  fn if_match_strong_comparison() {
+    let request = headers(&[(IF_MATCH, "\"v0\", \"v1\"")]);
+    assert!(has_preconditions(&request));
+    assert!(check_preconditions(&request, &strong("v1")).is_ok());
+
+    let err = check_preconditions(&request, &strong("v2")).unwrap_err();
+    assert_eq!(err.status, 412);
+    assert_eq!(err.kind, ApiErrorKind::PreconditionFailed);
+
+    // weak tags never match strongly
+    assert!(check_preconditions(&request, &weak("v1")).is_err());
+    assert!(check_preconditions(&headers(&[(IF_MATCH, "W/\"v1\"")]), &strong("v1")).is_err());
+    assert!(check_preconditions(&request, &Validators::new()).is_err());
+
+    assert!(check_preconditions(&headers(&[(IF_MATCH, "*")]), &strong("v2")).is_ok());
+  }
+
+  #[test]
+  fn if_unmodified_since() {
+    let modified = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+    let validators = Validators::new().with_last_modified(modified);
+    let date = |time| httpdate::fmt_http_date(time);
+
+    assert!(check_preconditions(&headers(&[(IF_UNMODIFIED_SINCE, &date(modified))]), &validators).is_ok());
+    let request = headers(&[(IF_UNMODIFIED_SINCE, &date(modified - Duration::from_secs(1)))]);
+    assert_eq!(check_preconditions(&request, &validators).unwrap_err().status, 412);
+
+    assert!(!has_preconditions(&headers(&[(IF_NONE_MATCH, "*")])));
+    assert!(check_preconditions(&HeaderMap::new(), &validators).is_ok());
+  }
+}
@@ -0,0 +1,495 @@
+use std::{
+  collections::HashMap,
+  sync::{Arc, Mutex, OnceLock},
+};
+
+use axum::{
+  body::Body,
+  extract::Request,
+  http::{Method, StatusCode},
+  response::Response,
+};
+use regex::Regex;
+use serde_json::{Map, Value};
+
+/// What to do when a response doesn't match the registered schemas or garde rules
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum ContractMode {
+  /// Panic with the mismatches, for tests and development
+  Panic,
+  /// Log the mismatches with `tracing::error!` and send the response anyway
+  Log,
+}
+
+/// Report a contract violation of the endpoint registered at `method` and `path` according to `mode`
+pub fn violation(mode: ContractMode, method: &Method, path: &str, message: &str) {
+  match mode {
+    ContractMode::Panic => panic!("contract violation in {method} {path}: {message}"),
+    ContractMode::Log => {
+      tracing::error!(http.method = %method, http.route = path, "contract violation: {message}");
+    }
+  }
+}
+
+/// A mismatch between a json value and a schema
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct SchemaError {
+  /// JSON pointer to the invalid value, eg: `/items/0/email`
+  pub pointer: String,
+  pub message: String,
+}
+
+impl std::fmt::Display for SchemaError {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    let pointer = if self.pointer.is_empty() { "/" } else { &self.pointer };
+    write!(f, "{pointer}: {}", self.message)
+  }
+}
+
+/// Validate `value` against an OpenAPI 3 flavored JSON Schema as generated by schemars
+///
+/// Supports the keywords schemars emits: `type`, `nullable`, `enum`, `const`, `properties`, `required`,
+/// `additionalProperties`, `items`, `allOf`, `anyOf`, `oneOf`, `not`, local `$ref`s and the numeric, string and array bounds \
+/// Unknown keywords and `format` are ignored
+pub fn validate(schema: &Value, value: &Value) -> Vec<SchemaError> {
+  let mut errors = vec![];
+  validate_at(schema, schema, value, &mut String::new(), &mut errors);
+  errors
+}
+
+fn type_name(value: &Value) -> &'static str {
+  match value {
+    Value::Null => "null",
+    Value::Bool(_) => "boolean",
+    Value::Number(n) if n.is_i64() || n.is_u64() => "integer",
+    Value::Number(_) => "number",
+    Value::String(_) => "string",
+    Value::Array(_) => "array",
+    Value::Object(_) => "object",
+  }
+}
+
+fn is_type(value: &Value, ty: &str) -> bool {
+  match ty {
+    "number" => value.is_number(),
+    "integer" => match value {
+      Value::Number(n) => n.is_i64() || n.is_u64() || n.as_f64().is_some_and(|f| f.fract() == 0.0),
+      _ => false,
+    },
+    ty => type_name(value) == ty,
+  }
+}
+
+fn resolve<'a>(root: &'a Value, reference: &str) -> Option<&'a Value> {
+  let pointer = reference.strip_prefix('#')?;
+  root.pointer(pointer)
+}
+
+fn escape(segment: &str) -> String {
+  segment.replace('~', "~0").replace('/', "~1")
+}
+
+fn validate_at(root: &Value, schema: &Value, value: &Value, pointer: &mut String, errors: &mut Vec<SchemaError>) {
+  let schema = match schema {
+    Value::Bool(true) => return,
+    Value::Bool(false) => {
+      errors.push(SchemaError { pointer: pointer.clone(), message: String::from("no value is allowed here") });
+      return;
+    }
+    Value::Object(schema) => schema,
+    _ => return,
+  };
+
+  let mut error = |message: String| errors.push(SchemaError { pointer: pointer.clone(), message });
+
+  if let Some(reference) = schema.get("$ref").and_then(|v| v.as_str()) {
+    match resolve(root, reference) {
+      Some(target) => validate_at(root, target, value, pointer, errors),
+      None => error(format!("unresolved reference `{reference}`")),
+    }
+    return;
+  }
+
+  if value.is_null() && schema.get("nullable").and_then(|v| v.as_bool()) == Some(true) {
+    return;
+  }
+
+  if let Some(ty) = schema.get("type") {
+    let types = match ty {
+      Value::String(ty) => vec![ty.as_str()],
+      Value::Array(types) => types.iter().filter_map(|t| t.as_str()).collect(),
+      _ => vec![],
+    };
+    if !types.is_empty() && !types.iter().any(|ty| is_type(value, ty)) {
+      error(format!("expected {}, found {}", types.join(" or "), type_name(value)));
+      return;
+    }
+  }
+
+  if let Some(variants) = schema.get("enum").and_then(|v| v.as_array()) {
+    if !variants.contains(value) {
+      error(format!("{value} is not one of {}", Value::Array(variants.clone())));
+    }
+  }
+
+  if let Some(expected) = schema.get("const") {
+    if expected != value {
+      error(format!("expected {expected}, found {value}"));
+    }
+  }
+
+  match value {
+    Value::Number(n) => {
+      let n = n.as_f64().unwrap_or_default();
+      let bound = |key: &str| schema.get(key).and_then(|v| v.as_f64());
+      let exclusive = |key: &str| schema.get(key).and_then(|v| v.as_bool()) == Some(true);
+      if let Some(min) = bound("minimum") {
+        if n < min || (exclusive("exclusiveMinimum") && n == min) {
+          error(format!("{n} is less than the minimum of {min}"));
+        }
+      }
+      if let Some(max) = bound("maximum") {
+        if n > max || (exclusive("exclusiveMaximum") && n == max) {
+          error(format!("{n} is greater than the maximum of {max}"));
+        }
+      }
+    }
+
+    Value::String(s) => {
+      let len = s.chars().count() as u64;
+      if let Some(min) = schema.get("minLength").and_then(|v| v.as_u64()) {
+        if len < min {
+          error(format!("length {len} is less than the minimum of {min}"));
+        }
+      }
+      if let Some(max) = schema.get("maxLength").and_then(|v| v.as_u64()) {
+        if len > max {
+          error(format!("length {len} is greater than the maximum of {max}"));
+        }
+      }
+      if let Some(pattern) = schema.get("pattern").and_then(|v| v.as_str()) {
+        match is_match(pattern, s) {
+          Ok(false) => error(format!("does not match the pattern `{pattern}`")),
+          Ok(true) => {}
+          Err(err) => error(format!("invalid pattern `{pattern}` in schema: {err}")),
+        }
+      }
+    }
+
+    Value::Array(items) => {
+      let len = items.len() as u64;
+      if let Some(min) = schema.get("minItems").and_then(|v| v.as_u64()) {
+        if len < min {
+          error(format!("{len} items is less than the minimum of {min}"));
+        }
+      }
+      if let Some(max) = schema.get("maxItems").and_then(|v| v.as_u64()) {
+        if len > max {
+          error(format!("{len} items is greater than the maximum of {max}"));
+        }
+      }
+      if let Some(item_schema) = schema.get("items") {
+        for (i, item) in items.iter().enumerate() {
+          // draft 4 style tuple schemas
+          let item_schema = match item_schema {
+            Value::Array(schemas) => match schemas.get(i) {
+              Some(schema) => schema,
+              None => continue,
+            },
+            schema => schema,
+          };
+          let len = pointer.len();
+          pointer.push_str(&format!("/{i}"));
+          validate_at(root, item_schema, item, pointer, errors);
+          pointer.truncate(len);
+        }
+      }
+    }
+
+    Value::Object(object) => validate_object(root, schema, object, pointer, errors),
+
+    Value::Null | Value::Bool(_) => {}
+  }
+
+  if let Some(all_of) = schema.get("allOf").and_then(|v| v.as_array()) {
+    for sub in all_of {
+      validate_at(root, sub, value, pointer, errors);
+    }
+  }
+
+  let matching = |subs: &Vec<Value>| {
+    subs.iter().filter(|sub| validate_sub(root, sub, value)).count()
+  };
+
+  if let Some(any_of) = schema.get("anyOf").and_then(|v| v.as_array()) {
+    if matching(any_of) == 0 {
+      errors.push(SchemaError { pointer: pointer.clone(), message: String::from("does not match any of the anyOf schemas") });
+    }
+  }
+
+  if let Some(one_of) = schema.get("oneOf").and_then(|v| v.as_array()) {
+    let count = matching(one_of);
+    if count != 1 {
+      errors.push(SchemaError { pointer: pointer.clone(), message: format!("matches {count} of the oneOf schemas, expected exactly 1") });
+    }
+  }
+
+  if let Some(not) = schema.get("not") {
+    if validate_sub(root, not, value) {
+      errors.push(SchemaError { pointer: pointer.clone(), message: String::from("matches the not schema") });
+    }
+  }
+}
+
+// the patterns come from the registered schemas, so the cache is bounded by them
+static PATTERNS: OnceLock<Mutex<HashMap<String, Result<Regex, String>>>> = OnceLock::new();
+
+/// Match `s` against a schema `pattern`, compiled once per pattern
+fn is_match(pattern: &str, s: &str) -> Result<bool, String> {
+  let compiled = PATTERNS
+    .get_or_init(Default::default)
+    .lock()
+    .unwrap()
+    .entry(pattern.to_string())
+    .or_insert_with(|| Regex::new(pattern).map_err(|err| err.to_string()))
+    .clone();
+
+  compiled.map(|re| re.is_match(s))
+}
+
+fn validate_sub(root: &Value, schema: &Value, value: &Value) -> bool {
+  let mut errors = vec![];
+  validate_at(root, schema, value, &mut String::new(), &mut errors);
+  errors.is_empty()
+}
+
+fn validate_object(
+  root: &Value,
+  schema: &Map<String, Value>,
+  object: &Map<String, Value>,
+  pointer: &mut String,
+  errors: &mut Vec<SchemaError>,
+) {
+  if let Some(required) = schema.get("required").and_then(|v| v.as_array()) {
+    for name in required.iter().filter_map(|v| v.as_str()) {
+      if !object.contains_key(name) {
+        errors.push(SchemaError { pointer: pointer.clone(), message: format!("missing required property `{name}`") });
+      }
+    }
+  }
+
+  let properties = schema.get("properties").and_then(|v| v.as_object());
+  let additional = schema.get("additionalProperties");
+
+  for (name, value) in object {
+    let len = pointer.len();
+    pointer.push('/');
+    pointer.push_str(&escape(name));
+
+    match properties.and_then(|properties| properties.get(name)) {
+      Some(property) => validate_at(root, property, value, pointer, errors),
+      None => match additional {
+        Some(Value::Bool(false)) => {
+          errors.push(SchemaError { pointer: pointer.clone(), message: String::from("unknown property") });
+        }
+        Some(additional @ Value::Object(_)) => validate_at(root, additional, value, pointer, errors),
+        _ => {}
+      },
+    }
+
+    pointer.truncate(len);
+  }
+}
+
+/// The schemas a response of an endpoint is checked against
+#[derive(Debug, Clone)]
+pub struct ContractSchemas {
+  pub output: Arc<Value>,
+  pub error: Arc<Value>,
+}
+
+/// Run `handler` and validate the json response body against the endpoint output schema,
+/// or the error payload schema for error statuses
+pub async fn contract_checked<F: std::future::Future<Output = Response>>(
+  mode: Option<ContractMode>,
+  schemas: &ContractSchemas,
+  method: &Method,
+  path: &str,
+  req: Request,
+  handler: impl FnOnce(Request) -> F,
+) -> Response {
+  let mode = match mode {
+    Some(mode) => mode,
+    None => return handler(req).await,
+  };
+
+  let res = handler(req).await;
+
+  let status = res.status();
+  let schema = if status.is_success() {
+    &schemas.output
+  } else if status.is_client_error() || status.is_server_error() {
+    &schemas.error
+  } else {
+    return res;
+  };
+
+  // HEAD and 204 responses have no body to check
+  if status == StatusCode::NO_CONTENT || method == Method::HEAD {
+    return res;
+  }
+
+  let (parts, body) = res.into_parts();
+  let bytes = match axum::body::to_bytes(body, usize::MAX).await {
+    Ok(bytes) => bytes,
+    Err(err) => {
+      tracing::warn!("error buffering response body for contract validation: {err}");
+      return Response::from_parts(parts, Body::empty());
+    }
+  };
+
+  match serde_json::from_slice::<Value>(&bytes) {
+    Ok(value) => {
+      let errors = validate(schema, &value);
+      if !errors.is_empty() {
+        let errors = errors.iter().map(|e| e.to_string()).collect::<Vec<_>>().join(", ");
+        violation(mode, method, path, &format!("response with status {status} does not match its schema: {errors}"));
+      }
+    }
+    Err(err) => violation(mode, method, path, &format!("response with status {status} is not valid json: {err}")),
+  }
+
+  Response::from_parts(parts, Body::from(bytes))
+}
+
+
+#[cfg(test)]
+mod tests {
+  use serde_json::json;
+
+  use super::*;
+
+  fn errors(schema: Value, value: Value) -> Vec<String> {
+    validate(&schema, &value).iter().map(|err| err.to_string()).collect()
+  }
+
+  #[test]
+  fn types() {
+    assert!(errors(json!({ "type": "string" }), json!("a")).is_empty());
+    assert_eq!(errors(json!({ "type": "string" }), json!(1)), ["/: expected string, found integer"]);
+    assert!(errors(json!({ "type": "integer" }), json!(2.0)).is_empty());
+    assert_eq!(errors(json!({ "type": "integer" }), json!(2.5)), ["/: expected integer, found number"]);
+    assert!(errors(json!({ "type": "number" }), json!(2)).is_empty());
+    assert!(errors(json!({ "type": ["string", "null"] }), json!(null)).is_empty());
+    assert_eq!(errors(json!({ "type": "string" }), json!(null)), ["/: expected string, found null"]);
+    assert!(errors(json!({ "type": "string", "nullable": true }), json!(null)).is_empty());
+    assert!(errors(json!(true), json!({})).is_empty());
+    assert_eq!(errors(json!(false), json!({})), ["/: no value is allowed here"]);
+  }
+
+  #[test]
+  fn objects() {
+    let schema = json!({
+      "type": "object",
+      "properties": {
+        "id": { "type": "string" },
+        "tags": { "type": "array", "items": { "type": "string" } },
+        "a/b": { "type": "integer" },
+      },
+      "required": ["id"],
+      "additionalProperties": false,
+    });
+
+    assert!(errors(schema.clone(), json!({ "id": "1", "tags": ["a"] })).is_empty());
+    assert_eq!(errors(schema.clone(), json!({ "tags": [] })), ["/: missing required property `id`"]);
+    assert_eq!(errors(schema.clone(), json!({ "id": "1", "other": 1 })), ["/other: unknown property"]);
+    assert_eq!(
+      errors(schema.clone(), json!({ "id": "1", "tags": ["a", 2], "a/b": "c" })),
+      ["/tags/1: expected string, found integer", "/a~1b: expected integer, found string"]
+    );
+
+    let map = json!({ "type": "object", "additionalProperties": { "type": "integer" } });
+    assert_eq!(errors(map, json!({ "a": 1, "b": "2" })), ["/b: expected integer, found string"]);
+  }
+
+  #[test]
+  fn enums() {
+    let schema = json!({ "type": "string", "enum": ["active", "disabled"] });
+    assert!(errors(schema.clone(), json!("active")).is_empty());
+    assert_eq!(errors(schema, json!("deleted")), [r#"/: "deleted" is not one of ["active","disabled"]"#]);
+    assert_eq!(errors(json!({ "const": "user" }), json!("admin")), [r#"/: expected "user", found "admin""#]);
+  }
+
+  #[test]
+  fn patterns() {
+    let schema = json!({ "type": "string", "pattern": "^[a-z]+$" });
+    assert!(errors(schema.clone(), json!("abc")).is_empty());
+    assert_eq!(errors(schema, json!("ABC")), ["/: does not match the pattern `^[a-z]+$`"]);
+
+    // patterns are not anchored
+    assert!(errors(json!({ "pattern": "[0-9]" }), json!("a1b")).is_empty());
+    assert_eq!(errors(json!({ "pattern": "(" }), json!("a")).len(), 1);
+    assert!(errors(json!({ "pattern": "(" }), json!("a"))[0].starts_with("/: invalid pattern `(` in schema"));
+  }
+
+  #[test]
+  fn bounds() {
+    let number = json!({ "type": "number", "minimum": 1, "maximum": 10 });
+    assert!(errors(number.clone(), json!(1)).is_empty());
+    assert!(errors(number.clone(), json!(10)).is_empty());
+    assert_eq!(errors(number.clone(), json!(0.5)), ["/: 0.5 is less than the minimum of 1"]);
+    assert_eq!(errors(number, json!(11)), ["/: 11 is greater than the maximum of 10"]);
+
+    let exclusive = json!({ "minimum": 1, "exclusiveMinimum": true, "maximum": 10, "exclusiveMaximum": true });
+    assert_eq!(errors(exclusive.clone(), json!(1)).len(), 1);
+    assert_eq!(errors(exclusive.clone(), json!(10)).len(), 1);
+    assert!(errors(exclusive, json!(5)).is_empty());
+
+    let string = json!({ "type": "string", "minLength": 2, "maxLength": 3 });
+    assert!(errors(string.clone(), json!("éé")).is_empty());
+    assert_eq!(errors(string.clone(), json!("a")), ["/: length 1 is less than the minimum of 2"]);
+    assert_eq!(errors(string, json!("abcd")), ["/: length 4 is greater than the maximum of 3"]);
+
+    let array = json!({ "type": "array", "minItems": 1, "maxItems": 2 });
+    assert_eq!(errors(array.clone(), json!([])), ["/: 0 items is less than the minimum of 1"]);
+    assert_eq!(errors(array, json!([1, 2, 3])), ["/: 3 items is greater than the maximum of 2"]);
+  }
+
+  #[test]
+  fn combinators() {
+    let all_of = json!({ "allOf": [{ "type": "object", "required": ["a"] }, { "required": ["b"] }] });
+    assert!(errors(all_of.clone(), json!({ "a": 1, "b": 2 })).is_empty());
+    assert_eq!(errors(all_of, json!({ "a": 1 })), ["/: missing required property `b`"]);
+
+    let any_of = json!({ "anyOf": [{ "type": "string" }, { "type": "integer", "minimum": 0 }] });
+    assert!(errors(any_of.clone(), json!("a")).is_empty());
+    assert!(errors(any_of.clone(), json!(1)).is_empty());
+    assert_eq!(errors(any_of, json!(-1)), ["/: does not match any of the anyOf schemas"]);
+
+    let one_of = json!({ "oneOf": [{ "type": "integer" }, { "type": "number", "minimum": 1 }] });
+    assert!(errors(one_of.clone(), json!(0)).is_empty());
+    assert!(errors(one_of.clone(), json!(1.5)).is_empty());
+    assert_eq!(errors(one_of.clone(), json!(2)), ["/: matches 2 of the oneOf schemas, expected exactly 1"]);
+    assert_eq!(errors(one_of, json!("a")), ["/: matches 0 of the oneOf schemas, expected exactly 1"]);
+
+    assert_eq!(errors(json!({ "not": { "type": "null" } }), json!(null)), ["/: matches the not schema"]);
+  }
+
+  #[test]
+  fn references() {
+    let schema = json!({
+      "$ref": "#/$defs/Tree",
+      "$defs": {
+        "Tree": {
+          "type": "object",
+          "properties": { "children": { "type": "array", "items": { "$ref": "#/$defs/Tree" } } },
+          "required": ["children"],
+        },
+      },
+    });
+
+    assert!(errors(schema.clone(), json!({ "children": [{ "children": [] }] })).is_empty());
+    assert_eq!(errors(schema, json!({ "children": [{}] })), ["/children/0: missing required property `children`"]);
+    assert_eq!(errors(json!({ "$ref": "#/missing" }), json!(1)), ["/: unresolved reference `#/missing`"]);
+  }
+}
@@ -0,0 +1,236 @@
+use std::time::Duration;
+
+use axum::{
+  extract::Request,
+  http::{
+    header::{
+      ACCESS_CONTROL_ALLOW_CREDENTIALS, ACCESS_CONTROL_ALLOW_HEADERS, ACCESS_CONTROL_ALLOW_METHODS,
+      ACCESS_CONTROL_ALLOW_ORIGIN, ACCESS_CONTROL_EXPOSE_HEADERS, ACCESS_CONTROL_MAX_AGE,
+      ACCESS_CONTROL_REQUEST_HEADERS, CONTENT_TYPE, ORIGIN, VARY,
+    },
+    HeaderMap, HeaderName, HeaderValue, Method, StatusCode,
+  },
+  response::Response,
+};
+
+use crate::{rate_limit::{RATELIMIT_LIMIT, RATELIMIT_REMAINING, RATELIMIT_RESET}, trace::X_REQUEST_ID};
+
+#[derive(Debug, Clone)]
+pub enum AllowOrigins {
+  /// Any origin, sent as `*`, can't be combined with credentials
+  Any,
+  /// Only these exact origins, eg: `https://app.example.test`
+  List(Vec<String>),
+}
+
+/// CORS policy for every endpoint of the registry
+///
+/// Preflight `OPTIONS` requests are answered automatically
+/// with the methods registered for the requested path \
+/// Allows any origin without credentials by default, see [`CorsOptions::with_origins`] and [`CorsOptions::with_credentials`]
+#[derive(Debug, Clone)]
+pub struct CorsOptions {
+  allow_origins: AllowOrigins,
+  allow_credentials: bool,
+  /// Request headers allowed in addition to the ones in the CORS safelist, `None` to allow any requested header
+  pub allow_headers: Option<Vec<HeaderName>>,
+  /// Response headers readable by the client
+  pub expose_headers: Vec<HeaderName>,
+  /// How long the preflight response can be cached by the client
+  pub max_age: Option<Duration>,
+}
+
+impl Default for CorsOptions {
+  fn default() -> Self {
+    Self {
+      allow_origins: AllowOrigins::Any,
+      allow_credentials: false,
+      allow_headers: Some(vec![CONTENT_TYPE, X_REQUEST_ID]),
+      expose_headers: vec![
+        X_REQUEST_ID,
+        RATELIMIT_LIMIT,
+        RATELIMIT_REMAINING,
+        RATELIMIT_RESET,
+        axum::http::header::RETRY_AFTER,
+      ],
+      max_age: Some(Duration::from_secs(60 * 60)),
+    }
+  }
+}
+
+impl CorsOptions {
+  /// Only allow these exact origins, eg: `https://app.example.test`
+  pub fn with_origins(origins: Vec<String>) -> Self {
+    Self {
+      allow_origins: AllowOrigins::List(origins),
+      ..Default::default()
+    }
+  }
+
+  /// Allow cookies and `Authorization` headers in cross origin requests \
+  /// Panics if any origin is allowed, every site could then make authenticated requests on behalf of the user
+  pub fn with_credentials(mut self) -> Self {
+    assert!(
+      matches!(self.allow_origins, AllowOrigins::List(_)),
+      "CORS credentials require a list of allowed origins, see CorsOptions::with_origins"
+    );
+    self.allow_credentials = true;
+    self
+  }
+
+  pub fn allow_origins(&self) -> &AllowOrigins {
+    &self.allow_origins
+  }
+
+  pub fn allow_credentials(&self) -> bool {
+    self.allow_credentials
+  }
+
+  /// The value of `Access-Control-Allow-Origin` for a request from `origin`, `None` if not allowed
+  pub fn allow_origin(&self, origin: &HeaderValue) -> Option<HeaderValue> {
+    match &self.allow_origins {
+      AllowOrigins::Any => Some(HeaderValue::from_static("*")),
+
+      AllowOrigins::List(list) => {
+        let origin_str = origin.to_str().ok()?;
+        if list.iter().any(|allowed| allowed == origin_str) {
+          Some(origin.clone())
+        } else {
+          None
+        }
+      }
+    }
+  }
+
+  /// Whether the CORS headers depend on the `Origin` of the request, shared caches then need `Vary: origin`
+  fn varies_by_origin(&self) -> bool {
+    matches!(self.allow_origins, AllowOrigins::List(_))
+  }
+
+  /// Add the CORS headers shared by preflight and actual responses, `Vary` is set even when the origin is rejected
+  fn add_origin_headers(&self, origin: Option<&HeaderValue>, headers: &mut HeaderMap) -> bool {
+    if self.varies_by_origin() {
+      headers.append(VARY, HeaderValue::from_static("origin"));
+    }
+
+    let allow_origin = match origin.and_then(|origin| self.allow_origin(origin)) {
+      Some(allow_origin) => allow_origin,
+      None => return false,
+    };
+
+    headers.insert(ACCESS_CONTROL_ALLOW_ORIGIN, allow_origin);
+
+    if self.allow_credentials {
+      headers.insert(ACCESS_CONTROL_ALLOW_CREDENTIALS, HeaderValue::from_static("true"));
+    }
+
+    true
+  }
+
+  /// Answer a preflight request for a path that has the `methods` registered
+  pub fn preflight(&self, req: &Request, methods: &[Method]) -> Response {
+    let mut res = Response::new(Default::default());
+    *res.status_mut() = StatusCode::NO_CONTENT;
+
+    let headers = res.headers_mut();
+    if !self.add_origin_headers(req.headers().get(ORIGIN), headers) {
+      return res;
+    }
+
+    let methods = methods.iter().map(|m| m.as_str()).collect::<Vec<_>>().join(", ");
+    if let Ok(methods) = HeaderValue::from_str(&methods) {
+      headers.insert(ACCESS_CONTROL_ALLOW_METHODS, methods);
+    }
+
+    match &self.allow_headers {
+      Some(allow_headers) => {
+        let list = allow_headers.iter().map(|h| h.as_str()).collect::<Vec<_>>().join(", ");
+        if let Ok(list) = HeaderValue::from_str(&list) {
+          headers.insert(ACCESS_CONTROL_ALLOW_HEADERS, list);
+        }
+      }
+
+      None => {
+        if let Some(requested) = req.headers().get(ACCESS_CONTROL_REQUEST_HEADERS) {
+          headers.insert(ACCESS_CONTROL_ALLOW_HEADERS, requested.clone());
+          headers.append(VARY, HeaderValue::from_static("access-control-request-headers"));
+        }
+      }
+    }
+
+    if let Some(max_age) = self.max_age {
+      headers.insert(ACCESS_CONTROL_MAX_AGE, HeaderValue::from(max_age.as_secs()));
+    }
+
+    res
+  }
+}
+
+/// Run `handler` and add the CORS headers to the response of a cross origin request \
+/// Also used for the 404 and 405 fallback responses of the router
+pub async fn cors<F: std::future::Future<Output = Response>>(
+  options: Option<&CorsOptions>,
+  req: Request,
+  handler: impl FnOnce(Request) -> F,
+) -> Response {
+  let options = match options {
+    Some(options) => options,
+    None => return handler(req).await,
+  };
+
+  let origin = req.headers().get(ORIGIN).cloned();
+  let mut res = handler(req).await;
+
+  let headers = res.headers_mut();
+  if options.add_origin_headers(origin.as_ref(), headers) && !options.expose_headers.is_empty() {
+    let list = options.expose_headers.iter().map(|h| h.as_str()).collect::<Vec<_>>().join(", ");
+    if let Ok(list) = HeaderValue::from_str(&list) {
+      headers.insert(ACCESS_CONTROL_EXPOSE_HEADERS, list);
+    }
+  }
+
+  res
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn request(origin: &'static str) -> Request {
+    Request::builder().method(Method::OPTIONS).uri("/users").header(ORIGIN, origin).body(Default::default()).unwrap()
+  }
+
+  #[test]
+  #[should_panic(expected = "CORS credentials require a list of allowed origins")]
+  fn any_origin_with_credentials() {
+    let _ = CorsOptions::default().with_credentials();
+  }
+
+  #[test]
+  fn any_origin() {
+    let res = CorsOptions::default().preflight(&request("https://evil.example.test"), &[Method::GET]);
+    let headers = res.headers();
+    assert_eq!(headers[ACCESS_CONTROL_ALLOW_ORIGIN], "*");
+    assert_eq!(headers.get(ACCESS_CONTROL_ALLOW_CREDENTIALS), None);
+    assert_eq!(headers.get(VARY), None);
+  }
+
+  #[test]
+  fn origins_with_credentials() {
+    let options = CorsOptions::with_origins(vec![String::from("https://app.example.test")]).with_credentials();
+
+    let res = options.preflight(&request("https://app.example.test"), &[Method::GET, Method::POST]);
+    let headers = res.headers();
+    assert_eq!(headers[ACCESS_CONTROL_ALLOW_ORIGIN], "https://app.example.test");
+    assert_eq!(headers[ACCESS_CONTROL_ALLOW_CREDENTIALS], "true");
+    assert_eq!(headers[ACCESS_CONTROL_ALLOW_METHODS], "GET, POST");
+    assert_eq!(headers[VARY], "origin");
+
+    let res = options.preflight(&request("https://evil.example.test"), &[Method::GET]);
+    let headers = res.headers();
+    assert_eq!(headers.get(ACCESS_CONTROL_ALLOW_ORIGIN), None);
+    assert_eq!(headers.get(ACCESS_CONTROL_ALLOW_CREDENTIALS), None);
+    assert_eq!(headers[VARY], "origin");
+  }
+}
@@ -0,0 +1,380 @@
+use std::collections::{BTreeSet, HashSet};
+
+use serde_json::Value;
+
+use crate::schema::{resolve, types};
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd)]
+pub enum Severity {
+  /// Existing clients may fail with the new spec
+  Breaking,
+  NonBreaking,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Change {
+  pub severity: Severity,
+  /// Where the change happened, eg: `GET /users/{id} response .items[].email`
+  pub location: String,
+  pub message: String,
+}
+
+impl std::fmt::Display for Change {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    let severity = match self.severity {
+      Severity::Breaking => "breaking",
+      Severity::NonBreaking => "non-breaking",
+    };
+    write!(f, "[{severity}] {}: {}", self.location, self.message)
+  }
+}
+
+/// Which side produces the values of a schema, a change that is safe for one side breaks the other
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+enum Direction {
+  /// Sent by the clients, narrowing what's accepted is breaking
+  Request,
+  /// Sent by the server, widening what's produced is breaking
+  Response,
+}
+
+struct Differ<'a> {
+  old_root: &'a Value,
+  new_root: &'a Value,
+  changes: Vec<Change>,
+  /// The `(old, new)` pairs of `$ref`s already compared in each direction, recursive schemas are compared once
+  visited: HashSet<(Direction, String, String)>,
+}
+
+/// Compare two OpenAPI specs exported by [`crate::registry::Registry::openapi_spec`]
+///
+/// Changes are sorted with the breaking ones first
+pub fn diff(old: &Value, new: &Value) -> Vec<Change> {
+  let mut differ = Differ { old_root: old, new_root: new, changes: vec![], visited: HashSet::new() };
+  differ.paths();
+  differ.changes.sort_by_key(|change| change.severity);
+  differ.changes
+}
+
+pub fn has_breaking(changes: &[Change]) -> bool {
+  changes.iter().any(|change| change.severity == Severity::Breaking)
+}
+
+const METHODS: &[&str] = &["get", "put", "post", "delete", "options", "head", "patch", "trace"];
+
+/// The types accepted by `schema`, including `null` when it's `nullable`
+fn accepted_types(schema: &Value) -> BTreeSet<String> {
+  let mut types = types(schema).into_iter().map(String::from).collect::<BTreeSet<_>>();
+  if schema.get("nullable").and_then(|v| v.as_bool()) == Some(true) && !types.is_empty() {
+    types.insert(String::from("null"));
+  }
+  // an integer is always accepted where a number is
+  if types.contains("number") {
+    types.remove("integer");
+  }
+  types
+}
+
+fn required(schema: &Value) -> BTreeSet<String> {
+  schema
+    .get("required")
+    .and_then(|v| v.as_array())
+    .map(|required| required.iter().filter_map(|v| v.as_str()).map(String::from).collect())
+    .unwrap_or_default()
+}
+
+fn json_set(value: Option<&Value>) -> Option<Vec<&Value>> {
+  value.and_then(|v| v.as_array()).map(|values| values.iter().collect())
+}
+
+impl Differ<'_> {
+  fn push(&mut self, severity: Severity, location: &str, message: String) {
+    self.changes.push(Change { severity, location: location.to_string(), message });
+  }
+
+  fn paths(&mut self) {
+    let empty = serde_json::Map::new();
+    let old_paths = self.old_root["paths"].as_object().unwrap_or(&empty);
+    let new_paths = self.new_root["paths"].as_object().unwrap_or(&empty);
+
+    for (path, old_methods) in old_paths {
+      for method in METHODS {
+        let old = match old_methods.get(*method) {
+          Some(old) => old,
+          None => continue,
+        };
+        let location = format!("{} {path}", method.to_uppercase());
+        match new_paths.get(path).and_then(|methods| methods.get(*method)) {
+          Some(new) => self.endpoint(&location, old, new),
+          None => self.push(Severity::Breaking, &location, String::from("endpoint removed")),
+        }
+      }
+    }
+
+    for (path, new_methods) in new_paths {
+      for method in METHODS {
+        let exists = old_paths.get(path).and_then(|methods| methods.get(*method)).is_some();
+        if new_methods.get(*method).is_some() && !exists {
+          let location = format!("{} {path}", method.to_uppercase());
+          self.push(Severity::NonBreaking, &location, String::from("endpoint added"));
+        }
+      }
+    }
+  }
+
+  fn endpoint(&mut self, location: &str, old: &Value, new: &Value) {
+    self.parameters(location, old, new);
+
+    let body = |endpoint: &Value| endpoint.pointer("/requestBody/content/application~1json/schema").cloned();
+    match (body(old), body(new)) {
+      (Some(old), Some(new)) => {
+        self.schema(&format!("{location} payload"), Direction::Request, &old, &new);
+      }
+      (None, Some(_)) => self.push(Severity::Breaking, location, String::from("payload added")),
+      (Some(_), None) => self.push(Severity::NonBreaking, location, String::from("payload removed")),
+      (None, None) => {}
+    }
+
+    let output = |endpoint: &Value| endpoint.pointer("/responses/200/content/application~1json/schema").cloned();
+    if let (Some(old), Some(new)) = (output(old), output(new)) {
+      self.schema(&format!("{location} response"), Direction::Response, &old, &new);
+    }
+  }
+
+  fn parameters(&mut self, location: &str, old: &Value, new: &Value) {
+    let list = |endpoint: &Value| {
+      endpoint["parameters"]
+        .as_array()
+        .map(|params| {
+          params
+            .iter()
+            .filter_map(|p| Some(((p["in"].as_str()?.to_string(), p["name"].as_str()?.to_string()), p.clone())))
+            .collect::<Vec<_>>()
+        })
+        .unwrap_or_default()
+    };
+    let old_params = list(old);
+    let new_params = list(new);
+
+    for ((place, name), old_param) in &old_params {
+      let param_location = format!("{location} {place} parameter `{name}`");
+      let new_param = new_params.iter().find(|(key, _)| key.0 == *place && key.1 == *name).map(|(_, p)| p);
+      match new_param {
+        None => self.push(Severity::NonBreaking, &param_location, String::from("parameter removed")),
+        Some(new_param) => {
+          let old_required = old_param["required"].as_bool() == Some(true);
+          let new_required = new_param["required"].as_bool() == Some(true);
+          if !old_required && new_required {
+            self.push(Severity::Breaking, &param_location, String::from("parameter became required"));
+          } else if old_required && !new_required {
+            self.push(Severity::NonBreaking, &param_location, String::from("parameter became optional"));
+          }
+          self.schema(&param_location, Direction::Request, &old_param["schema"], &new_param["schema"]);
+        }
+      }
+    }
+
+    for ((place, name), new_param) in &new_params {
+      if old_params.iter().any(|(key, _)| key.0 == *place && key.1 == *name) {
+        continue;
+      }
+      let param_location = format!("{location} {place} parameter `{name}`");
+      if new_param["required"].as_bool() == Some(true) {
+        self.push(Severity::Breaking, &param_location, String::from("required parameter added"));
+      } else {
+        self.push(Severity::NonBreaking, &param_location, String::from("optional parameter added"));
+      }
+    }
+  }
+
+  fn schema(&mut self, location: &str, direction: Direction, old: &Value, new: &Value) {
+    let reference = |schema: &Value| schema.get("$ref").and_then(|v| v.as_str()).map(String::from);
+    let (old_ref, new_ref) = (reference(old), reference(new));
+    if (old_ref.is_some() || new_ref.is_some()) && !self.visited.insert((direction, old_ref.unwrap_or_default(), new_ref.unwrap_or_default())) {
+      return;
+    }
+
+    let old = resolve(self.old_root, old).clone();
+    let new = resolve(self.new_root, new).clone();
+    if old == new {
+      return;
+    }
+
+    // narrowing breaks requests, widening breaks responses
+    let (narrowed, widened) = match direction {
+      Direction::Request => (Severity::Breaking, Severity::NonBreaking),
+      Direction::Response => (Severity::NonBreaking, Severity::Breaking),
+    };
+
+    let old_types = accepted_types(&old);
+    let new_types = accepted_types(&new);
+    if !old_types.is_empty() || !new_types.is_empty() {
+      let removed = old_types.difference(&new_types).cloned().collect::<Vec<_>>();
+      let added = new_types.difference(&old_types).cloned().collect::<Vec<_>>();
+      if !removed.is_empty() && !added.is_empty() && removed.iter().all(|t| t != "null") && added.iter().all(|t| t != "null") {
+        self.push(Severity::Breaking, location, format!("type changed from {} to {}", join(&old_types), join(&new_types)));
+        return;
+      }
+      // a schema without a type accepts anything
+      if !removed.is_empty() && !new_types.is_empty() {
+        self.push(narrowed, location, format!("type no longer allows {}", removed.join(", ")));
+      }
+      if !added.is_empty() && !old_types.is_empty() {
+        self.push(widened, location, format!("type now allows {}", added.join(", ")));
+      }
+    }
+
+    self.enums(location, narrowed, widened, &old, &new);
+    self.bounds(location, narrowed, widened, &old, &new);
+
+    if old.get("pattern") != new.get("pattern") {
+      let severity = match (old.get("pattern"), new.get("pattern")) {
+        (Some(_), None) => widened,
+        (None, Some(_)) => narrowed,
+        // can't tell whether a regex is narrower than another
+        _ => Severity::Breaking,
+      };
+      self.push(severity, location, format!("pattern changed from {} to {}", display(old.get("pattern")), display(new.get("pattern"))));
+    }
+
+    for key in ["anyOf", "oneOf"] {
+      if let (Some(old_variants), Some(new_variants)) = (json_set(old.get(key)), json_set(new.get(key))) {
+        let removed = old_variants.iter().filter(|v| !new_variants.contains(v)).count();
+        let added = new_variants.iter().filter(|v| !old_variants.contains(v)).count();
+        if removed > 0 {
+          self.push(narrowed, location, format!("{removed} {key} variant(s) removed or changed"));
+        }
+        if added > 0 {
+          self.push(widened, location, format!("{added} {key} variant(s) added or changed"));
+        }
+      }
+    }
+
+    self.properties(location, direction, narrowed, widened, &old, &new);
+
+    if let (Some(old_items), Some(new_items)) = (old.get("items"), new.get("items")) {
+      if old_items.is_object() && new_items.is_object() {
+        self.schema(&format!("{location}[]"), direction, old_items, new_items);
+      }
+    }
+
+    if let (Some(old_additional @ Value::Object(_)), Some(new_additional @ Value::Object(_))) =
+      (old.get("additionalProperties"), new.get("additionalProperties"))
+    {
+      self.schema(&format!("{location}.*"), direction, old_additional, new_additional);
+    }
+  }
+
+  fn enums(&mut self, location: &str, narrowed: Severity, widened: Severity, old: &Value, new: &Value) {
+    match (json_set(old.get("enum")), json_set(new.get("enum"))) {
+      (Some(old_values), Some(new_values)) => {
+        let removed = old_values.iter().filter(|v| !new_values.contains(v)).map(|v| v.to_string()).collect::<Vec<_>>();
+        let added = new_values.iter().filter(|v| !old_values.contains(v)).map(|v| v.to_string()).collect::<Vec<_>>();
+        if !removed.is_empty() {
+          self.push(narrowed, location, format!("enum values removed: {}", removed.join(", ")));
+        }
+        if !added.is_empty() {
+          self.push(widened, location, format!("enum values added: {}", added.join(", ")));
+        }
+      }
+      (None, Some(_)) => self.push(narrowed, location, String::from("restricted to an enum")),
+      (Some(_), None) => self.push(widened, location, String::from("no longer restricted to an enum")),
+      (None, None) => {}
+    }
+  }
+
+  fn bounds(&mut self, location: &str, narrowed: Severity, widened: Severity, old: &Value, new: &Value) {
+    // (keyword, whether a bigger value narrows the accepted values)
+    const BOUNDS: &[(&str, bool)] = &[
+      ("minimum", true),
+      ("exclusiveMinimum", true),
+      ("maximum", false),
+      ("exclusiveMaximum", false),
+      ("minLength", true),
+      ("maxLength", false),
+      ("minItems", true),
+      ("maxItems", false),
+    ];
+
+    for (key, bigger_narrows) in BOUNDS {
+      let old_bound = old.get(*key).and_then(bound);
+      let new_bound = new.get(*key).and_then(bound);
+      // turning an exclusive flag on narrows both the minimum and the maximum
+      let is_flag = matches!(old.get(*key), Some(Value::Bool(_))) || matches!(new.get(*key), Some(Value::Bool(_)));
+      let bigger_narrows = *bigger_narrows || is_flag;
+      let severity = match (old_bound, new_bound) {
+        (Some(old_bound), Some(new_bound)) if old_bound == new_bound => continue,
+        (Some(old_bound), Some(new_bound)) => {
+          if (new_bound > old_bound) == bigger_narrows { narrowed } else { widened }
+        }
+        (None, Some(_)) => narrowed,
+        (Some(_), None) => widened,
+        (None, None) => continue,
+      };
+      self.push(severity, location, format!("{key} changed from {} to {}", display(old.get(*key)), display(new.get(*key))));
+    }
+  }
+
+  fn properties(&mut self, location: &str, direction: Direction, narrowed: Severity, widened: Severity, old: &Value, new: &Value) {
+    let empty = serde_json::Map::new();
+    let old_properties = old.get("properties").and_then(|v| v.as_object()).unwrap_or(&empty);
+    let new_properties = new.get("properties").and_then(|v| v.as_object()).unwrap_or(&empty);
+    let old_required = required(old);
+    let new_required = required(new);
+    let closed = new.get("additionalProperties") == Some(&Value::Bool(false));
+
+    for (name, old_property) in old_properties {
+      let property_location = format!("{location}.{name}");
+      match new_properties.get(name) {
+        None => {
+          let severity = match direction {
+            // the server ignores unknown fields unless the object is closed
+            Direction::Request if !closed => Severity::NonBreaking,
+            _ => Severity::Breaking,
+          };
+          self.push(severity, &property_location, String::from("field removed"));
+        }
+        Some(new_property) => {
+          match (old_required.contains(name), new_required.contains(name)) {
+            (false, true) => self.push(narrowed, &property_location, String::from("field became required")),
+            (true, false) => self.push(widened, &property_location, String::from("field became optional")),
+            _ => {}
+          }
+          self.schema(&property_location, direction, old_property, new_property);
+        }
+      }
+    }
+
+    for name in new_properties.keys() {
+      if old_properties.contains_key(name) {
+        continue;
+      }
+      let property_location = format!("{location}.{name}");
+      if direction == Direction::Request && new_required.contains(name) {
+        self.push(Severity::Breaking, &property_location, String::from("required field added"));
+      } else {
+        self.push(Severity::NonBreaking, &property_location, String::from("field added"));
+      }
+    }
+  }
+}
+
+fn bound(value: &Value) -> Option<f64> {
+  // openapi 3.0 uses booleans for the exclusive bounds
+  match value {
+    Value::Bool(true) => Some(1.0),
+    Value::Bool(false) => Some(0.0),
+    value => value.as_f64(),
+  }
+}
+
+fn join(types: &BTreeSet<String>) -> String {
+  types.iter().cloned().collect::<Vec<_>>().join(" | ")
+}
+
+fn display(value: Option<&Value>) -> String {
+  match value {
+    Some(value) => value.to_string(),
+    None => String::from("none"),
+  }
+}
@@ -1,4 +1,4 @@
-use std::borrow::Cow;
+use std::{borrow::Cow, sync::Arc, time::Duration};
 use bytes::BytesMut;
 use garde::Validate;
 use axum::{
@@ -8,16 +8,25 @@ use axum::{
 };
 use normalize::Normalize;
 use tokio_stream::StreamExt;
+use tokio_util::sync::CancellationToken;
 
 use crate::schema::Schema;
 use crate::void::Void;
 use crate::error::{ApiError, ApiErrorKind, IntoApiError};
+use crate::rate_limit::{RateLimit, RateLimitContext};
+use crate::compression::{content_encoding, DecompressError, Encoding};
+use crate::conditional::{check_preconditions, has_preconditions, Validators};
+use crate::cache::{CachePolicy, CacheVisibility, ResponseCache};
+use crate::idempotency::{IdempotencyContext, IdempotencyPolicy};
 
 pub struct ParsedRequest<Context, Params, Query, Payload> {
   pub context: Context,
   pub params: Params,
   pub query: Query,
   pub payload: Payload,
+  /// Cancelled when the request is aborted, because the client disconnected or the endpoint timed out \
+  /// Long running work should watch it to stop cleanly
+  pub cancel: CancellationToken,
 }
 
 pub trait EndpointError: std::error::Error + IntoApiError {}
@@ -35,10 +44,88 @@ pub trait Endpoint: Send + Sync + 'static {
 
   fn method(&self) -> Method;
 
+  /// A short summary of the endpoint, shown in the openapi spec and the generated TS docs
+  fn summary(&self) -> Option<Cow<'static, str>> {
+    None
+  }
+
+  /// A longer description of the endpoint, markdown is allowed
+  fn description(&self) -> Option<Cow<'static, str>> {
+    None
+  }
+
+  /// Mark the endpoint as deprecated in the openapi spec and with `@deprecated` in the generated TS
+  fn deprecated(&self) -> bool {
+    false
+  }
+
   fn max_payload_size(&self) -> usize {
     2 * 1024 * 1024 // 2MB
   }
 
+  /// Max time to handle a request, `None` to use the registry default
+  fn timeout(&self) -> Option<Duration> {
+    None
+  }
+
+  /// Rate limit for this endpoint, `None` to use the registry group or default limit
+  fn rate_limit(&self) -> Option<RateLimit> {
+    None
+  }
+
+  /// Key that identifies the client for rate limiting, eg: the authenticated principal in `ctx` \
+  /// `None` to use the registry [`crate::rate_limit::RateLimitKey`]
+  fn rate_limit_key(&self, _parts: &Parts, _ctx: &Self::Ctx) -> Option<String> {
+    None
+  }
+
+  /// Send `ETag` and `Last-Modified` headers in GET responses and answer
+  /// `If-None-Match` and `If-Modified-Since` requests with 304 Not Modified \
+  /// The ETag is a hash of the serialized output unless [`Endpoint::validators`] provides one
+  fn conditional(&self) -> bool {
+    false
+  }
+
+  /// The validators of an output, eg: from a version or an updated at column of the record
+  fn validators(&self, _output: &Self::Output) -> Validators {
+    Validators::default()
+  }
+
+  /// The current validators of the resource targeted by a state changing request (PUT, PATCH, DELETE...),
+  /// used to evaluate `If-Match` and `If-Unmodified-Since`, `None` to ignore the preconditions \
+  /// Borrowed mutably so `Ctx` and `Params` only need to be `Send`
+  async fn current_validators(
+    &self,
+    _ctx: &mut Self::Ctx,
+    _params: &mut Self::Params,
+  ) -> Result<Option<Validators>, Box<dyn EndpointError>> {
+    Ok(None)
+  }
+
+  /// Cache the output of GET requests in the registry [`ResponseCache`], keyed by params and query \
+  /// Only use it for deterministic endpoints, the policy also sets the `Cache-Control` response header
+  fn cache(&self) -> Option<CachePolicy> {
+    None
+  }
+
+  /// Extra cache key for outputs that depend on the `ctx`, eg: the user id or the tenant \
+  /// Required to store [`CacheVisibility::Private`] outputs in the server, they are not cached when `None`
+  fn cache_vary(&self, _ctx: &Self::Ctx) -> Option<String> {
+    None
+  }
+
+  /// Honor the `Idempotency-Key` request header using the registry [`crate::idempotency::IdempotencyStore`] \
+  /// Retries with the same key and payload get the first response replayed instead of running the endpoint again
+  fn idempotency(&self) -> Option<IdempotencyPolicy> {
+    None
+  }
+
+  /// Principal the idempotency keys are scoped to with [`crate::idempotency::IdempotencyScope::Principal`], eg: the user or the api client id in `ctx` \
+  /// Requests with an `Idempotency-Key` are rejected when `None`
+  fn idempotency_scope(&self, _parts: &Parts, _ctx: &Self::Ctx) -> Option<String> {
+    None
+  }
+
   async fn ctx(&self, parts: &mut Parts) -> Result<Self::Ctx, Box<dyn EndpointError>>;
 
   async fn run(
@@ -51,20 +138,34 @@ pub trait Endpoint: Send + Sync + 'static {
     >
   ) -> Result<Self::Output, Box<dyn EndpointError>>;
 
-  async fn handle(&self, req: Request) -> Result<Self::Output, ApiError> {
-    let (mut parts, body) = req.into_parts();
-
-    let ctx = match self.ctx(&mut parts).await {
+  /// Run the `ctx` and the rate limit check, then parse, normalize and validate the params, query and payload
+  async fn parse(
+    &self,
+    parts: &mut Parts,
+    body: Body,
+    cancel: CancellationToken,
+  ) -> Result<ParsedRequest<Self::Ctx, Self::Params, Self::Query, Self::Payload>, ApiError> {
+    let mut ctx = match self.ctx(parts).await {
       Ok(ctx) => ctx,
       Err(err) => {
         return Err(err.into_api_error())
       }
     };
 
-    let params = match Self::Params::void() {
+    if let Some(rate_limit) = parts.extensions.get::<RateLimitContext>().cloned() {
+      let key = self.rate_limit_key(parts, &ctx);
+      rate_limit.check(self.rate_limit(), key, parts).await?;
+    }
+
+    if let Some(idempotency) = parts.extensions.get::<IdempotencyContext>().cloned() {
+      let principal = self.idempotency_scope(parts, &ctx);
+      idempotency.claim(principal.as_deref()).await?;
+    }
+
+    let mut params = match Self::Params::void() {
       Some(void) => void,
       None => {
-        let mut params = match Path::<Self::Params>::from_request_parts(&mut parts, &()).await {
+        let mut params = match Path::<Self::Params>::from_request_parts(parts, &()).await {
           Ok(Path(params)) => params,
           Err(err) => {
             return Err(ApiError {
@@ -90,6 +191,14 @@ pub trait Endpoint: Send + Sync + 'static {
         params
       }
     };
+
+    if parts.method != Method::GET && parts.method != Method::HEAD && has_preconditions(&parts.headers) {
+      match self.current_validators(&mut ctx, &mut params).await {
+        Ok(Some(current)) => check_preconditions(&parts.headers, &current)?,
+        Ok(None) => {},
+        Err(err) => return Err(err.into_api_error()),
+      }
+    }
     
 
     let query = match Self::Query::void() {
@@ -97,7 +206,7 @@ pub trait Endpoint: Send + Sync + 'static {
       None => {
         // axum extractor
         // use axum::extract::Query;
-        // let query = match Query::<Self::Query>::from_request_parts(&mut parts, &()).await {
+        // let query = match Query::<Self::Query>::from_request_parts(parts, &()).await {
         //   Ok(Query(query)) => query,
         //   Err(err) => {
         //     return ApiError {
@@ -153,7 +262,7 @@ pub trait Endpoint: Send + Sync + 'static {
         //   }
         // };
 
-        if !is_content_type_json(&parts) {
+        if !is_content_type_json(parts) {
           return Err(ApiError {
             status: StatusCode::BAD_REQUEST.as_u16(),
             kind: ApiErrorKind::PayloadContentType,
@@ -161,7 +270,18 @@ pub trait Endpoint: Send + Sync + 'static {
           })
         }
 
-        let buf = read_body(self.max_payload_size(), body).await?;
+        let encoding = match content_encoding(&parts.headers) {
+          Ok(encoding) => encoding,
+          Err(encoding) => {
+            return Err(ApiError {
+              status: StatusCode::UNSUPPORTED_MEDIA_TYPE.as_u16(),
+              kind: ApiErrorKind::PayloadContentEncoding,
+              message: format!("unsupported content-encoding `{encoding}` of request, must be one of gzip, deflate, br or zstd"),
+            })
+          }
+        };
+
+        let buf = read_body(self.max_payload_size(), encoding, body).await?;
 
         let mut payload = match serde_json::from_slice::<Self::Payload>(&buf) {
           Ok(payload) => payload,
@@ -189,14 +309,48 @@ pub trait Endpoint: Send + Sync + 'static {
         payload
       }
     };
-    
-    let parsed = ParsedRequest {
+
+    Ok(ParsedRequest {
       context: ctx,
       params,
       query,
       payload,
+      cancel,
+    })
+  }
+
+  async fn handle(&self, req: Request) -> Result<Self::Output, ApiError> {
+    // if this future is dropped before completion (client disconnect or timeout) the token is cancelled
+    let cancel = CancellationToken::new();
+    let _cancel_guard = cancel.clone().drop_guard();
+
+    let (mut parts, body) = req.into_parts();
+
+    let parsed = self.parse(&mut parts, body, cancel).await?;
+
+    let cache = match (self.cache(), parts.extensions.get::<Arc<ResponseCache>>()) {
+      (Some(policy), Some(cache)) if parts.method == Method::GET || parts.method == Method::HEAD => {
+        match (policy.visibility, self.cache_vary(&parsed.context)) {
+          // a private output shared between principals would leak, it is only cached per vary key
+          (CacheVisibility::Private, None) => None,
+          (_, vary) => {
+            ResponseCache::key(&self.method(), &self.path(), &parsed.params, &parsed.query, vary.as_deref())
+              .map(|key| (policy, cache.clone(), key))
+          }
+        }
+      }
+      _ => None,
     };
 
+    if let Some((_, cache, key)) = &cache {
+      if let Some(bytes) = cache.store.get(key).await {
+        // a value that doesn't deserialize anymore (eg: after a deploy) is just a miss
+        if let Ok(out) = serde_json::from_slice::<Self::Output>(&bytes) {
+          return Ok(out);
+        }
+      }
+    }
+
     let mut out = match self.run(parsed).await {
       Ok(out) => out,
       Err(err) => {
@@ -207,11 +361,19 @@ pub trait Endpoint: Send + Sync + 'static {
     // we do not garde(validate) the output but we DO normalize it
     out.normalize();
 
+    if let Some((policy, cache, key)) = cache {
+      if let Ok(bytes) = serde_json::to_vec(&out) {
+        cache.store.set(&key, bytes.into(), policy.ttl).await;
+      }
+    }
+
     Ok(out)
   } 
 }
 
-async fn read_body(max_size: usize, body: Body) -> Result<Bytes, ReadBodyError> {
+/// Read the body, decompressing it if `encoding` is set \
+/// `max_size` applies both to the received and to the decompressed size
+async fn read_body(max_size: usize, encoding: Option<Encoding>, body: Body) -> Result<Bytes, ReadBodyError> {
   let mut bytes = BytesMut::new();
   let mut stream = body.into_data_stream();
   while let Some(chunk) = stream.try_next().await? {
@@ -221,7 +383,16 @@ async fn read_body(max_size: usize, body: Body) -> Result<Bytes, ReadBodyError>
     } 
     bytes.extend_from_slice(&chunk);
   }
-  Ok(bytes.freeze())
+
+  match encoding {
+    None => Ok(bytes.freeze()),
+    Some(encoding) => {
+      let decompressed = tokio::task::spawn_blocking(move || encoding.decompress(&bytes, max_size))
+        .await
+        .map_err(|e| ReadBodyError::Decompress(DecompressError::Io(std::io::Error::other(e))))??;
+      Ok(decompressed.into())
+    }
+  }
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -230,15 +401,24 @@ pub enum ReadBodyError {
   MaxSizeExceeded(usize),
   #[error(transparent)]
   Read(#[from] axum::Error),
+  #[error(transparent)]
+  Decompress(#[from] DecompressError),
 }
 
 impl From<ReadBodyError> for ApiError {
   fn from(e: ReadBodyError) -> Self {
-      ApiError {
+    match e {
+      ReadBodyError::MaxSizeExceeded(_) | ReadBodyError::Decompress(DecompressError::MaxSizeExceeded(_)) => ApiError {
+        status: StatusCode::PAYLOAD_TOO_LARGE.as_u16(),
+        kind: ApiErrorKind::PayloadTooLarge,
+        message: format!("error reading payload: {e}"),
+      },
+      e => ApiError {
         status: StatusCode::BAD_REQUEST.as_u16(),
         kind: ApiErrorKind::PayloadRead,
         message: format!("error reading payload: {e}"),
-      }
+      },
+    }
   }
 }
 
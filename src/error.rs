@@ -27,6 +27,8 @@ pub enum ApiErrorKind {
   
   ResourceNotFound,
   RecordNotFound,
+  MethodNotAllowed,
+  PreconditionFailed,
 
   InvalidParamsParse,
   InvalidParamsValidate,
@@ -35,9 +37,19 @@ pub enum ApiErrorKind {
   InvalidQueryValidate,
 
   PayloadRead,
+  PayloadTooLarge,
   PayloadContentType,
+  PayloadContentEncoding,
   InvalidPayloadParse,
   InvalidPayloadValidate,
+
+  RateLimited,
+
+  IdempotencyKeyInvalid,
+  IdempotencyKeyInProgress,
+  IdempotencyKeyMismatch,
+
+  Timeout,
 }
 
 
@@ -45,22 +57,38 @@ pub trait IntoApiError {
   fn into_api_error(self: Box<Self>) -> ApiError;
 }
 
+/// The body of every error response
 #[derive(Debug, Serialize, Deserialize, JsonSchema, Validate, Shape, Normalize)]
 pub struct ApiErrorPayload {
   #[normalize(dive)]
   #[garde(dive)]
   pub error: ApiError,
+  /// The id of the request that produced this error, see [`crate::trace::RequestId`]
+  #[normalize(skip)]
+  #[garde(skip)]
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub request_id: Option<String>,
 }
 
-impl IntoResponse for ApiError {
-  fn into_response(self) -> Response {
+impl ApiError {
+  /// Same as [`IntoResponse::into_response`] but including the request id in the error payload
+  pub fn into_response_with_request_id(self, request_id: Option<String>) -> Response {
     let status = self.status;
-    let mut payload = ApiErrorPayload { error: self };
+    // the error is kept in the response extensions so the registry layers (tracing, etc) can inspect it
+    let error = self.clone();
+    let mut payload = ApiErrorPayload { error: self, request_id };
     payload.normalize();
     let body = serde_json::to_vec(&payload).expect("error serializing api error");
     let mut res = Response::new(body.into());
     *res.status_mut() = status.try_into().expect("invalid status code in api error");
     res.headers_mut().insert(CONTENT_TYPE, HeaderValue::from_static("application/json")); 
+    res.extensions_mut().insert(error);
     res
   }
+}
+
+impl IntoResponse for ApiError {
+  fn into_response(self) -> Response {
+    self.into_response_with_request_id(None)
+  }
 }
\ No newline at end of file
@@ -0,0 +1,380 @@
+use std::{
+  path::{Path, PathBuf},
+  sync::Arc,
+};
+
+use serde_json::{json, Value};
+
+use crate::codegen::{openapi::OpenApi, ts::Ts, Generator};
+use crate::registry::Registry;
+
+/// A file format the registry can be exported to
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Format {
+  OpenApiJson,
+  OpenApiYaml,
+  /// The `Api` definitions used by typed clients
+  Ts,
+  /// The `Api` definitions with a small fetch based client
+  TsClient,
+  /// Every endpoint schema in a single JSON Schema document under `$defs`
+  JsonSchema,
+  /// A directory with one `$id` addressable JSON Schema document per schema
+  JsonSchemaFiles,
+  /// Zod runtime validators for every endpoint input and output
+  Zod,
+  /// A directory with the files of a [`Generator`], the built-in ones or a custom one, eg: `Format::Generated(Arc::new(MyGenerator))`
+  Generated(Arc<dyn Generator>),
+}
+
+impl Format {
+  const BUILT_IN: &[Format] = &[
+    Format::OpenApiJson,
+    Format::OpenApiYaml,
+    Format::Ts,
+    Format::TsClient,
+    Format::JsonSchema,
+    Format::JsonSchemaFiles,
+    Format::Zod,
+  ];
+
+  /// Every format, including the built-in generators of [`crate::codegen::generators`]
+  pub fn all() -> Vec<Format> {
+    Self::BUILT_IN
+      .iter()
+      .cloned()
+      .chain(crate::codegen::generators().into_iter().map(Format::Generated))
+      .collect()
+  }
+
+  pub fn name(&self) -> &'static str {
+    match self {
+      Format::OpenApiJson => "openapi-json",
+      Format::OpenApiYaml => "openapi-yaml",
+      Format::Ts => "ts",
+      Format::TsClient => "ts-client",
+      Format::JsonSchema => "json-schema",
+      Format::JsonSchemaFiles => "json-schema-files",
+      Format::Zod => "zod",
+      Format::Generated(generator) => generator.name(),
+    }
+  }
+
+  pub fn from_name(name: &str) -> Option<Self> {
+    Self::all().into_iter().find(|format| format.name() == name)
+  }
+
+  /// The file or directory name used when no path is given, generators use their name
+  pub fn default_file_name(&self) -> &'static str {
+    match self {
+      Format::OpenApiJson => "openapi.json",
+      Format::OpenApiYaml => "openapi.yaml",
+      Format::Ts => "api.ts",
+      Format::TsClient => "client.ts",
+      Format::JsonSchema => "schemas.json",
+      Format::JsonSchemaFiles => "schemas",
+      Format::Zod => "zod.ts",
+      Format::Generated(generator) => generator.name(),
+    }
+  }
+
+  /// Whether the target path is a directory of files instead of a single file
+  pub fn is_dir(&self) -> bool {
+    matches!(self, Format::JsonSchemaFiles | Format::Generated(_))
+  }
+
+  /// Render the registry in this format as the files to write under `path`, the output is formatted and deterministic
+  pub fn render(&self, registry: &Registry, path: &Path, options: &ExportOptions) -> Vec<(PathBuf, String)> {
+    let single = |content: String| vec![(path.to_path_buf(), content)];
+    // the single file of a generator, written to `path` itself
+    let file = |generator: &dyn Generator| single(generator.generate(&registry.ir()).remove(0).contents);
+    match self {
+      Format::OpenApiJson => file(&OpenApi { yaml: false }),
+      Format::OpenApiYaml => file(&OpenApi { yaml: true }),
+      Format::Ts => file(&Ts { client: false }),
+      Format::TsClient => file(&Ts { client: true }),
+      Format::JsonSchema => single(json_text(&json_schema_bundle(registry))),
+      Format::Zod => single(text(&registry.zod_definitions())),
+      Format::Generated(generator) => generated(generator.as_ref(), registry, path),
+      Format::JsonSchemaFiles => registry
+        .json_schemas(&options.schema_base)
+        .into_iter()
+        .map(|(name, schema)| (path.join(format!("{name}{SCHEMA_FILE_SUFFIX}")), json_text(&schema)))
+        .collect(),
+    }
+  }
+}
+
+const SCHEMA_FILE_SUFFIX: &str = ".schema.json";
+
+/// The files of a generator under `dir`, eg: to write the output of a custom [`Generator`]
+pub fn generated(generator: &dyn Generator, registry: &Registry, dir: &Path) -> Vec<(PathBuf, String)> {
+  generator
+    .generate(&registry.ir())
+    .into_iter()
+    .map(|file| (dir.join(file.path), file.contents))
+    .collect()
+}
+
+/// Options shared by every target
+#[derive(Debug, Clone, Default)]
+pub struct ExportOptions {
+  /// Prefix of the `$id` of standalone JSON Schema files, eg: `https://example.com/schemas/`
+  pub schema_base: String,
+}
+
+/// An export target, a format written to a path
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Target {
+  pub format: Format,
+  pub path: PathBuf,
+}
+
+impl Target {
+  pub fn new(format: Format, path: impl Into<PathBuf>) -> Self {
+    Self { format, path: path.into() }
+  }
+
+  /// Parse `format` or `format=path`, relative paths and default file names are resolved against `dir`
+  pub fn parse(value: &str, dir: &Path) -> Result<Self, String> {
+    let (name, path) = match value.split_once('=') {
+      Some((name, path)) => (name, Some(path)),
+      None => (value, None),
+    };
+
+    let format = match Format::from_name(name) {
+      Some(format) => format,
+      None => {
+        let names = Format::all().iter().map(|f| f.name()).collect::<Vec<_>>().join(", ");
+        return Err(format!("unknown format `{name}`, expected one of {names}"));
+      }
+    };
+
+    let path = match path {
+      Some(path) => dir.join(path),
+      None => dir.join(format.default_file_name()),
+    };
+
+    Ok(Self { format, path })
+  }
+}
+
+/// The result of comparing a target with the file on disk
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Status {
+  UpToDate,
+  Stale,
+  Missing,
+}
+
+/// Write every target, creating the parent directories
+///
+/// Directory targets also lose the generated files that are no longer rendered, eg: of a removed endpoint
+pub fn write(registry: &Registry, targets: &[Target], options: &ExportOptions) -> std::io::Result<()> {
+  for target in targets {
+    let files = target.format.render(registry, &target.path, options);
+    for leftover in leftover_files(target, &files) {
+      std::fs::remove_file(leftover)?;
+    }
+    for (path, content) in files {
+      if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+      }
+      std::fs::write(&path, content)?;
+    }
+  }
+  Ok(())
+}
+
+/// Compare every target with the files on disk without writing anything
+pub fn check(registry: &Registry, targets: &[Target], options: &ExportOptions) -> Vec<(Target, Status)> {
+  targets
+    .iter()
+    .map(|target| {
+      if target.format.is_dir() && !target.path.is_dir() {
+        return (target.clone(), Status::Missing);
+      }
+
+      let files = target.format.render(registry, &target.path, options);
+      let mut status = match leftover_files(target, &files).is_empty() {
+        true => Status::UpToDate,
+        false => Status::Stale,
+      };
+      for (path, content) in &files {
+        match std::fs::read_to_string(path) {
+          Ok(current) if current == *content => {}
+          Ok(_) => status = Status::Stale,
+          // a single missing schema file makes the directory stale, not missing
+          Err(_) if target.format.is_dir() => status = Status::Stale,
+          Err(_) => return (target.clone(), Status::Missing),
+        }
+      }
+      (target.clone(), status)
+    })
+    .collect()
+}
+
+/// Generated schema files in a directory target that are not part of `files`
+fn leftover_files(target: &Target, files: &[(PathBuf, String)]) -> Vec<PathBuf> {
+  if target.format != Format::JsonSchemaFiles {
+    return vec![];
+  }
+
+  let entries = match std::fs::read_dir(&target.path) {
+    Ok(entries) => entries,
+    Err(_) => return vec![],
+  };
+
+  entries
+    .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+    .filter(|path| path.to_string_lossy().ends_with(SCHEMA_FILE_SUFFIX))
+    .filter(|path| !files.iter().any(|(file, _)| file == path))
+    .collect()
+}
+
+/// Pretty printed json with a trailing newline
+pub fn json_text(value: &Value) -> String {
+  let mut out = serde_json::to_string_pretty(value).expect("error serializing json");
+  out.push('\n');
+  out
+}
+
+/// Normalize generated source text: unix line endings, no trailing whitespace,
+/// no repeated blank lines and a single trailing newline
+pub fn text(source: &str) -> String {
+  let mut out = String::with_capacity(source.len());
+  let mut blank = false;
+  for line in source.replace("\r\n", "\n").lines() {
+    let line = line.trim_end();
+    if line.is_empty() {
+      if blank || out.is_empty() {
+        continue;
+      }
+      blank = true;
+    } else {
+      blank = false;
+    }
+    out.push_str(line);
+    out.push('\n');
+  }
+  while out.ends_with("\n\n") {
+    out.pop();
+  }
+  out
+}
+
+/// Every params, query, payload and output schema plus the error payload as a single JSON Schema document
+pub fn json_schema_bundle(registry: &Registry) -> Value {
+  let mut defs = serde_json::Map::new();
+  for (name, mut schema) in registry.json_schemas("") {
+    if let Value::Object(schema) = &mut schema {
+      schema.remove("$schema");
+      schema.remove("$id");
+    }
+    defs.insert(name, schema);
+  }
+
+  json!({
+    "$schema": "https://json-schema.org/draft/2020-12/schema",
+    "$defs": defs,
+  })
+}
+
+/// A PascalCase name for the schemas of an endpoint, eg: `GET /users/:id` => `GetUsersId`
+pub fn schema_name_prefix(method: &str, path: &str) -> String {
+  let mut out = String::new();
+  for word in std::iter::once(method).chain(path.split(|c: char| !c.is_ascii_alphanumeric())) {
+    let mut chars = word.chars();
+    if let Some(first) = chars.next() {
+      out.push(first.to_ascii_uppercase());
+      out.extend(chars.map(|c| c.to_ascii_lowercase()));
+    }
+  }
+  out
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn statuses(registry: &Registry, targets: &[Target]) -> Vec<Status> {
+    check(registry, targets, &ExportOptions::default()).into_iter().map(|(_, status)| status).collect()
+  }
+
+  #[test]
+  fn parse_target() {
+    let dir = Path::new("/out");
+    assert_eq!(Target::parse("openapi-json", dir), Ok(Target::new(Format::OpenApiJson, "/out/openapi.json")));
+    assert_eq!(Target::parse("ts=web/api.ts", dir), Ok(Target::new(Format::Ts, "/out/web/api.ts")));
+    assert_eq!(Target::parse("zod=/abs/zod.ts", dir), Ok(Target::new(Format::Zod, "/abs/zod.ts")));
+
+    let python = Target::parse("python", dir).unwrap();
+    assert_eq!(python.format.name(), "python");
+    assert_eq!(python.path, Path::new("/out/python"));
+    assert!(python.format.is_dir());
+
+    let err = Target::parse("nope=x", dir).unwrap_err();
+    assert!(err.starts_with("unknown format `nope`, expected one of openapi-json, "), "{err}");
+  }
+
+  #[test]
+  fn check_file() {
+    let registry = crate::api::registry();
+    let dir = tempfile::tempdir().unwrap();
+    let targets = [Target::new(Format::OpenApiJson, dir.path().join("nested/openapi.json"))];
+
+    assert_eq!(statuses(&registry, &targets), [Status::Missing]);
+
+    write(&registry, &targets, &ExportOptions::default()).unwrap();
+    assert_eq!(statuses(&registry, &targets), [Status::UpToDate]);
+
+    // reformatting counts as stale
+    let spec = std::fs::read_to_string(&targets[0].path).unwrap();
+    std::fs::write(&targets[0].path, spec.replace("  ", "    ")).unwrap();
+    assert_eq!(statuses(&registry, &targets), [Status::Stale]);
+  }
+
+  #[test]
+  fn check_dir() {
+    let registry = crate::api::registry();
+    let dir = tempfile::tempdir().unwrap();
+    let schemas = dir.path().join("schemas");
+    let targets = [Target::new(Format::JsonSchemaFiles, &schemas)];
+
+    assert_eq!(statuses(&registry, &targets), [Status::Missing]);
+
+    write(&registry, &targets, &ExportOptions::default()).unwrap();
+    assert_eq!(statuses(&registry, &targets), [Status::UpToDate]);
+    let files = std::fs::read_dir(&schemas).unwrap().count();
+    assert!(files > 0);
+
+    // the schemas of a removed endpoint, other files are not ours
+    std::fs::write(schemas.join(format!("DeleteUsersIdOutput{SCHEMA_FILE_SUFFIX}")), "{}").unwrap();
+    std::fs::write(schemas.join("README.md"), "schemas").unwrap();
+    assert_eq!(statuses(&registry, &targets), [Status::Stale]);
+
+    write(&registry, &targets, &ExportOptions::default()).unwrap();
+    assert_eq!(statuses(&registry, &targets), [Status::UpToDate]);
+    assert!(!schemas.join(format!("DeleteUsersIdOutput{SCHEMA_FILE_SUFFIX}")).exists());
+    assert!(schemas.join("README.md").exists());
+    assert_eq!(std::fs::read_dir(&schemas).unwrap().count(), files + 1);
+
+    // a missing file makes the directory stale
+    let (first, _) = &Format::JsonSchemaFiles.render(&registry, &schemas, &ExportOptions::default())[0];
+    std::fs::remove_file(first).unwrap();
+    assert_eq!(statuses(&registry, &targets), [Status::Stale]);
+  }
+
+  #[test]
+  fn normalized_text() {
+    assert_eq!(text("\n\nline  \r\n\r\n\n  indented\t\n\n"), "line\n\n  indented\n");
+    assert_eq!(text("a\nb"), "a\nb\n");
+    assert_eq!(text(""), "");
+  }
+
+  #[test]
+  fn schema_names() {
+    assert_eq!(schema_name_prefix("GET", "/users/:id"), "GetUsersId");
+    assert_eq!(schema_name_prefix("POST", "/user-groups/*rest"), "PostUserGroupsRest");
+  }
+}
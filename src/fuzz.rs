@@ -0,0 +1,608 @@
+use axum::{
+  body::Body,
+  extract::Request,
+  http::{header::CONTENT_TYPE, HeaderValue, Method, StatusCode},
+};
+use serde_json::{json, Map, Value};
+
+use crate::contract::validate;
+use crate::error::{ApiErrorKind, ApiErrorPayload};
+use crate::registry::Registry;
+use crate::schema::{merge_all_of, resolve, types};
+use crate::testing::TestClient;
+
+/// A small deterministic xorshift generator, fuzz runs are reproducible from their seed
+#[derive(Debug, Clone)]
+pub struct Rng(u64);
+
+impl Rng {
+  pub fn new(seed: u64) -> Self {
+    // xorshift gets stuck on 0
+    Self(seed.max(1))
+  }
+
+  pub fn next_u64(&mut self) -> u64 {
+    let mut x = self.0;
+    x ^= x << 13;
+    x ^= x >> 7;
+    x ^= x << 17;
+    self.0 = x;
+    x
+  }
+
+  /// A number in `min..=max`
+  pub fn range(&mut self, min: i64, max: i64) -> i64 {
+    if max <= min {
+      return min;
+    }
+    let span = (max as i128 - min as i128 + 1) as u128;
+    (min as i128 + (self.next_u64() as u128 % span) as i128) as i64
+  }
+
+  pub fn chance(&mut self, percent: u64) -> bool {
+    self.next_u64() % 100 < percent
+  }
+
+  pub fn pick<'a, T>(&mut self, items: &'a [T]) -> &'a T {
+    &items[self.next_u64() as usize % items.len()]
+  }
+}
+
+const MAX_DEPTH: usize = 6;
+
+/// Generate a value that matches `schema`
+///
+/// `pattern`s can't be generated in general, candidates are retried a few times
+/// and the value may still not match, callers should [`validate`] when it matters
+pub fn generate(root: &Value, schema: &Value, rng: &mut Rng) -> Value {
+  generate_at(root, schema, rng, 0)
+}
+
+fn generate_at(root: &Value, schema: &Value, rng: &mut Rng, depth: usize) -> Value {
+  let schema = resolve(root, schema);
+  if !schema.is_object() {
+    return Value::Null;
+  }
+
+  if let Some(value) = schema.get("const") {
+    return value.clone();
+  }
+
+  if let Some(variants) = schema.get("enum").and_then(|v| v.as_array()).filter(|v| !v.is_empty()) {
+    return rng.pick(variants).clone();
+  }
+
+  for key in ["oneOf", "anyOf"] {
+    if let Some(subs) = schema.get(key).and_then(|v| v.as_array()).filter(|v| !v.is_empty()) {
+      return generate_at(root, rng.pick(subs), rng, depth + 1);
+    }
+  }
+
+  if let Some(merged) = merge_all_of(root, schema) {
+    return generate_at(root, &merged, rng, depth);
+  }
+
+  let nullable = schema.get("nullable").and_then(|v| v.as_bool()) == Some(true);
+  let mut candidates = types(schema);
+  if nullable {
+    candidates.push("null");
+  }
+  // prefer non null values, nested optionals still get some nulls
+  let non_null = candidates.iter().copied().filter(|ty| *ty != "null").collect::<Vec<_>>();
+  let ty = if non_null.is_empty() {
+    match candidates.first() {
+      Some(ty) => *ty,
+      None if schema.get("properties").is_some() => "object",
+      None if schema.get("items").is_some() => "array",
+      None => "string",
+    }
+  } else if non_null.len() < candidates.len() && rng.chance(15) {
+    "null"
+  } else {
+    *rng.pick(&non_null)
+  };
+
+  match ty {
+    "null" => Value::Null,
+    "boolean" => Value::Bool(rng.chance(50)),
+    "integer" => generate_integer(schema, rng),
+    "number" => generate_number(schema, rng),
+    "string" => generate_string(schema, rng),
+    "array" => {
+      let min = schema.get("minItems").and_then(|v| v.as_u64()).unwrap_or(0) as i64;
+      let max = schema.get("maxItems").and_then(|v| v.as_u64()).map(|v| v as i64).unwrap_or(min + 3);
+      let len = if depth >= MAX_DEPTH { min } else { rng.range(min, max.min(min + 3)) };
+      let items = schema.get("items").cloned().unwrap_or(Value::Bool(true));
+      let items = (0..len as usize)
+        .map(|i| match &items {
+          Value::Array(tuple) => tuple.get(i).map(|s| generate_at(root, s, rng, depth + 1)).unwrap_or(Value::Null),
+          items => generate_at(root, items, rng, depth + 1),
+        })
+        .collect();
+      Value::Array(items)
+    }
+    _ => {
+      let mut object = Map::new();
+      let required = required(schema);
+      if let Some(properties) = schema.get("properties").and_then(|v| v.as_object()) {
+        for (name, property) in properties {
+          let is_required = required.contains(&name.as_str());
+          if is_required || (depth < MAX_DEPTH && rng.chance(60)) {
+            object.insert(name.clone(), generate_at(root, property, rng, depth + 1));
+          }
+        }
+      }
+      Value::Object(object)
+    }
+  }
+}
+
+fn required(schema: &Value) -> Vec<&str> {
+  schema
+    .get("required")
+    .and_then(|v| v.as_array())
+    .map(|required| required.iter().filter_map(|v| v.as_str()).collect())
+    .unwrap_or_default()
+}
+
+fn generate_integer(schema: &Value, rng: &mut Rng) -> Value {
+  let exclusive = |key: &str| schema.get(key).and_then(|v| v.as_bool()) == Some(true);
+  let mut min = schema.get("minimum").and_then(|v| v.as_f64()).map(|v| v.ceil() as i64).unwrap_or(-1000);
+  let mut max = schema.get("maximum").and_then(|v| v.as_f64()).map(|v| v.floor() as i64).unwrap_or(min.max(0) + 1000);
+  if exclusive("exclusiveMinimum") {
+    min += 1;
+  }
+  if exclusive("exclusiveMaximum") {
+    max -= 1;
+  }
+  // try the bounds often, that's where bugs live
+  let value = match rng.range(0, 4) {
+    0 => min,
+    1 => max,
+    _ => rng.range(min, max),
+  };
+  json!(value)
+}
+
+fn generate_number(schema: &Value, rng: &mut Rng) -> Value {
+  let min = schema.get("minimum").and_then(|v| v.as_f64()).unwrap_or(-1000.0);
+  let max = schema.get("maximum").and_then(|v| v.as_f64()).unwrap_or(min.max(0.0) + 1000.0);
+  let fraction = (rng.next_u64() % 10_000) as f64 / 10_000.0;
+  let value = min + (max - min) * fraction;
+  // keep the value strictly inside exclusive bounds
+  let value = if value <= min && schema.get("exclusiveMinimum").is_some() { (min + max) / 2.0 } else { value };
+  json!(value)
+}
+
+fn generate_string(schema: &Value, rng: &mut Rng) -> Value {
+  match schema.get("format").and_then(|v| v.as_str()) {
+    Some("email") => return json!(format!("user{}@example.com", rng.range(0, 9999))),
+    Some("date-time") => return json!("2024-01-02T03:04:05Z"),
+    Some("date") => return json!("2024-01-02"),
+    Some("time") => return json!("03:04:05"),
+    Some("uuid") => return json!(uuid::Uuid::from_u64_pair(rng.next_u64(), rng.next_u64()).to_string()),
+    Some("uri") => return json!(format!("https://example.com/{}", rng.range(0, 9999))),
+    Some("ipv4") => return json!(format!("10.0.{}.{}", rng.range(0, 255), rng.range(0, 255))),
+    _ => {}
+  }
+
+  let min = schema.get("minLength").and_then(|v| v.as_u64()).unwrap_or(0) as i64;
+  let max = schema.get("maxLength").and_then(|v| v.as_u64()).map(|v| v as i64).unwrap_or(min + 16);
+  let pattern = schema.get("pattern").and_then(|v| v.as_str()).and_then(|p| regex::Regex::new(p).ok());
+
+  const SIMPLE: &[char] = &['a', 'b', 'z', 'A', 'Z', '0', '9'];
+  const TRICKY: &[char] = &[' ', '-', '_', '.', '/', '%', '?', '&', '"', '\'', 'é', 'ß', '漢', '🦀'];
+
+  let mut last = String::new();
+  for attempt in 0..8 {
+    let len = rng.range(min, max.min(min + 16));
+    // the last attempts only use simple characters, that's what most patterns accept
+    let tricky = attempt < 4 && rng.chance(30);
+    last = (0..len)
+      .map(|_| if tricky && rng.chance(30) { *rng.pick(TRICKY) } else { *rng.pick(SIMPLE) })
+      .collect();
+    match &pattern {
+      Some(re) if !re.is_match(&last) => continue,
+      _ => break,
+    }
+  }
+  json!(last)
+}
+
+/// The part of a request an input is generated for
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Section {
+  Params,
+  Query,
+  Payload,
+}
+
+impl Section {
+  /// The error kinds allowed for an invalid input in this section
+  pub fn error_kinds(&self) -> [ApiErrorKind; 2] {
+    match self {
+      Section::Params => [ApiErrorKind::InvalidParamsParse, ApiErrorKind::InvalidParamsValidate],
+      Section::Query => [ApiErrorKind::InvalidQueryParse, ApiErrorKind::InvalidQueryValidate],
+      Section::Payload => [ApiErrorKind::InvalidPayloadParse, ApiErrorKind::InvalidPayloadValidate],
+    }
+  }
+
+  fn parse_error_kind(&self) -> ApiErrorKind {
+    self.error_kinds()[0].clone()
+  }
+}
+
+/// An invalid variant of a valid input, with a description of what was broken
+#[derive(Debug, Clone)]
+pub struct Mutation {
+  pub description: String,
+  pub value: Value,
+}
+
+/// Deliberately invalid variants of `value` according to `schema`
+///
+/// Params and query values travel as strings, so only mutations that
+/// are still invalid once stringified are generated for them
+pub fn mutations(root: &Value, schema: &Value, value: &Value, section: Section) -> Vec<Mutation> {
+  let schema = resolve(root, schema);
+  let mut out = vec![];
+
+  let (properties, object) = match (schema.get("properties").and_then(|v| v.as_object()), value.as_object()) {
+    (Some(properties), Some(object)) => (properties, object),
+    _ => {
+      if section == Section::Payload {
+        if let Some(wrong) = wrong_type(root, schema, section) {
+          out.push(Mutation { description: format!("payload replaced by {wrong}"), value: wrong });
+        }
+      }
+      return out;
+    }
+  };
+
+  if section == Section::Payload {
+    out.push(Mutation { description: String::from("payload replaced by a string"), value: json!("not an object") });
+  }
+
+  for name in required(schema) {
+    // a missing path param doesn't match the route at all
+    if section != Section::Params && object.contains_key(name) {
+      let mut mutated = object.clone();
+      mutated.remove(name);
+      out.push(Mutation { description: format!("required `{name}` removed"), value: Value::Object(mutated) });
+    }
+  }
+
+  for (name, property) in properties {
+    if let Some(wrong) = wrong_type(root, property, section) {
+      let mut mutated = object.clone();
+      mutated.insert(name.clone(), wrong.clone());
+      out.push(Mutation { description: format!("`{name}` set to {wrong}"), value: Value::Object(mutated) });
+    }
+  }
+
+  out
+}
+
+/// A value of the wrong type for `schema`, `None` if every value is accepted
+fn wrong_type(root: &Value, schema: &Value, section: Section) -> Option<Value> {
+  let schema = resolve(root, schema);
+  let types = types(schema);
+  let accepts = |ty: &str| types.contains(&ty) || (ty == "number" && types.contains(&"integer"));
+
+  if let Some(variants) = schema.get("enum").and_then(|v| v.as_array()) {
+    let invalid = json!("__invalid_variant__");
+    return (!variants.contains(&invalid)).then_some(invalid);
+  }
+
+  if types.is_empty() || types.contains(&"string") {
+    // any param or query value is a valid string
+    return match section {
+      Section::Payload if !types.is_empty() && !accepts("number") => Some(json!(12345)),
+      _ => None,
+    };
+  }
+
+  if accepts("integer") || accepts("number") || accepts("boolean") {
+    return Some(json!("not-a-number-or-bool"));
+  }
+
+  match section {
+    Section::Payload => Some(json!("not-an-object-or-array")),
+    _ => None,
+  }
+}
+
+#[derive(Debug, Clone)]
+pub struct FuzzOptions {
+  pub seed: u64,
+  /// Valid inputs generated per endpoint
+  pub cases: usize,
+}
+
+impl Default for FuzzOptions {
+  fn default() -> Self {
+    Self { seed: 0x5eed, cases: 32 }
+  }
+}
+
+#[derive(Debug, Clone)]
+pub struct FuzzFailure {
+  pub method: Method,
+  pub path: String,
+  pub input: String,
+  pub reason: String,
+}
+
+impl std::fmt::Display for FuzzFailure {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(f, "{} {} with {}: {}", self.method, self.path, self.input, self.reason)
+  }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct FuzzReport {
+  pub requests: usize,
+  pub failures: Vec<FuzzFailure>,
+  /// Endpoints without a generated input that passed validation, their invalid inputs were not tested
+  pub skipped: Vec<(Method, String)>,
+}
+
+impl FuzzReport {
+  /// Panic listing every failure
+  #[track_caller]
+  pub fn assert_ok(&self) {
+    if !self.failures.is_empty() {
+      let failures = self.failures.iter().map(|f| format!("  {f}")).collect::<Vec<_>>().join("\n");
+      panic!("{} of {} fuzz requests failed:\n{failures}", self.failures.len(), self.requests);
+    }
+  }
+}
+
+#[derive(Debug, Clone)]
+struct Input {
+  params: Value,
+  query: Value,
+  payload: Option<Value>,
+}
+
+impl Input {
+  fn describe(&self) -> String {
+    let mut out = format!("params = {}, query = {}", self.params, self.query);
+    if let Some(payload) = &self.payload {
+      out.push_str(&format!(", payload = {payload}"));
+    }
+    out
+  }
+}
+
+enum Outcome {
+  Response { status: StatusCode, kind: Option<ApiErrorKind> },
+  Panic(String),
+  Unsendable(String),
+}
+
+struct FuzzEndpoint {
+  path: String,
+  method: Method,
+  params: Option<Value>,
+  query: Option<Value>,
+  payload: Option<Value>,
+}
+
+/// Fires generated inputs at every endpoint of a [`Registry`] through a [`TestClient`]
+///
+/// Valid inputs must never produce a 5xx or a parse error, \
+/// invalid inputs must produce a 4xx with the error kind of the broken request section
+pub struct Fuzzer {
+  client: TestClient,
+  endpoints: Vec<FuzzEndpoint>,
+  pub options: FuzzOptions,
+}
+
+impl Fuzzer {
+  pub fn new(registry: &Registry) -> Self {
+    Self::with_client(registry, TestClient::new(registry))
+  }
+
+  /// Use a configured client, eg: one with the credentials needed by the endpoints `ctx`
+  pub fn with_client(registry: &Registry, client: TestClient) -> Self {
+    let mut endpoints = vec![];
+    for (path, methods_map) in &registry.map {
+      for (method, item) in methods_map {
+        endpoints.push(FuzzEndpoint {
+          path: path.clone(),
+          method: method.clone(),
+          params: item.params.as_ref().map(|s| s.as_value().clone()),
+          query: item.query.as_ref().map(|s| s.as_value().clone()),
+          payload: item.payload.as_ref().map(|s| s.as_value().clone()),
+        });
+      }
+    }
+
+    Self { client, endpoints, options: FuzzOptions::default() }
+  }
+
+  pub async fn run(&self) -> FuzzReport {
+    let mut report = FuzzReport::default();
+    let mut rng = Rng::new(self.options.seed);
+
+    for FuzzEndpoint { path, method, params, query, payload } in &self.endpoints {
+      let mut baseline = None;
+
+      for _ in 0..self.options.cases {
+        let gen = |schema: &Option<Value>, rng: &mut Rng| match schema {
+          Some(schema) => generate(schema, schema, rng),
+          None => Value::Null,
+        };
+        let input = Input {
+          params: gen(params, &mut rng),
+          query: gen(query, &mut rng),
+          payload: payload.as_ref().map(|schema| generate(schema, schema, &mut rng)),
+        };
+
+        // skip values that don't match a pattern we couldn't generate for
+        let matches = |schema: &Option<Value>, value: Option<&Value>| match (schema, value) {
+          (Some(schema), Some(value)) => validate(schema, value).is_empty(),
+          _ => true,
+        };
+        let valid = matches(params, Some(&input.params))
+          && matches(query, Some(&input.query))
+          && matches(payload, input.payload.as_ref());
+        if !valid {
+          continue;
+        }
+
+        report.requests += 1;
+        match self.send(path, method, &input, None).await {
+          Outcome::Response { status, kind } => {
+            let parse_error = [Section::Params, Section::Query, Section::Payload]
+              .iter()
+              .find(|section| kind.as_ref() == Some(&section.parse_error_kind()));
+            if status.is_server_error() {
+              report.failures.push(failure(path, method, &input, format!("valid input returned {status} {kind:?}")));
+            } else if let Some(section) = parse_error {
+              report.failures.push(failure(path, method, &input, format!("valid input was rejected by the {section:?} parser")));
+            } else if status.is_success() && baseline.is_none() {
+              baseline = Some(input);
+            }
+          }
+          Outcome::Panic(message) => report.failures.push(failure(path, method, &input, format!("panicked: {message}"))),
+          Outcome::Unsendable(message) => report.failures.push(failure(path, method, &input, message)),
+        }
+      }
+
+      let baseline = match baseline {
+        Some(baseline) => baseline,
+        None => {
+          report.skipped.push((method.clone(), path.clone()));
+          continue;
+        }
+      };
+
+      let sections = [
+        (Section::Params, params, Some(&baseline.params)),
+        (Section::Query, query, Some(&baseline.query)),
+        (Section::Payload, payload, baseline.payload.as_ref()),
+      ];
+
+      for (section, schema, value) in sections {
+        let (schema, value) = match (schema, value) {
+          (Some(schema), Some(value)) => (schema, value),
+          _ => continue,
+        };
+
+        for mutation in mutations(schema, schema, value, section) {
+          let mut input = baseline.clone();
+          match section {
+            Section::Params => input.params = mutation.value,
+            Section::Query => input.query = mutation.value,
+            Section::Payload => input.payload = Some(mutation.value),
+          }
+
+          report.requests += 1;
+          let expected = section.error_kinds();
+          match self.send(path, method, &input, None).await {
+            Outcome::Response { status, kind } => {
+              let ok = status.is_client_error() && kind.as_ref().is_some_and(|kind| expected.contains(kind));
+              if !ok {
+                report.failures.push(failure(
+                  path,
+                  method,
+                  &input,
+                  format!("{} returned {status} {kind:?}, expected one of {expected:?}", mutation.description),
+                ));
+              }
+            }
+            Outcome::Panic(message) => report.failures.push(failure(path, method, &input, format!("panicked: {message}"))),
+            Outcome::Unsendable(message) => report.failures.push(failure(path, method, &input, message)),
+          }
+        }
+      }
+
+      // a body that is not json at all
+      if payload.is_some() {
+        report.requests += 1;
+        match self.send(path, method, &baseline, Some(b"{\"unterminated")).await {
+          Outcome::Response { status, kind } if status.is_client_error() && kind == Some(ApiErrorKind::InvalidPayloadParse) => {}
+          Outcome::Response { status, kind } => report.failures.push(failure(
+            path,
+            method,
+            &baseline,
+            format!("malformed json payload returned {status} {kind:?}, expected InvalidPayloadParse"),
+          )),
+          Outcome::Panic(message) => report.failures.push(failure(path, method, &baseline, format!("panicked: {message}"))),
+          Outcome::Unsendable(message) => report.failures.push(failure(path, method, &baseline, message)),
+        }
+      }
+    }
+
+    report
+  }
+
+  async fn send(&self, path: &str, method: &Method, input: &Input, raw_payload: Option<&'static [u8]>) -> Outcome {
+    let mut uri = match crate::path::fill(path, &input.params) {
+      Ok(uri) => uri,
+      Err(err) => return Outcome::Unsendable(format!("error building path: {err}")),
+    };
+
+    if let Value::Object(query) = &input.query {
+      // absent optional query values are omitted, not sent as null
+      let query = query.iter().filter(|(_, v)| !v.is_null()).map(|(k, v)| (k.clone(), v.clone())).collect::<Map<_, _>>();
+      match serde_qs::to_string(&query) {
+        Ok(query) if !query.is_empty() => {
+          uri.push('?');
+          uri.push_str(&query);
+        }
+        Ok(_) => {}
+        Err(err) => return Outcome::Unsendable(format!("error serializing query: {err}")),
+      }
+    }
+
+    let body = match (raw_payload, &input.payload) {
+      (Some(raw), _) => Body::from(raw),
+      (None, Some(payload)) => Body::from(serde_json::to_vec(payload).expect("error serializing payload")),
+      (None, None) => Body::empty(),
+    };
+
+    let mut req = match Request::builder().method(method.clone()).uri(&uri).body(body) {
+      Ok(req) => req,
+      Err(err) => return Outcome::Unsendable(format!("error building request for {uri}: {err}")),
+    };
+    if input.payload.is_some() {
+      req.headers_mut().insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+    }
+
+    // run in its own task so a panicking endpoint is reported instead of aborting the run
+    let client = self.client.clone();
+    let res = tokio::spawn(async move {
+      let res = client.send(req).await;
+      let status = res.status();
+      let body = axum::body::to_bytes(res.into_body(), usize::MAX).await.unwrap_or_default();
+      let kind = serde_json::from_slice::<ApiErrorPayload>(&body).ok().map(|payload| payload.error.kind);
+      (status, kind)
+    });
+
+    match res.await {
+      Ok((status, kind)) => Outcome::Response { status, kind },
+      Err(err) if err.is_panic() => {
+        let panic = err.into_panic();
+        let message = panic
+          .downcast_ref::<String>()
+          .cloned()
+          .or_else(|| panic.downcast_ref::<&str>().map(|s| s.to_string()))
+          .unwrap_or_else(|| String::from("<non string panic>"));
+        Outcome::Panic(message)
+      }
+      Err(err) => Outcome::Panic(err.to_string()),
+    }
+  }
+}
+
+fn failure(path: &str, method: &Method, input: &Input, reason: String) -> FuzzFailure {
+  FuzzFailure {
+    method: method.clone(),
+    path: path.to_string(),
+    input: input.describe(),
+    reason,
+  }
+}
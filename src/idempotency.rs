@@ -0,0 +1,572 @@
+use std::{
+  collections::HashMap,
+  fmt::Write,
+  sync::{Arc, Mutex},
+  time::{Duration, Instant},
+};
+
+use axum::{
+  async_trait,
+  body::{Body, Bytes},
+  extract::Request,
+  http::{HeaderMap, HeaderName, HeaderValue, Method, StatusCode, Uri},
+  response::{IntoResponse, Response},
+};
+use sha2::{Digest, Sha256};
+
+use crate::error::{ApiError, ApiErrorKind};
+
+pub const IDEMPOTENCY_KEY: HeaderName = HeaderName::from_static("idempotency-key");
+pub const IDEMPOTENT_REPLAYED: HeaderName = HeaderName::from_static("idempotent-replayed");
+
+/// Who shares the idempotency keys of an endpoint
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum IdempotencyScope {
+  /// Keys are scoped to the principal returned by [`crate::endpoint::Endpoint::idempotency_scope`] \
+  /// Requests with a key but without a principal are rejected
+  Principal,
+  /// Keys are shared between every client, only for endpoints whose responses don't depend on the caller
+  Global,
+}
+
+/// Idempotency-Key handling of an endpoint, see [`crate::endpoint::Endpoint::idempotency`]
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct IdempotencyPolicy {
+  /// How long the first response is kept and replayed
+  pub ttl: Duration,
+  /// Whether requests without the `Idempotency-Key` header are rejected
+  pub required: bool,
+  pub scope: IdempotencyScope,
+}
+
+impl IdempotencyPolicy {
+  const TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+  /// Optional keys scoped to the principal, responses are kept for 24 hours
+  pub const fn per_principal() -> Self {
+    Self { ttl: Self::TTL, required: false, scope: IdempotencyScope::Principal }
+  }
+
+  /// Optional keys shared between every client, responses are kept for 24 hours
+  pub const fn global() -> Self {
+    Self { ttl: Self::TTL, required: false, scope: IdempotencyScope::Global }
+  }
+}
+
+/// A response as stored for replays
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct StoredResponse {
+  pub status: u16,
+  pub headers: HeaderMap,
+  pub body: Bytes,
+}
+
+impl StoredResponse {
+  pub fn into_response(self) -> Response {
+    let mut res = Response::new(Body::from(self.body));
+    *res.status_mut() = StatusCode::from_u16(self.status).unwrap_or(StatusCode::OK);
+    *res.headers_mut() = self.headers;
+    res.headers_mut().insert(IDEMPOTENT_REPLAYED, HeaderValue::from_static("true"));
+    res
+  }
+}
+
+/// The state of a key when a request starts
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Begin {
+  /// First time the key is seen, the request must run and then [`IdempotencyStore::complete`] or [`IdempotencyStore::abort`]
+  Started,
+  /// Another request with the same key is still running
+  InProgress,
+  /// The key was used with a different payload
+  Mismatch,
+  /// The key was used with the same payload, the stored response must be replayed
+  Completed(StoredResponse),
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("idempotency store error: {0}")]
+pub struct IdempotencyStoreError(pub String);
+
+/// Storage for the idempotency keys and their responses
+#[async_trait]
+pub trait IdempotencyStore: Send + Sync + 'static {
+  /// Atomically check `key` and mark it as in progress if it's not known
+  async fn begin(&self, key: &str, fingerprint: &str, ttl: Duration) -> Result<Begin, IdempotencyStoreError>;
+  /// Store the response of a started key
+  async fn complete(&self, key: &str, response: StoredResponse, ttl: Duration) -> Result<(), IdempotencyStoreError>;
+  /// Forget a started key so the request can be retried
+  async fn abort(&self, key: &str) -> Result<(), IdempotencyStoreError>;
+}
+
+#[derive(Debug)]
+struct MemoryEntry {
+  fingerprint: String,
+  response: Option<StoredResponse>,
+  expires: Instant,
+}
+
+#[derive(Debug)]
+struct MemoryEntries {
+  map: HashMap<String, MemoryEntry>,
+  purged: Instant,
+}
+
+/// An in process [`IdempotencyStore`], keys are not shared between instances of the server
+///
+/// Expired keys are dropped at most every [`MemoryIdempotencyStore::purge_interval`]
+#[derive(Debug)]
+pub struct MemoryIdempotencyStore {
+  entries: Mutex<MemoryEntries>,
+  pub purge_interval: Duration,
+}
+
+impl Default for MemoryIdempotencyStore {
+  fn default() -> Self {
+    Self {
+      entries: Mutex::new(MemoryEntries { map: HashMap::new(), purged: Instant::now() }),
+      purge_interval: Duration::from_secs(60),
+    }
+  }
+}
+
+impl MemoryIdempotencyStore {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Remove the expired keys, done automatically while beginning requests
+  pub fn purge(&self) {
+    let now = Instant::now();
+    let mut entries = self.entries.lock().unwrap();
+    entries.map.retain(|_, entry| entry.expires > now);
+    entries.purged = now;
+  }
+
+  /// The number of keys in memory
+  pub fn len(&self) -> usize {
+    self.entries.lock().unwrap().map.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.len() == 0
+  }
+}
+
+#[async_trait]
+impl IdempotencyStore for MemoryIdempotencyStore {
+  async fn begin(&self, key: &str, fingerprint: &str, ttl: Duration) -> Result<Begin, IdempotencyStoreError> {
+    let now = Instant::now();
+    if now.duration_since(self.entries.lock().unwrap().purged) >= self.purge_interval {
+      self.purge();
+    }
+
+    let mut entries = self.entries.lock().unwrap();
+    if let Some(entry) = entries.map.get(key).filter(|entry| entry.expires > now) {
+      if entry.fingerprint != fingerprint {
+        return Ok(Begin::Mismatch);
+      }
+
+      return match &entry.response {
+        Some(response) => Ok(Begin::Completed(response.clone())),
+        None => Ok(Begin::InProgress),
+      };
+    }
+
+    entries.map.insert(key.to_string(), MemoryEntry {
+      fingerprint: fingerprint.to_string(),
+      response: None,
+      expires: now + ttl,
+    });
+
+    Ok(Begin::Started)
+  }
+
+  async fn complete(&self, key: &str, response: StoredResponse, ttl: Duration) -> Result<(), IdempotencyStoreError> {
+    if let Some(entry) = self.entries.lock().unwrap().map.get_mut(key) {
+      entry.response = Some(response);
+      entry.expires = Instant::now() + ttl;
+    }
+    Ok(())
+  }
+
+  async fn abort(&self, key: &str) -> Result<(), IdempotencyStoreError> {
+    self.entries.lock().unwrap().map.remove(key);
+    Ok(())
+  }
+}
+
+fn fingerprint(method: &Method, uri: &Uri, body: &[u8]) -> String {
+  let mut hasher = Sha256::new();
+  hasher.update(method.as_str());
+  hasher.update(b" ");
+  hasher.update(uri.path_and_query().map(|v| v.as_str()).unwrap_or("/"));
+  hasher.update(b"\n");
+  hasher.update(body);
+  let mut out = String::new();
+  for byte in hasher.finalize() {
+    let _ = write!(out, "{byte:02x}");
+  }
+  out
+}
+
+fn error(status: StatusCode, kind: ApiErrorKind, message: &str) -> ApiError {
+  ApiError {
+    status: status.as_u16(),
+    kind,
+    message: message.to_string(),
+  }
+}
+
+/// Whether a response is the final outcome of the request and is replayed for retries \
+/// Server errors and client errors that a retry can fix (auth, conflicts, rate limits...) are not stored
+fn is_final(status: StatusCode) -> bool {
+  status.is_success() || (status.is_client_error() && !matches!(
+    status,
+    StatusCode::UNAUTHORIZED
+      | StatusCode::FORBIDDEN
+      | StatusCode::REQUEST_TIMEOUT
+      | StatusCode::CONFLICT
+      | StatusCode::TOO_EARLY
+      | StatusCode::TOO_MANY_REQUESTS
+  ))
+}
+
+#[derive(Debug)]
+enum Claim {
+  /// The endpoint didn't reach the claim, eg: the `ctx` failed
+  Pending,
+  /// The key was claimed, the response must be stored or the key aborted
+  Started(String),
+  /// The key was already completed, the handler response is replaced by the stored one
+  Replay(StoredResponse),
+  Done,
+}
+
+struct IdempotencyState {
+  store: Arc<dyn IdempotencyStore>,
+  policy: IdempotencyPolicy,
+  key: String,
+  fingerprint: String,
+  claim: Mutex<Claim>,
+}
+
+/// Passed to the endpoint in the request extensions by [`idempotent`] \
+/// The key is claimed after the `ctx`, so it can be scoped to the authenticated principal
+#[derive(Clone)]
+pub struct IdempotencyContext(Arc<IdempotencyState>);
+
+impl IdempotencyContext {
+  /// Claim the key for the `principal`, see [`crate::endpoint::Endpoint::idempotency_scope`] \
+  /// Errors if another request holds the key, if it was used with another request or if the first response must be replayed
+  pub async fn claim(&self, principal: Option<&str>) -> Result<(), ApiError> {
+    let state = &self.0;
+    let store_key = match (state.policy.scope, principal) {
+      (IdempotencyScope::Principal, Some(principal)) => format!("principal:{principal}|{}", state.key),
+      (IdempotencyScope::Principal, None) => {
+        return Err(error(StatusCode::BAD_REQUEST, ApiErrorKind::IdempotencyKeyInvalid, "the Idempotency-Key header needs an authenticated client for this endpoint"));
+      }
+      (IdempotencyScope::Global, _) => format!("global|{}", state.key),
+    };
+
+    match state.store.begin(&store_key, &state.fingerprint, state.policy.ttl).await {
+      Ok(Begin::Started) => {
+        *state.claim.lock().unwrap() = Claim::Started(store_key);
+        Ok(())
+      }
+      Ok(Begin::Completed(response)) => {
+        *state.claim.lock().unwrap() = Claim::Replay(response);
+        // never sent, [`idempotent`] replaces the response with the stored one
+        Err(error(StatusCode::CONFLICT, ApiErrorKind::IdempotencyKeyInProgress, "the Idempotency-Key was already completed"))
+      }
+      Ok(Begin::InProgress) => {
+        Err(error(StatusCode::CONFLICT, ApiErrorKind::IdempotencyKeyInProgress, "a request with the same Idempotency-Key is still in progress"))
+      }
+      Ok(Begin::Mismatch) => {
+        Err(error(StatusCode::UNPROCESSABLE_ENTITY, ApiErrorKind::IdempotencyKeyMismatch, "the Idempotency-Key was already used with a different request"))
+      }
+      Err(err) => {
+        // the store is unavailable, run the request without idempotency
+        tracing::warn!("{err}");
+        Ok(())
+      }
+    }
+  }
+
+  fn started(&self) -> Option<String> {
+    match &*self.0.claim.lock().unwrap() {
+      Claim::Started(store_key) => Some(store_key.clone()),
+      _ => None,
+    }
+  }
+
+  fn finish(&self) -> Claim {
+    std::mem::replace(&mut *self.0.claim.lock().unwrap(), Claim::Done)
+  }
+
+  async fn abort(&self, store_key: &str) {
+    if let Err(err) = self.0.store.abort(store_key).await {
+      tracing::warn!("{err}");
+    }
+  }
+}
+
+/// Aborts a claimed key if the request future is dropped, eg: client disconnect or timeout
+struct AbortOnDrop(IdempotencyContext);
+
+impl Drop for AbortOnDrop {
+  fn drop(&mut self) {
+    let Some(store_key) = self.0.started() else {
+      return;
+    };
+
+    let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+      return;
+    };
+
+    let context = self.0.clone();
+    runtime.spawn(async move {
+      context.abort(&store_key).await;
+    });
+  }
+}
+
+/// Run `handler` honoring the `Idempotency-Key` header
+///
+/// The endpoint claims the key after its `ctx`, see [`IdempotencyContext`]. The first final response
+/// for a key is stored with its headers and a hash of the method, uri and payload, and replayed for retries
+/// with the same key, other responses abort the key so the request can be retried
+pub async fn idempotent<F: std::future::Future<Output = Response>>(
+  store: Option<&Arc<dyn IdempotencyStore>>,
+  policy: Option<&IdempotencyPolicy>,
+  max_payload_size: usize,
+  req: Request,
+  handler: impl FnOnce(Request) -> F,
+) -> Response {
+  let (store, policy) = match (store, policy) {
+    (Some(store), Some(policy)) => (store, policy),
+    _ => return handler(req).await,
+  };
+
+  let key = match req.headers().get(IDEMPOTENCY_KEY).map(|v| v.to_str()) {
+    Some(Ok(key)) if !key.is_empty() && key.len() <= 255 => key.to_string(),
+    Some(_) => {
+      return error(StatusCode::BAD_REQUEST, ApiErrorKind::IdempotencyKeyInvalid, "the Idempotency-Key header must be a string of 1 to 255 characters").into_response();
+    }
+    None => {
+      if policy.required {
+        return error(StatusCode::BAD_REQUEST, ApiErrorKind::IdempotencyKeyInvalid, "the Idempotency-Key header is required for this endpoint").into_response();
+      }
+      return handler(req).await;
+    }
+  };
+
+  let (mut parts, body) = req.into_parts();
+  let body = match axum::body::to_bytes(body, max_payload_size).await {
+    Ok(body) => body,
+    Err(err) => {
+      return error(StatusCode::BAD_REQUEST, ApiErrorKind::PayloadRead, &format!("error reading payload: {err}")).into_response();
+    }
+  };
+
+  let context = IdempotencyContext(Arc::new(IdempotencyState {
+    store: store.clone(),
+    policy: *policy,
+    key,
+    fingerprint: fingerprint(&parts.method, &parts.uri, &body),
+    claim: Mutex::new(Claim::Pending),
+  }));
+
+  parts.extensions.insert(context.clone());
+  let _abort_guard = AbortOnDrop(context.clone());
+
+  let res = handler(Request::from_parts(parts, Body::from(body))).await;
+
+  let store_key = match context.started() {
+    Some(store_key) => store_key,
+    None => {
+      return match context.finish() {
+        Claim::Replay(stored) => stored.into_response(),
+        _ => res,
+      };
+    }
+  };
+
+  if !is_final(res.status()) {
+    context.abort(&store_key).await;
+    context.finish();
+    return res;
+  }
+
+  let (res_parts, res_body) = res.into_parts();
+  let res_body = match axum::body::to_bytes(res_body, usize::MAX).await {
+    Ok(res_body) => res_body,
+    Err(err) => {
+      tracing::warn!("error buffering response body for idempotency: {err}");
+      context.abort(&store_key).await;
+      context.finish();
+      return Response::from_parts(res_parts, Body::empty());
+    }
+  };
+
+  let stored = StoredResponse {
+    status: res_parts.status.as_u16(),
+    headers: res_parts.headers.clone(),
+    body: res_body.clone(),
+  };
+
+  if let Err(err) = store.complete(&store_key, stored, policy.ttl).await {
+    tracing::warn!("{err}");
+  }
+  context.finish();
+
+  Response::from_parts(res_parts, Body::from(res_body))
+}
+
+#[cfg(test)]
+mod tests {
+  use std::sync::atomic::{AtomicUsize, Ordering};
+
+  use super::*;
+
+  struct Server {
+    store: Arc<dyn IdempotencyStore>,
+    policy: IdempotencyPolicy,
+    runs: AtomicUsize,
+  }
+
+  impl Server {
+    fn new(policy: IdempotencyPolicy) -> Self {
+      Self { store: Arc::new(MemoryIdempotencyStore::new()), policy, runs: AtomicUsize::new(0) }
+    }
+
+    async fn call(&self, key: Option<&str>, principal: Option<&str>, body: &'static str, status: StatusCode) -> Response {
+      let mut req = Request::builder().method(Method::POST).uri("/users");
+      if let Some(key) = key {
+        req = req.header(IDEMPOTENCY_KEY, key);
+      }
+
+      idempotent(Some(&self.store), Some(&self.policy), 1024, req.body(Body::from(body)).unwrap(), |req| async move {
+        if let Some(context) = req.extensions().get::<IdempotencyContext>() {
+          if let Err(err) = context.claim(principal).await {
+            return err.into_response();
+          }
+        }
+        let run = self.runs.fetch_add(1, Ordering::SeqCst);
+        (status, format!("run {run}")).into_response()
+      })
+      .await
+    }
+  }
+
+  async fn text(res: Response) -> String {
+    let body = axum::body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+    String::from_utf8(body.to_vec()).unwrap()
+  }
+
+  #[tokio::test]
+  async fn claim_and_replay() {
+    let server = Server::new(IdempotencyPolicy::per_principal());
+
+    let res = server.call(Some("k1"), Some("alice"), "{}", StatusCode::CREATED).await;
+    assert_eq!(res.status(), StatusCode::CREATED);
+    assert_eq!(res.headers().get(IDEMPOTENT_REPLAYED), None);
+    assert_eq!(text(res).await, "run 0");
+
+    let res = server.call(Some("k1"), Some("alice"), "{}", StatusCode::CREATED).await;
+    assert_eq!(res.status(), StatusCode::CREATED);
+    assert_eq!(res.headers()[IDEMPOTENT_REPLAYED], "true");
+    assert_eq!(text(res).await, "run 0");
+
+    // the same key of another principal is another request
+    let res = server.call(Some("k1"), Some("bob"), "{}", StatusCode::CREATED).await;
+    assert_eq!(text(res).await, "run 1");
+
+    // no key, no replay
+    let res = server.call(None, Some("alice"), "{}", StatusCode::CREATED).await;
+    assert_eq!(text(res).await, "run 2");
+  }
+
+  #[tokio::test]
+  async fn principal_required() {
+    let server = Server::new(IdempotencyPolicy::per_principal());
+    let res = server.call(Some("k1"), None, "{}", StatusCode::CREATED).await;
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(server.runs.load(Ordering::SeqCst), 0);
+
+    let server = Server::new(IdempotencyPolicy::global());
+    server.call(Some("k1"), Some("alice"), "{}", StatusCode::CREATED).await;
+    let res = server.call(Some("k1"), None, "{}", StatusCode::CREATED).await;
+    assert_eq!(res.headers()[IDEMPOTENT_REPLAYED], "true");
+  }
+
+  #[tokio::test]
+  async fn mismatch() {
+    let server = Server::new(IdempotencyPolicy::per_principal());
+    server.call(Some("k1"), Some("alice"), "{\"name\":\"a\"}", StatusCode::CREATED).await;
+
+    let res = server.call(Some("k1"), Some("alice"), "{\"name\":\"b\"}", StatusCode::CREATED).await;
+    assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(server.runs.load(Ordering::SeqCst), 1);
+  }
+
+  #[tokio::test]
+  async fn in_progress() {
+    let store = MemoryIdempotencyStore::new();
+    let ttl = Duration::from_secs(60);
+    assert_eq!(store.begin("k1", "a", ttl).await.unwrap(), Begin::Started);
+    assert_eq!(store.begin("k1", "a", ttl).await.unwrap(), Begin::InProgress);
+    assert_eq!(store.begin("k1", "b", ttl).await.unwrap(), Begin::Mismatch);
+
+    let response = StoredResponse { status: 201, headers: HeaderMap::new(), body: Bytes::from("done") };
+    store.complete("k1", response.clone(), ttl).await.unwrap();
+    assert_eq!(store.begin("k1", "a", ttl).await.unwrap(), Begin::Completed(response));
+  }
+
+  #[tokio::test]
+  async fn retryable_responses_are_not_stored() {
+    let server = Server::new(IdempotencyPolicy::per_principal());
+    let res = server.call(Some("k1"), Some("alice"), "{}", StatusCode::SERVICE_UNAVAILABLE).await;
+    assert_eq!(text(res).await, "run 0");
+
+    let res = server.call(Some("k1"), Some("alice"), "{}", StatusCode::CREATED).await;
+    assert_eq!(res.headers().get(IDEMPOTENT_REPLAYED), None);
+    assert_eq!(text(res).await, "run 1");
+  }
+
+  #[tokio::test]
+  async fn release_on_drop() {
+    let store: Arc<dyn IdempotencyStore> = Arc::new(MemoryIdempotencyStore::new());
+    let policy = IdempotencyPolicy::global();
+    let req = Request::builder().method(Method::POST).uri("/users").header(IDEMPOTENCY_KEY, "k1").body(Body::from("{}")).unwrap();
+
+    let pending = idempotent(Some(&store), Some(&policy), 1024, req, |req| async move {
+      let context = req.extensions().get::<IdempotencyContext>().unwrap().clone();
+      context.claim(None).await.unwrap();
+      std::future::pending::<Response>().await
+    });
+
+    // the client disconnects while the handler runs
+    assert!(tokio::time::timeout(Duration::from_millis(10), pending).await.is_err());
+    tokio::task::yield_now().await;
+
+    let fingerprint = fingerprint(&Method::POST, &Uri::from_static("/users"), b"{}");
+    assert_eq!(store.begin("global|k1", &fingerprint, policy.ttl).await.unwrap(), Begin::Started);
+  }
+
+  #[tokio::test]
+  async fn purge() {
+    let store = MemoryIdempotencyStore { purge_interval: Duration::from_millis(200), ..Default::default() };
+    store.begin("short", "a", Duration::from_millis(10)).await.unwrap();
+    store.begin("long", "a", Duration::from_secs(60)).await.unwrap();
+
+    // expired keys can be reused before being purged
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    assert_eq!(store.begin("short", "b", Duration::from_millis(10)).await.unwrap(), Begin::Started);
+    assert_eq!(store.len(), 2);
+
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    store.begin("other", "a", Duration::from_secs(60)).await.unwrap();
+    assert_eq!(store.len(), 2);
+  }
+}
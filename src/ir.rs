@@ -0,0 +1,597 @@
+use std::time::Duration;
+
+use indexmap::IndexMap;
+use serde_json::Value;
+use shape::ToTypescript;
+
+use crate::codegen::pascal;
+use crate::idempotency::IdempotencyPolicy;
+use crate::rate_limit::RateLimit;
+use crate::schema::{merge_all_of, resolve, types};
+use crate::registry::Registry;
+
+/// A language independent description of a registry, the input of a [`crate::codegen::Generator`]
+///
+/// Objects are named by their `title` or by the path to them, eg: `GetUsersQuery`, `PageItems`\
+/// The types are `#[non_exhaustive]`, information can be added without breaking existing generators
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct Api {
+  /// The named types referenced by [`Type::Named`], in the order they are first used
+  pub types: IndexMap<String, Def>,
+  /// The operations in the order of the registry
+  pub operations: Vec<Operation>,
+  /// The payload of every error response, see [`crate::error::ErrorPayload`]
+  pub error: Output,
+}
+
+impl Api {
+  pub fn new(registry: &Registry) -> Self {
+    let mut builder = Builder::default();
+
+    let error = registry.error_payload_schema().as_value();
+    let error = Output {
+      ty: builder.ty(error, error, "ErrorPayload"),
+      schema: crate::schema::to_json_schema(error),
+      openapi: registry.error_payload_schema().clone(),
+      typescript: registry.error_payload_shape.to_typescript(),
+    };
+
+    let mut operations = vec![];
+    for (path, methods_map) in &registry.map {
+      for (method, item) in methods_map {
+        let name = crate::export::schema_name_prefix(method.as_str(), path);
+        let mut input = |schema: Option<&schemars::Schema>, shape: Option<&shape::Type>, suffix: &str| {
+          schema.zip(shape).map(|(openapi, shape)| {
+            let schema = openapi.as_value();
+            let required = schema.get("required").and_then(|v| v.as_array()).is_some_and(|v| !v.is_empty());
+            let ty = builder.ty(schema, schema, &format!("{name}{suffix}"));
+            Input {
+              ty,
+              required,
+              schema: crate::schema::to_json_schema(schema),
+              openapi: openapi.clone(),
+              typescript: shape.to_typescript(),
+            }
+          })
+        };
+
+        let params = input(item.params.as_ref(), item.params_shape.as_ref(), "Params");
+        let query = input(item.query.as_ref(), item.query_shape.as_ref(), "Query");
+        let payload = input(item.payload.as_ref(), item.payload_shape.as_ref(), "Payload");
+        let output = item.output.as_value();
+        let output = Output {
+          ty: builder.ty(output, output, &format!("{name}Output")),
+          schema: crate::schema::to_json_schema(output),
+          openapi: item.output.clone(),
+          typescript: item.output_shape.to_typescript(),
+        };
+
+        // the headers and responses only apply when the registry has a store or a limiter
+        let idempotency = item.idempotency.filter(|_| registry.idempotency.is_some());
+        let rate_limit = registry
+          .rate_limiter
+          .as_ref()
+          .and_then(|rate_limiter| rate_limiter.limit_for(&item.method, &item.path, item.rate_limit))
+          .map(|(_, limit)| limit);
+
+        operations.push(Operation {
+          name,
+          method: method.to_string(),
+          path: path.clone(),
+          params,
+          query,
+          payload,
+          output,
+          summary: item.summary.clone(),
+          description: item.description.clone(),
+          deprecated: item.deprecated,
+          idempotency,
+          timeout: item.timeout.or(registry.timeout),
+          rate_limit,
+        });
+      }
+    }
+
+    Self { types: builder.types, operations, error }
+  }
+
+  /// Whether a type is used anywhere, eg: to only emit the helpers it needs
+  pub fn uses(&self, matches: impl Fn(&Type) -> bool) -> bool {
+    fn walk(ty: &Type, matches: &dyn Fn(&Type) -> bool) -> bool {
+      matches(ty)
+        || match ty {
+          Type::Nullable(ty) | Type::List(ty) | Type::Map(ty) => walk(ty, matches),
+          Type::Union(types) => types.iter().any(|ty| walk(ty, matches)),
+          _ => false,
+        }
+    }
+
+    let fields = self.types.values().flat_map(|def| match &def.kind {
+      DefKind::Struct(fields) => fields.iter().collect::<Vec<_>>(),
+      DefKind::Enum(_) => vec![],
+      DefKind::Tagged { shared, variants, .. } => shared.iter().chain(variants.iter().flat_map(|v| &v.1)).collect(),
+    });
+    let inputs = self.operations.iter().flat_map(|op| {
+      [&op.params, &op.query, &op.payload].into_iter().flatten().map(|input| &input.ty).chain([&op.output.ty])
+    });
+
+    fields.map(|field| &field.ty).chain(inputs).chain([&self.error.ty]).any(|ty| walk(ty, &matches))
+  }
+}
+
+impl Registry {
+  /// The [`Api`] of the registry, see [`crate::codegen::Generator`]
+  pub fn ir(&self) -> Api {
+    Api::new(self)
+  }
+}
+
+/// A field or value type
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum Type {
+  Any,
+  Bool,
+  Int,
+  Float,
+  String,
+  /// A single string value, eg: the tag of a variant
+  Literal(String),
+  Nullable(Box<Type>),
+  List(Box<Type>),
+  /// An object with string keys and values of the same type
+  Map(Box<Type>),
+  /// An untagged union, most languages fall back to a json value
+  Union(Vec<Type>),
+  /// A type in [`Api::types`]
+  Named(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct Field {
+  /// The json name of the field
+  pub name: String,
+  pub ty: Type,
+  pub required: bool,
+  pub doc: Option<String>,
+}
+
+impl Field {
+  pub fn new(name: impl Into<String>, ty: Type, required: bool) -> Self {
+    Self { name: name.into(), ty, required, doc: None }
+  }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum DefKind {
+  Struct(Vec<Field>),
+  /// A string enum
+  Enum(Vec<String>),
+  /// An internally tagged union, every variant has the `tag` property with its own value
+  Tagged {
+    tag: String,
+    /// Fields of every variant, eg: the properties next to the `oneOf`
+    shared: Vec<Field>,
+    /// The tag value and the other fields of each variant
+    variants: Vec<(String, Vec<Field>)>,
+  },
+}
+
+/// A named type
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct Def {
+  pub doc: Option<String>,
+  pub kind: DefKind,
+}
+
+/// The params, query or payload of an operation
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct Input {
+  pub ty: Type,
+  /// Whether it has required fields, inputs without them can be omitted
+  pub required: bool,
+  /// The JSON Schema (2020-12) the type was built from, with its `$defs`
+  pub schema: Value,
+  /// The schema as generated for the registry, in the OpenAPI 3.0 dialect, eg: `nullable`
+  pub openapi: schemars::Schema,
+  /// The TypeScript type of the Rust definition on a single line, without docs
+  pub typescript: String,
+}
+
+/// The output of an operation or the error payload
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct Output {
+  pub ty: Type,
+  /// The JSON Schema (2020-12) the type was built from, with its `$defs`
+  pub schema: Value,
+  /// The schema as generated for the registry, in the OpenAPI 3.0 dialect, eg: `nullable`
+  pub openapi: schemars::Schema,
+  /// The TypeScript type of the Rust definition on a single line, without docs
+  pub typescript: String,
+}
+
+/// An endpoint of the registry
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct Operation {
+  /// The PascalCase name of the endpoint, eg: `GetUsersId`
+  pub name: String,
+  /// The uppercase method, eg: `GET`
+  pub method: String,
+  /// The axum path, eg: `/users/:id`
+  pub path: String,
+  pub params: Option<Input>,
+  pub query: Option<Input>,
+  pub payload: Option<Input>,
+  pub output: Output,
+  pub summary: Option<String>,
+  pub description: Option<String>,
+  pub deprecated: bool,
+  /// The idempotency policy, `None` if the endpoint has none or the registry has no store
+  pub idempotency: Option<IdempotencyPolicy>,
+  /// The endpoint timeout or the registry default
+  pub timeout: Option<Duration>,
+  /// The rate limit of the endpoint, its group or the registry default, `None` if the registry has no limiter
+  pub rate_limit: Option<RateLimit>,
+}
+
+impl Operation {
+  /// The summary and description separated by a blank line
+  pub fn doc(&self) -> Option<String> {
+    let doc = self.summary.iter().chain(&self.description).cloned().collect::<Vec<_>>();
+    (!doc.is_empty()).then(|| doc.join("\n\n"))
+  }
+
+  /// The params, query and payload with their suffix, eg: `("Query", input)`
+  pub fn inputs(&self) -> [(&'static str, Option<&Input>); 3] {
+    [("Params", self.params.as_ref()), ("Query", self.query.as_ref()), ("Payload", self.payload.as_ref())]
+  }
+}
+
+#[derive(Default)]
+struct Builder {
+  types: IndexMap<String, Def>,
+  /// Names of the `$ref`s being or already converted, to support recursive types
+  refs: IndexMap<String, String>,
+  /// The `$ref`s being converted, a recursion through a type without a name becomes [`Type::Any`]
+  visiting: Vec<String>,
+}
+
+impl Builder {
+  fn ty(&mut self, root: &Value, schema: &Value, hint: &str) -> Type {
+    let reference = schema.get("$ref").and_then(|v| v.as_str()).map(String::from);
+    let hint = match reference.as_deref().and_then(|r| r.rsplit('/').next()) {
+      Some(name) => pascal(name),
+      None => hint.to_string(),
+    };
+
+    if let Some(reference) = &reference {
+      if let Some(name) = self.refs.get(reference) {
+        return Type::Named(name.clone());
+      }
+      if self.visiting.contains(reference) {
+        return Type::Any;
+      }
+    }
+
+    let schema = resolve(root, schema);
+    let null = |sub: &&Value| types(resolve(root, sub)) == ["null"];
+    let nullable = schema.get("nullable").and_then(|v| v.as_bool()) == Some(true)
+      || types(schema).contains(&"null")
+      || ["anyOf", "oneOf"].iter().any(|key| schema.get(*key).and_then(|v| v.as_array()).is_some_and(|v| v.iter().any(|sub| null(&sub))));
+
+    let reference = match reference {
+      Some(reference) => reference,
+      None => return wrap(self.non_null(root, schema, &hint), nullable),
+    };
+
+    // reserve the name before converting the fields, recursive references point to it
+    let name = self.free_name(&title(schema).unwrap_or(hint.clone()));
+    self.refs.insert(reference.clone(), name.clone());
+    self.types.insert(name.clone(), Def { doc: None, kind: DefKind::Struct(vec![]) });
+    self.visiting.push(reference.clone());
+
+    let ty = match self.def(root, schema, &name) {
+      Some(def) => {
+        self.types.insert(name.clone(), def);
+        Type::Named(name)
+      }
+      None => {
+        // not a definition, eg: a list or an untagged union, `def` returns before descending so nothing points to the name
+        self.types.shift_remove(&name);
+        self.refs.shift_remove(&reference);
+        self.non_null(root, schema, &hint)
+      }
+    };
+
+    self.visiting.pop();
+    wrap(ty, nullable)
+  }
+
+  /// The definition of a struct, a string enum or a tagged union, `None` for other schemas \
+  /// Returns before converting any subschema when the schema is not a definition
+  fn def(&mut self, root: &Value, schema: &Value, name: &str) -> Option<Def> {
+    if schema.get("const").is_some() {
+      return None;
+    }
+
+    if let Some(variants) = schema.get("enum").and_then(|v| v.as_array()) {
+      let strings = variants.iter().filter_map(|v| v.as_str()).map(String::from).collect::<Vec<_>>();
+      if strings.len() > 1 && strings.len() == variants.iter().filter(|v| !v.is_null()).count() {
+        return Some(Def { doc: description(schema), kind: DefKind::Enum(strings) });
+      }
+      return None;
+    }
+
+    for key in ["oneOf", "anyOf"] {
+      if let Some(subs) = schema.get(key).and_then(|v| v.as_array()) {
+        let subs = subs.iter().filter(|sub| types(resolve(root, sub)) != ["null"]).collect::<Vec<_>>();
+        if subs.len() == 1 && schema.get("properties").is_none() {
+          return None;
+        }
+        return self.tagged(root, schema, &subs, name);
+      }
+    }
+
+    if let Some(merged) = merge_all_of(root, schema) {
+      return self.def(root, &merged, name);
+    }
+
+    let is_object = types(schema).iter().find(|ty| **ty != "null").map_or(schema.get("properties").is_some(), |ty| *ty == "object");
+    match is_object && schema.get("properties").is_some() {
+      true => Some(Def { doc: description(schema), kind: DefKind::Struct(self.fields(root, schema, name)) }),
+      false => None,
+    }
+  }
+
+  fn non_null(&mut self, root: &Value, schema: &Value, hint: &str) -> Type {
+    let name = title(schema).unwrap_or(hint.to_string());
+    if let Some(def) = self.def(root, schema, &name) {
+      return Type::Named(self.define(name, def));
+    }
+
+    if let Some(value) = schema.get("const") {
+      return match value {
+        Value::String(value) => Type::Literal(value.clone()),
+        value => primitive(value),
+      };
+    }
+
+    if let Some(variants) = schema.get("enum").and_then(|v| v.as_array()) {
+      let strings = variants.iter().filter_map(|v| v.as_str()).collect::<Vec<_>>();
+      return match strings.as_slice() {
+        [value] => Type::Literal(value.to_string()),
+        _ => Type::Any,
+      };
+    }
+
+    for key in ["oneOf", "anyOf"] {
+      if let Some(subs) = schema.get(key).and_then(|v| v.as_array()) {
+        let subs = subs.iter().filter(|sub| types(resolve(root, sub)) != ["null"]).collect::<Vec<_>>();
+        if subs.len() == 1 && schema.get("properties").is_none() {
+          return self.ty(root, subs[0], hint);
+        }
+        let types = subs.iter().enumerate().map(|(i, sub)| self.ty(root, sub, &format!("{hint}{}", i + 1))).collect();
+        return Type::Union(types);
+      }
+    }
+
+    if let Some(merged) = merge_all_of(root, schema) {
+      return self.non_null(root, &merged, hint);
+    }
+
+    let types = types(schema);
+    let ty = match types.iter().find(|ty| **ty != "null") {
+      Some(ty) => *ty,
+      None if schema.get("items").is_some() => "array",
+      None => return Type::Any,
+    };
+
+    match ty {
+      "boolean" => Type::Bool,
+      "integer" => Type::Int,
+      "number" => Type::Float,
+      "string" => Type::String,
+      "array" => match schema.get("items") {
+        Some(items @ Value::Object(_)) => Type::List(Box::new(self.ty(root, items, &format!("{hint}Item")))),
+        _ => Type::List(Box::new(Type::Any)),
+      },
+      "object" => match schema.get("additionalProperties") {
+        Some(values @ Value::Object(_)) => Type::Map(Box::new(self.ty(root, values, &format!("{hint}Value")))),
+        _ => Type::Map(Box::new(Type::Any)),
+      },
+      _ => Type::Any,
+    }
+  }
+
+  /// An internally tagged union, when every variant has a required property with a single string value
+  fn tagged(&mut self, root: &Value, schema: &Value, subs: &[&Value], name: &str) -> Option<Def> {
+    let variants = subs.iter().map(|sub| resolve(root, sub)).collect::<Vec<_>>();
+    let first = variants.first()?.get("properties")?.as_object()?;
+
+    let tag_of = |variant: &Value, tag: &str| -> Option<String> {
+      let required = variant.get("required")?.as_array()?.iter().any(|v| v.as_str() == Some(tag));
+      let property = resolve(root, variant.get("properties")?.get(tag)?);
+      let value = property.get("const").or_else(|| property.get("enum")?.as_array().filter(|v| v.len() == 1)?.first());
+      value?.as_str().filter(|_| required).map(String::from)
+    };
+
+    let tag = first.keys().find(|tag| variants.iter().all(|variant| tag_of(variant, tag).is_some()))?.clone();
+
+    let shared = self.fields(root, schema, name);
+    let mut tagged = vec![];
+    for variant in variants {
+      let value = tag_of(variant, &tag)?;
+      let fields = self.fields(root, variant, &format!("{name}{}", pascal(&value)));
+      tagged.push((value, fields.into_iter().filter(|field| field.name != tag).collect()));
+    }
+
+    Some(Def { doc: description(schema), kind: DefKind::Tagged { tag, shared, variants: tagged } })
+  }
+
+  fn fields(&mut self, root: &Value, schema: &Value, name: &str) -> Vec<Field> {
+    let required = schema
+      .get("required")
+      .and_then(|v| v.as_array())
+      .map(|required| required.iter().filter_map(|v| v.as_str()).map(String::from).collect::<Vec<_>>())
+      .unwrap_or_default();
+
+    let properties = match schema.get("properties").and_then(|v| v.as_object()) {
+      Some(properties) => properties,
+      None => return vec![],
+    };
+
+    properties
+      .iter()
+      .map(|(field, property)| Field {
+        name: field.clone(),
+        ty: self.ty(root, property, &format!("{name}{}", pascal(field))),
+        required: required.contains(field),
+        doc: description(property).or_else(|| description(resolve(root, property))),
+      })
+      .collect()
+  }
+
+  /// Add a definition, reusing an identical one with the same name or numbering the name
+  fn define(&mut self, name: String, def: Def) -> String {
+    let name = pascal(&name);
+    let mut candidate = name.clone();
+    let mut n = 1;
+    while let Some(existing) = self.types.get(&candidate) {
+      if *existing == def {
+        return candidate;
+      }
+      n += 1;
+      candidate = format!("{name}{n}");
+    }
+    self.types.insert(candidate.clone(), def);
+    candidate
+  }
+
+  fn free_name(&self, name: &str) -> String {
+    let name = pascal(name);
+    let mut candidate = name.clone();
+    let mut n = 1;
+    while self.types.contains_key(&candidate) {
+      n += 1;
+      candidate = format!("{name}{n}");
+    }
+    candidate
+  }
+}
+
+fn wrap(ty: Type, nullable: bool) -> Type {
+  match (nullable, ty) {
+    (true, ty @ (Type::Any | Type::Nullable(_))) => ty,
+    (true, ty) => Type::Nullable(Box::new(ty)),
+    (false, ty) => ty,
+  }
+}
+
+fn primitive(value: &Value) -> Type {
+  match value {
+    Value::Bool(_) => Type::Bool,
+    Value::Number(number) if number.is_f64() => Type::Float,
+    Value::Number(_) => Type::Int,
+    Value::String(_) => Type::String,
+    _ => Type::Any,
+  }
+}
+
+fn title(schema: &Value) -> Option<String> {
+  schema.get("title").and_then(|v| v.as_str()).map(String::from)
+}
+
+fn description(schema: &Value) -> Option<String> {
+  // doc comments with line breaks keep the leading space of each line
+  let lines = schema.get("description")?.as_str()?.lines().map(str::trim).collect::<Vec<_>>();
+  Some(lines.join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+  use serde_json::json;
+
+  use super::*;
+
+  fn build(schema: Value) -> (Type, IndexMap<String, Def>) {
+    let mut builder = Builder::default();
+    let ty = builder.ty(&schema, &schema, "Output");
+    (ty, builder.types)
+  }
+
+  #[test]
+  fn recursive_struct() {
+    let (ty, types) = build(json!({
+      "$ref": "#/$defs/Node",
+      "$defs": {
+        "Node": {
+          "type": "object",
+          "properties": { "children": { "type": "array", "items": { "$ref": "#/$defs/Node" } } },
+        },
+      },
+    }));
+
+    assert_eq!(ty, Type::Named(String::from("Node")));
+    let fields = match &types["Node"].kind {
+      DefKind::Struct(fields) => fields,
+      kind => panic!("unexpected {kind:?}"),
+    };
+    assert_eq!(fields[0].ty, Type::List(Box::new(Type::Named(String::from("Node")))));
+  }
+
+  #[test]
+  fn recursive_tagged_enum() {
+    let (ty, types) = build(json!({
+      "$ref": "#/$defs/Expr",
+      "$defs": {
+        "Expr": {
+          "oneOf": [
+            {
+              "type": "object",
+              "properties": { "kind": { "const": "num" }, "value": { "type": "integer" } },
+              "required": ["kind", "value"],
+            },
+            {
+              "type": "object",
+              "properties": { "kind": { "const": "neg" }, "expr": { "$ref": "#/$defs/Expr" } },
+              "required": ["kind", "expr"],
+            },
+          ],
+        },
+      },
+    }));
+
+    assert_eq!(ty, Type::Named(String::from("Expr")));
+    let variants = match &types["Expr"].kind {
+      DefKind::Tagged { variants, .. } => variants,
+      kind => panic!("unexpected {kind:?}"),
+    };
+    assert_eq!(variants[1].1[0].ty, Type::Named(String::from("Expr")));
+  }
+
+  #[test]
+  fn recursive_untagged_enum() {
+    // `enum Tree { Leaf(String), Node(Vec<Tree>) }` has no name in the IR, the recursion becomes `Any`
+    let (ty, types) = build(json!({
+      "$ref": "#/$defs/Tree",
+      "$defs": {
+        "Tree": {
+          "anyOf": [
+            { "type": "string" },
+            { "type": "array", "items": { "$ref": "#/$defs/Tree" } },
+          ],
+        },
+      },
+    }));
+
+    assert_eq!(ty, Type::Union(vec![Type::String, Type::List(Box::new(Type::Any))]));
+    assert!(types.is_empty());
+  }
+}
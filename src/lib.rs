@@ -5,4 +5,24 @@ pub mod ts;
 pub mod endpoint;
 pub mod schema;
 pub mod response;
-pub mod void;
\ No newline at end of file
+pub mod void;
+pub mod path;
+pub mod trace;
+pub mod metrics;
+pub mod otel;
+pub mod rate_limit;
+pub mod cors;
+pub mod compression;
+pub mod conditional;
+pub mod cache;
+pub mod idempotency;
+pub mod testing;
+pub mod contract;
+pub mod fuzz;
+pub mod mock;
+pub mod diff;
+pub mod yaml;
+pub mod export;
+pub mod zod;
+pub mod codegen;
+pub mod ir;
\ No newline at end of file
@@ -0,0 +1,263 @@
+use std::{fmt::Write, sync::Mutex, time::Instant};
+
+use axum::{
+  body::HttpBody,
+  extract::Request,
+  http::{header::{CONTENT_LENGTH, CONTENT_TYPE}, HeaderValue, Method},
+  response::Response,
+};
+use indexmap::IndexMap;
+
+use crate::error::{ApiError, ApiErrorKind};
+
+pub const DEFAULT_LATENCY_BUCKETS: &[f64] = &[
+  0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+pub const DEFAULT_SIZE_BUCKETS: &[f64] = &[
+  128.0, 512.0, 1024.0, 4096.0, 16384.0, 65536.0, 262144.0, 1048576.0, 4194304.0,
+];
+
+#[derive(Debug, Clone)]
+pub struct MetricsOptions {
+  /// The path where the metrics are served in text exposition format, `None` to not serve them
+  pub path: Option<String>,
+  /// A prefix added to every metric name
+  pub namespace: String,
+  /// Histogram buckets for the request duration, in seconds
+  pub latency_buckets: Vec<f64>,
+  /// Histogram buckets for the request and response body sizes, in bytes
+  pub size_buckets: Vec<f64>,
+}
+
+impl Default for MetricsOptions {
+  fn default() -> Self {
+    Self {
+      path: Some(String::from("/metrics")),
+      namespace: String::from("auto_api"),
+      latency_buckets: DEFAULT_LATENCY_BUCKETS.to_vec(),
+      size_buckets: DEFAULT_SIZE_BUCKETS.to_vec(),
+    }
+  }
+}
+
+#[derive(Debug, Clone)]
+struct Histogram {
+  bounds: Vec<f64>,
+  counts: Vec<u64>,
+  sum: f64,
+  count: u64,
+}
+
+impl Histogram {
+  fn new(bounds: &[f64]) -> Self {
+    Self {
+      bounds: bounds.to_vec(),
+      counts: vec![0; bounds.len()],
+      sum: 0.0,
+      count: 0,
+    }
+  }
+
+  fn observe(&mut self, value: f64) {
+    for (bound, count) in self.bounds.iter().zip(self.counts.iter_mut()) {
+      if value <= *bound {
+        *count += 1;
+      }
+    }
+    self.sum += value;
+    self.count += 1;
+  }
+
+  fn render(&self, out: &mut String, name: &str, labels: &str) {
+    for (bound, count) in self.bounds.iter().zip(self.counts.iter()) {
+      let _ = writeln!(out, "{name}_bucket{{{labels},le=\"{bound}\"}} {count}");
+    }
+    let _ = writeln!(out, "{name}_bucket{{{labels},le=\"+Inf\"}} {}", self.count);
+    let _ = writeln!(out, "{name}_sum{{{labels}}} {}", self.sum);
+    let _ = writeln!(out, "{name}_count{{{labels}}} {}", self.count);
+  }
+}
+
+#[derive(Debug, Clone)]
+struct EndpointMetrics {
+  // { key: status code => count }
+  requests: IndexMap<u16, u64>,
+  // { key: error kind => count }
+  errors: IndexMap<String, u64>,
+  latency: Histogram,
+  request_size: Histogram,
+  response_size: Histogram,
+}
+
+/// A single request as recorded by [`Metrics::observe`]
+#[derive(Debug, Clone, Copy)]
+pub struct Observation<'a> {
+  pub status: u16,
+  pub error_kind: Option<&'a ApiErrorKind>,
+  pub seconds: f64,
+  pub request_size: Option<u64>,
+  pub response_size: Option<u64>,
+}
+
+/// Request metrics for every registered endpoint, keyed by path template and method
+#[derive(Debug)]
+pub struct Metrics {
+  pub options: MetricsOptions,
+  // { key: (Path, Method) => Metrics }
+  endpoints: Mutex<IndexMap<(String, Method), EndpointMetrics>>,
+}
+
+impl Default for Metrics {
+  fn default() -> Self {
+    Self::new(MetricsOptions::default())
+  }
+}
+
+impl Metrics {
+  pub fn new(options: MetricsOptions) -> Self {
+    Self {
+      options,
+      endpoints: Mutex::new(IndexMap::new()),
+    }
+  }
+
+  fn endpoint_metrics(&self) -> EndpointMetrics {
+    EndpointMetrics {
+      requests: IndexMap::new(),
+      errors: IndexMap::new(),
+      latency: Histogram::new(&self.options.latency_buckets),
+      request_size: Histogram::new(&self.options.size_buckets),
+      response_size: Histogram::new(&self.options.size_buckets),
+    }
+  }
+
+  /// Register an endpoint so it shows in the output even before receiving requests
+  pub fn add_endpoint(&self, path: &str, method: &Method) {
+    let mut endpoints = self.endpoints.lock().unwrap();
+    if !endpoints.contains_key(&(path.to_string(), method.clone())) {
+      endpoints.insert((path.to_string(), method.clone()), self.endpoint_metrics());
+    }
+  }
+
+  pub fn observe(&self, path: &str, method: &Method, observation: Observation<'_>) {
+    let mut endpoints = self.endpoints.lock().unwrap();
+    let key = (path.to_string(), method.clone());
+    if !endpoints.contains_key(&key) {
+      endpoints.insert(key.clone(), self.endpoint_metrics());
+    }
+    let endpoint = endpoints.get_mut(&key).unwrap();
+
+    *endpoint.requests.entry(observation.status).or_default() += 1;
+    if let Some(kind) = observation.error_kind {
+      *endpoint.errors.entry(kind_label(kind)).or_default() += 1;
+    }
+    endpoint.latency.observe(observation.seconds);
+    if let Some(size) = observation.request_size {
+      endpoint.request_size.observe(size as f64);
+    }
+    if let Some(size) = observation.response_size {
+      endpoint.response_size.observe(size as f64);
+    }
+  }
+
+  /// Render the metrics in Prometheus text exposition format
+  pub fn render(&self) -> String {
+    let endpoints = self.endpoints.lock().unwrap().clone();
+    let ns = &self.options.namespace;
+    let mut out = String::new();
+
+    let _ = writeln!(out, "# HELP {ns}_requests_total Total number of requests by route, method and status");
+    let _ = writeln!(out, "# TYPE {ns}_requests_total counter");
+    for ((path, method), metrics) in &endpoints {
+      for (status, count) in &metrics.requests {
+        let _ = writeln!(out, "{ns}_requests_total{{{},status=\"{status}\"}} {count}", labels(path, method));
+      }
+    }
+
+    let _ = writeln!(out, "# HELP {ns}_errors_total Total number of api errors by route, method and error kind");
+    let _ = writeln!(out, "# TYPE {ns}_errors_total counter");
+    for ((path, method), metrics) in &endpoints {
+      for (kind, count) in &metrics.errors {
+        let _ = writeln!(out, "{ns}_errors_total{{{},kind=\"{kind}\"}} {count}", labels(path, method));
+      }
+    }
+
+    macro_rules! histogram {
+      ($field:ident, $name:expr, $help:expr) => {
+        let name = format!("{ns}_{}", $name);
+        let _ = writeln!(out, "# HELP {name} {}", $help);
+        let _ = writeln!(out, "# TYPE {name} histogram");
+        for ((path, method), metrics) in &endpoints {
+          metrics.$field.render(&mut out, &name, &labels(path, method));
+        }
+      };
+    }
+
+    histogram!(latency, "request_duration_seconds", "Request duration in seconds by route and method");
+    histogram!(request_size, "request_size_bytes", "Request body size in bytes by route and method");
+    histogram!(response_size, "response_size_bytes", "Response body size in bytes by route and method");
+
+    out
+  }
+
+  /// Serve the rendered metrics as a text/plain response
+  pub fn response(&self) -> Response {
+    let mut res = Response::new(self.render().into());
+    res.headers_mut().insert(CONTENT_TYPE, HeaderValue::from_static("text/plain; version=0.0.4"));
+    res
+  }
+}
+
+fn labels(path: &str, method: &Method) -> String {
+  format!("route=\"{}\",method=\"{}\"", escape(path), method.as_str())
+}
+
+fn escape(value: &str) -> String {
+  value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+/// The serialized name of the error kind, eg: `INVALID_QUERY_PARSE`
+pub fn kind_label(kind: &ApiErrorKind) -> String {
+  serde_json::to_value(kind)
+    .ok()
+    .and_then(|v| v["kind"].as_str().map(String::from))
+    .unwrap_or_else(|| format!("{kind:?}"))
+}
+
+/// Run `handler` and record the request in `metrics` under the registered `path` template and `method`
+pub async fn measured<F: std::future::Future<Output = Response>>(
+  metrics: Option<&Metrics>,
+  path: &str,
+  method: &Method,
+  req: Request,
+  handler: impl FnOnce(Request) -> F,
+) -> Response {
+  let metrics = match metrics {
+    Some(metrics) => metrics,
+    None => return handler(req).await,
+  };
+
+  let request_size = req
+    .headers()
+    .get(CONTENT_LENGTH)
+    .and_then(|v| v.to_str().ok())
+    .and_then(|v| v.parse::<u64>().ok());
+
+  let start = Instant::now();
+  let res = handler(req).await;
+  let seconds = start.elapsed().as_secs_f64();
+
+  let response_size = res.body().size_hint().exact();
+  let error_kind = res.extensions().get::<ApiError>().map(|err| &err.kind);
+
+  metrics.observe(path, method, Observation {
+    status: res.status().as_u16(),
+    error_kind,
+    seconds,
+    request_size,
+    response_size,
+  });
+
+  res
+}
@@ -0,0 +1,157 @@
+use serde_json::{json, Map, Value};
+
+use crate::contract::validate;
+use crate::fuzz::{generate, Rng};
+use crate::schema::{merge_all_of, resolve, types};
+
+const MAX_DEPTH: usize = 8;
+
+/// A deterministic example value for `schema`
+///
+/// The schema `examples`, `example` and `default` values are used when present, eg: `#[schemars(example = "...")]`, \
+/// otherwise a readable value is built from the type and bounds
+pub fn example(root: &Value, schema: &Value) -> Value {
+  example_at(root, schema, 0)
+}
+
+fn example_at(root: &Value, schema: &Value, depth: usize) -> Value {
+  let schema = resolve(root, schema);
+  if !schema.is_object() {
+    return Value::Null;
+  }
+
+  if let Some(example) = schema.get("examples").and_then(|v| v.as_array()).and_then(|v| v.first()) {
+    return example.clone();
+  }
+
+  for key in ["example", "default", "const"] {
+    if let Some(value) = schema.get(key) {
+      return value.clone();
+    }
+  }
+
+  if let Some(variant) = schema.get("enum").and_then(|v| v.as_array()).and_then(|v| v.first()) {
+    return variant.clone();
+  }
+
+  for key in ["oneOf", "anyOf"] {
+    if let Some(sub) = schema.get(key).and_then(|v| v.as_array()).and_then(|v| v.first()) {
+      return example_at(root, sub, depth + 1);
+    }
+  }
+
+  if let Some(merged) = merge_all_of(root, schema) {
+    return example_at(root, &merged, depth);
+  }
+
+  let types = types(schema);
+  let ty = match types.iter().find(|ty| **ty != "null") {
+    Some(ty) => *ty,
+    None if types.contains(&"null") => "null",
+    None if schema.get("properties").is_some() => "object",
+    None if schema.get("items").is_some() => "array",
+    None => return Value::Null,
+  };
+
+  let number = |key: &str| schema.get(key).and_then(|v| v.as_f64());
+
+  match ty {
+    "null" => Value::Null,
+    "boolean" => Value::Bool(true),
+    "integer" => {
+      let mut value = number("minimum").map(|v| v.ceil() as i64).unwrap_or(0);
+      if schema.get("exclusiveMinimum").and_then(|v| v.as_bool()) == Some(true) {
+        value += 1;
+      }
+      if let Some(max) = number("maximum") {
+        value = value.min(max.floor() as i64);
+      }
+      json!(value)
+    }
+    "number" => {
+      let value = match (number("minimum"), number("maximum")) {
+        (Some(min), Some(max)) => (min + max) / 2.0,
+        (Some(min), None) => min + 1.0,
+        (None, Some(max)) => max - 1.0,
+        (None, None) => 0.0,
+      };
+      json!(value)
+    }
+    "string" => json!(example_string(schema)),
+    "array" => {
+      let min = schema.get("minItems").and_then(|v| v.as_u64()).unwrap_or(0) as usize;
+      let max = schema.get("maxItems").and_then(|v| v.as_u64()).map(|v| v as usize).unwrap_or(usize::MAX);
+      let len = if depth >= MAX_DEPTH { min } else { min.max(1).min(max) };
+      let items = schema.get("items").cloned().unwrap_or(Value::Bool(true));
+      let items = (0..len)
+        .map(|i| match &items {
+          Value::Array(tuple) => tuple.get(i).map(|s| example_at(root, s, depth + 1)).unwrap_or(Value::Null),
+          items => example_at(root, items, depth + 1),
+        })
+        .collect();
+      Value::Array(items)
+    }
+    _ => {
+      let required = schema
+        .get("required")
+        .and_then(|v| v.as_array())
+        .map(|required| required.iter().filter_map(|v| v.as_str()).collect::<Vec<_>>())
+        .unwrap_or_default();
+
+      let mut object = Map::new();
+      if let Some(properties) = schema.get("properties").and_then(|v| v.as_object()) {
+        for (name, property) in properties {
+          // recursive types stop at the required properties
+          if depth < MAX_DEPTH || required.contains(&name.as_str()) {
+            object.insert(name.clone(), example_at(root, property, depth + 1));
+          }
+        }
+      }
+      Value::Object(object)
+    }
+  }
+}
+
+fn example_string(schema: &Value) -> String {
+  let value = match schema.get("format").and_then(|v| v.as_str()) {
+    Some("email") => "user@example.com",
+    Some("date-time") => "2024-01-02T03:04:05Z",
+    Some("date") => "2024-01-02",
+    Some("time") => "03:04:05",
+    Some("uuid") => "00000000-0000-4000-8000-000000000000",
+    Some("uri") => "https://example.com",
+    Some("ipv4") => "127.0.0.1",
+    Some("ipv6") => "::1",
+    _ => "string",
+  };
+
+  let min = schema.get("minLength").and_then(|v| v.as_u64()).unwrap_or(0) as usize;
+  let max = schema.get("maxLength").and_then(|v| v.as_u64()).map(|v| v as usize).unwrap_or(usize::MAX);
+
+  let mut value = value.to_string();
+  while value.chars().count() < min {
+    value.push('x');
+  }
+  value.chars().take(max).collect()
+}
+
+/// An example for `schema` that is valid against it
+///
+/// Falls back to generated values when the examples don't validate, eg: strings with a `pattern`
+pub fn valid_example(schema: &Value) -> Value {
+  let value = example(schema, schema);
+  if validate(schema, &value).is_empty() {
+    return value;
+  }
+
+  let mut rng = Rng::new(1);
+  for _ in 0..64 {
+    let generated = generate(schema, schema, &mut rng);
+    if validate(schema, &generated).is_empty() {
+      return generated;
+    }
+  }
+
+  tracing::warn!("no valid example could be generated for schema {schema}, serving an invalid one");
+  value
+}
@@ -0,0 +1,170 @@
+use axum::{
+  extract::Request,
+  http::{HeaderMap, Method},
+  response::Response,
+};
+use opentelemetry::{
+  propagation::{Extractor, TextMapPropagator},
+  trace::{FutureExt, SpanKind, Status, TraceContextExt, TraceError, Tracer as _, TracerProvider as _},
+  Context, KeyValue,
+};
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::{
+  propagation::TraceContextPropagator,
+  export::trace::SpanExporter,
+  runtime,
+  trace::{Tracer, TracerProvider},
+  Resource,
+};
+
+use crate::{error::ApiError, metrics::kind_label};
+
+/// Where the server spans are sent
+#[derive(Debug, Clone)]
+pub enum OtelExporter {
+  /// Export spans with OTLP over gRPC to a collector, eg: `http://localhost:4317`
+  Otlp { endpoint: String },
+}
+
+/// OpenTelemetry integration for the registry handlers
+///
+/// Extracts the W3C `traceparent` and `tracestate` headers from every request
+/// and opens a server span as a child of the remote context \
+/// The span context is available to endpoints in the request extensions as an [`opentelemetry::Context`]
+#[derive(Debug)]
+pub struct Otel {
+  pub provider: TracerProvider,
+  tracer: Tracer,
+  propagator: TraceContextPropagator,
+}
+
+impl Otel {
+  pub fn new(service_name: &str, exporter: OtelExporter) -> Result<Self, TraceError> {
+    let resource = Resource::new([KeyValue::new("service.name", service_name.to_string())]);
+
+    let provider = match exporter {
+      OtelExporter::Otlp { endpoint } => {
+        let exporter = opentelemetry_otlp::SpanExporter::builder()
+          .with_tonic()
+          .with_endpoint(endpoint)
+          .build()?;
+
+        TracerProvider::builder()
+          .with_resource(resource)
+          .with_batch_exporter(exporter, runtime::Tokio)
+          .build()
+      }
+    };
+
+    Ok(Self::with_provider(provider))
+  }
+
+  /// Export every span synchronously to `exporter` as soon as it ends \
+  /// Used in tests with the `InMemorySpanExporter` of the `testing` feature of `opentelemetry_sdk`
+  pub fn with_simple_exporter(service_name: &str, exporter: impl SpanExporter + 'static) -> Self {
+    let resource = Resource::new([KeyValue::new("service.name", service_name.to_string())]);
+    let provider = TracerProvider::builder()
+      .with_resource(resource)
+      .with_simple_exporter(exporter)
+      .build();
+
+    Self::with_provider(provider)
+  }
+
+  fn with_provider(provider: TracerProvider) -> Self {
+    let tracer = provider.tracer("auto-api");
+
+    Self {
+      provider,
+      tracer,
+      propagator: TraceContextPropagator::new(),
+    }
+  }
+
+  /// A guard that shuts down the tracer provider when dropped, flushing the spans not exported yet \
+  /// Keep it alive until the server stops, eg: `let _guard = otel.shutdown_guard();` in `main`
+  pub fn shutdown_guard(&self) -> OtelGuard {
+    OtelGuard { provider: self.provider.clone() }
+  }
+
+  /// Extract the remote trace context from the request headers
+  pub fn extract(&self, headers: &HeaderMap) -> Context {
+    self.propagator.extract(&HeaderExtractor(headers))
+  }
+}
+
+/// Shuts down the tracer provider on drop, see [`Otel::shutdown_guard`]
+#[must_use = "the provider is shut down when the guard is dropped"]
+#[derive(Debug)]
+pub struct OtelGuard {
+  provider: TracerProvider,
+}
+
+impl Drop for OtelGuard {
+  fn drop(&mut self) {
+    if let Err(err) = self.provider.shutdown() {
+      tracing::warn!("failed to shut down the tracer provider: {err}");
+    }
+  }
+}
+
+struct HeaderExtractor<'a>(&'a HeaderMap);
+
+impl Extractor for HeaderExtractor<'_> {
+  fn get(&self, key: &str) -> Option<&str> {
+    self.0.get(key).and_then(|v| v.to_str().ok())
+  }
+
+  fn keys(&self) -> Vec<&str> {
+    self.0.keys().map(|k| k.as_str()).collect()
+  }
+}
+
+/// Run `handler` inside an OpenTelemetry server span for the registered `path` template and `method`
+pub async fn otel_traced<F: std::future::Future<Output = Response>>(
+  otel: Option<&Otel>,
+  path: &str,
+  method: &Method,
+  mut req: Request,
+  handler: impl FnOnce(Request) -> F,
+) -> Response {
+  let otel = match otel {
+    Some(otel) => otel,
+    None => return handler(req).await,
+  };
+
+  let parent = otel.extract(req.headers());
+
+  let span = otel
+    .tracer
+    .span_builder(format!("{method} {path}"))
+    .with_kind(SpanKind::Server)
+    .with_attributes([
+      KeyValue::new("http.request.method", method.to_string()),
+      KeyValue::new("http.route", path.to_string()),
+      KeyValue::new("url.path", req.uri().path().to_string()),
+    ])
+    .start_with_context(&otel.tracer, &parent);
+
+  let cx = parent.with_span(span);
+  req.extensions_mut().insert(cx.clone());
+
+  let res = handler(req).with_context(cx.clone()).await;
+
+  let span = cx.span();
+  let status = res.status().as_u16();
+  span.set_attribute(KeyValue::new("http.response.status_code", status as i64));
+
+  if let Some(err) = res.extensions().get::<ApiError>() {
+    span.set_attribute(KeyValue::new("error.type", kind_label(&err.kind)));
+  }
+
+  // per the semantic conventions only 5xx responses mark a server span as failed
+  if status >= 500 {
+    span.set_status(Status::error(format!("status {status}")));
+  }
+
+  span.end();
+
+  res
+}
@@ -0,0 +1,276 @@
+use std::collections::BTreeSet;
+
+use schemars::Schema as SchemarsSchema;
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum PlaceholderKind {
+  /// A `:name` segment, matches exactly one path segment
+  Param,
+  /// A `*name` segment, matches the rest of the path
+  Wildcard,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Placeholder<'a> {
+  pub name: &'a str,
+  pub kind: PlaceholderKind,
+}
+
+/// Parse the `:name` and `*name` placeholders of an axum path template
+pub fn placeholders(path: &str) -> Vec<Placeholder<'_>> {
+  path
+    .split('/')
+    .filter_map(|segment| {
+      if let Some(name) = segment.strip_prefix(':') {
+        Some(Placeholder { name, kind: PlaceholderKind::Param })
+      } else {
+        segment.strip_prefix('*').map(|name| Placeholder { name, kind: PlaceholderKind::Wildcard })
+      }
+    })
+    .collect()
+}
+
+/// Convert an axum path template to an OpenAPI one
+///
+/// `/users/:id/*rest` => `/users/{id}/{rest}`
+pub fn to_openapi(path: &str) -> String {
+  path
+    .split('/')
+    .map(|segment| {
+      match segment.strip_prefix(':').or_else(|| segment.strip_prefix('*')) {
+        Some(name) => format!("{{{name}}}"),
+        None => segment.to_string(),
+      }
+    })
+    .collect::<Vec<_>>()
+    .join("/")
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum PathParamsError {
+  #[error("path `{path}` has an empty placeholder name")]
+  EmptyPlaceholder { path: String },
+  #[error("path `{path}` has a duplicated placeholder `{name}`")]
+  DuplicatePlaceholder { path: String, name: String },
+  #[error("wildcard placeholder `*{name}` must be the last segment of path `{path}`")]
+  WildcardNotLast { path: String, name: String },
+  #[error("path `{path}` has placeholders but the endpoint Params type is ()")]
+  MissingParams { path: String },
+  #[error("params schema for path `{path}` is not an object with properties")]
+  NotAnObject { path: String },
+  #[error("placeholder `{name}` in path `{path}` has no matching field in Params")]
+  MissingField { path: String, name: String },
+  #[error("placeholder `{name}` in path `{path}` matches an optional field in Params, path fields must be required")]
+  OptionalField { path: String, name: String },
+  #[error("field `{name}` in Params has no matching placeholder in path `{path}`")]
+  MissingPlaceholder { path: String, name: String },
+}
+
+/// Check that every placeholder in `path` has a matching required property
+/// in the `params` schema and that every property has a matching placeholder
+pub fn check_params(path: &str, params: Option<&SchemarsSchema>) -> Result<(), PathParamsError> {
+  let placeholders = placeholders(path);
+
+  let mut names = BTreeSet::new();
+  for (i, placeholder) in placeholders.iter().enumerate() {
+    if placeholder.name.is_empty() {
+      return Err(PathParamsError::EmptyPlaceholder { path: path.to_string() });
+    }
+
+    if !names.insert(placeholder.name) {
+      return Err(PathParamsError::DuplicatePlaceholder {
+        path: path.to_string(),
+        name: placeholder.name.to_string(),
+      });
+    }
+
+    if placeholder.kind == PlaceholderKind::Wildcard && i != placeholders.len() - 1 {
+      return Err(PathParamsError::WildcardNotLast {
+        path: path.to_string(),
+        name: placeholder.name.to_string(),
+      });
+    }
+  }
+
+  let schema = match params {
+    Some(schema) => schema,
+    None => {
+      if names.is_empty() {
+        return Ok(());
+      } else {
+        return Err(PathParamsError::MissingParams { path: path.to_string() });
+      }
+    }
+  };
+
+  let value = schema.as_value();
+  let properties = match value["properties"].as_object() {
+    Some(properties) => properties,
+    None => return Err(PathParamsError::NotAnObject { path: path.to_string() }),
+  };
+
+  let required = value["required"]
+    .as_array()
+    .map(|required| required.iter().filter_map(|v| v.as_str()).collect::<BTreeSet<_>>())
+    .unwrap_or_default();
+
+  for name in &names {
+    if !properties.contains_key(*name) {
+      return Err(PathParamsError::MissingField {
+        path: path.to_string(),
+        name: name.to_string(),
+      });
+    }
+
+    if !required.contains(name) {
+      return Err(PathParamsError::OptionalField {
+        path: path.to_string(),
+        name: name.to_string(),
+      });
+    }
+  }
+
+  for name in properties.keys() {
+    if !names.contains(name.as_str()) {
+      return Err(PathParamsError::MissingPlaceholder {
+        path: path.to_string(),
+        name: name.clone(),
+      });
+    }
+  }
+
+  Ok(())
+}
+
+/// Build a concrete path from an axum path template and the serialized params
+///
+/// `/users/:id` with `{ "id": "a b" }` => `/users/a%20b`
+pub fn fill(path: &str, params: &serde_json::Value) -> Result<String, String> {
+  path
+    .split('/')
+    .map(|segment| {
+      let (name, wildcard) = match (segment.strip_prefix(':'), segment.strip_prefix('*')) {
+        (Some(name), _) => (name, false),
+        (_, Some(name)) => (name, true),
+        _ => return Ok(segment.to_string()),
+      };
+
+      let value = match &params[name] {
+        serde_json::Value::String(value) => value.clone(),
+        serde_json::Value::Number(value) => value.to_string(),
+        serde_json::Value::Bool(value) => value.to_string(),
+        _ => return Err(format!("param `{name}` must be a string, number or boolean")),
+      };
+
+      // wildcards match the rest of the path so their slashes are kept
+      Ok(match wildcard {
+        true => value.split('/').map(encode_segment).collect::<Vec<_>>().join("/"),
+        false => encode_segment(&value),
+      })
+    })
+    .collect::<Result<Vec<_>, _>>()
+    .map(|segments| segments.join("/"))
+}
+
+fn encode_segment(value: &str) -> String {
+  let mut out = String::with_capacity(value.len());
+  for byte in value.bytes() {
+    match byte {
+      b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => out.push(byte as char),
+      _ => out.push_str(&format!("%{byte:02X}")),
+    }
+  }
+  out
+}
+
+#[cfg(test)]
+mod tests {
+  use serde_json::json;
+
+  use super::*;
+
+  fn schema(value: serde_json::Value) -> SchemarsSchema {
+    SchemarsSchema::try_from(value).unwrap()
+  }
+
+  fn params(required: &[&str], optional: &[&str]) -> SchemarsSchema {
+    let properties = required
+      .iter()
+      .chain(optional)
+      .map(|name| (name.to_string(), json!({ "type": "string" })))
+      .collect::<serde_json::Map<_, _>>();
+
+    schema(json!({ "type": "object", "properties": properties, "required": required }))
+  }
+
+  #[test]
+  fn placeholder_extraction() {
+    assert_eq!(
+      placeholders("/users/:id/files/*rest"),
+      vec![
+        Placeholder { name: "id", kind: PlaceholderKind::Param },
+        Placeholder { name: "rest", kind: PlaceholderKind::Wildcard },
+      ]
+    );
+    assert!(placeholders("/users").is_empty());
+    assert_eq!(to_openapi("/users/:id/files/*rest"), "/users/{id}/files/{rest}");
+  }
+
+  #[test]
+  fn matching_params() {
+    check_params("/users", None).unwrap();
+    check_params("/users/:id", Some(&params(&["id"], &[]))).unwrap();
+    check_params("/users/:id/files/*rest", Some(&params(&["id", "rest"], &[]))).unwrap();
+  }
+
+  #[test]
+  fn params_errors() {
+    let err = |path, params: Option<SchemarsSchema>| check_params(path, params.as_ref()).unwrap_err();
+
+    assert!(matches!(err("/users/:", None), PathParamsError::EmptyPlaceholder { .. }));
+    assert!(matches!(
+      err("/users/:id/:id", Some(params(&["id"], &[]))),
+      PathParamsError::DuplicatePlaceholder { name, .. } if name == "id"
+    ));
+    assert!(matches!(
+      err("/files/*rest/:id", Some(params(&["id", "rest"], &[]))),
+      PathParamsError::WildcardNotLast { name, .. } if name == "rest"
+    ));
+    assert!(matches!(err("/users/:id", None), PathParamsError::MissingParams { .. }));
+    assert!(matches!(
+      err("/users/:id", Some(schema(json!({ "type": "string" })))),
+      PathParamsError::NotAnObject { .. }
+    ));
+    assert!(matches!(
+      err("/users/:id", Some(params(&["user_id"], &[]))),
+      PathParamsError::MissingField { name, .. } if name == "id"
+    ));
+    assert!(matches!(
+      err("/users/:id", Some(params(&[], &["id"]))),
+      PathParamsError::OptionalField { name, .. } if name == "id"
+    ));
+    assert!(matches!(
+      err("/users/:id", Some(params(&["id", "org"], &[]))),
+      PathParamsError::MissingPlaceholder { name, .. } if name == "org"
+    ));
+  }
+
+  #[test]
+  fn fill_encodes_segments() {
+    assert_eq!(fill("/users/:id", &json!({ "id": "a b/c" })).unwrap(), "/users/a%20b%2Fc");
+    assert_eq!(fill("/users/:id", &json!({ "id": 42 })).unwrap(), "/users/42");
+    assert_eq!(fill("/flags/:on", &json!({ "on": true })).unwrap(), "/flags/true");
+    assert!(fill("/users/:id", &json!({ "id": null })).is_err());
+    assert!(fill("/users/:id", &json!({ "id": ["a"] })).is_err());
+  }
+
+  #[test]
+  fn fill_keeps_wildcard_slashes() {
+    assert_eq!(
+      fill("/files/*rest", &json!({ "rest": "docs/a b/ü.txt" })).unwrap(),
+      "/files/docs/a%20b/%C3%BC.txt"
+    );
+    assert_eq!(encode_segment("AZaz09-._~"), "AZaz09-._~");
+    assert_eq!(encode_segment("?#%"), "%3F%23%25");
+  }
+}
@@ -0,0 +1,583 @@
+use std::{
+  collections::HashMap,
+  net::SocketAddr,
+  sync::{Arc, Mutex},
+  time::{Duration, Instant},
+};
+
+use axum::{
+  async_trait,
+  extract::{ConnectInfo, Request},
+  http::{request::Parts, HeaderName, HeaderValue, Method, StatusCode},
+  response::Response,
+};
+
+use crate::error::{ApiError, ApiErrorKind};
+
+pub const RATELIMIT_LIMIT: HeaderName = HeaderName::from_static("ratelimit-limit");
+pub const RATELIMIT_REMAINING: HeaderName = HeaderName::from_static("ratelimit-remaining");
+pub const RATELIMIT_RESET: HeaderName = HeaderName::from_static("ratelimit-reset");
+
+/// A token bucket of `requests` tokens that is fully refilled every `per` \
+/// A limit of 0 requests denies every request
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct RateLimit {
+  pub requests: u32,
+  pub per: Duration,
+}
+
+impl RateLimit {
+  /// Panics if `requests` or `per` is zero, build the struct directly for a limit that denies every request
+  pub const fn new(requests: u32, per: Duration) -> Self {
+    assert!(requests > 0, "a rate limit needs at least one request");
+    assert!(!per.is_zero(), "a rate limit needs a non zero period");
+    Self { requests, per }
+  }
+
+  pub const fn per_second(requests: u32) -> Self {
+    Self::new(requests, Duration::from_secs(1))
+  }
+
+  pub const fn per_minute(requests: u32) -> Self {
+    Self::new(requests, Duration::from_secs(60))
+  }
+
+  pub const fn per_hour(requests: u32) -> Self {
+    Self::new(requests, Duration::from_secs(60 * 60))
+  }
+
+  /// Whether no request can ever be allowed, the buckets of these limits are not stored
+  pub fn denies_all(&self) -> bool {
+    self.requests == 0 || self.per.is_zero()
+  }
+
+  /// Time it takes to refill a single token
+  pub fn token_interval(&self) -> Duration {
+    self.per / self.requests.max(1)
+  }
+}
+
+pub type KeyFn = Arc<dyn Fn(&Parts) -> Option<String> + Send + Sync>;
+
+/// How the client of a request is identified when the endpoint doesn't provide a key
+#[derive(Clone)]
+pub enum RateLimitKey {
+  /// The peer address, requires serving with `into_make_service_with_connect_info::<SocketAddr>`
+  Ip,
+  /// The value of a request header, eg: `x-real-ip` behind a trusted proxy or an api key header
+  Header(HeaderName),
+  /// A custom key computed from the request
+  Custom(KeyFn),
+}
+
+impl RateLimitKey {
+  pub fn key(&self, parts: &Parts) -> Option<String> {
+    match self {
+      RateLimitKey::Ip => parts
+        .extensions
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip().to_string()),
+      RateLimitKey::Header(name) => parts
+        .headers
+        .get(name)
+        .and_then(|v| v.to_str().ok())
+        .map(String::from),
+      RateLimitKey::Custom(f) => f(parts),
+    }
+  }
+}
+
+/// The result of taking a token from a bucket
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct RateLimitDecision {
+  pub allowed: bool,
+  pub limit: u32,
+  pub remaining: u32,
+  /// Time until the bucket is full again
+  pub reset: Duration,
+  /// Time until the next token is available, only set when the request is not allowed
+  pub retry_after: Option<Duration>,
+}
+
+impl RateLimitDecision {
+  /// The decision for a limit that [`RateLimit::denies_all`]
+  fn denied(limit: &RateLimit) -> Self {
+    Self {
+      allowed: false,
+      limit: limit.requests,
+      remaining: 0,
+      reset: limit.per,
+      retry_after: Some(limit.per),
+    }
+  }
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("rate limit store error: {0}")]
+pub struct RateLimitStoreError(pub String);
+
+/// Storage for the token buckets
+#[async_trait]
+pub trait RateLimitStore: Send + Sync + 'static {
+  /// Take a token for `key` from the bucket described by `limit`
+  async fn take(&self, key: &str, limit: &RateLimit) -> Result<RateLimitDecision, RateLimitStoreError>;
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Bucket {
+  tokens: f64,
+  updated: Instant,
+  /// The bucket is full again and can be dropped after being idle for this long
+  ttl: Duration,
+}
+
+#[derive(Debug)]
+struct Buckets {
+  map: HashMap<String, Bucket>,
+  purged: Instant,
+}
+
+/// An in process [`RateLimitStore`], buckets are not shared between instances of the server
+///
+/// Idle buckets are dropped once they are full again, checked at most every [`MemoryStore::purge_interval`]
+#[derive(Debug)]
+pub struct MemoryStore {
+  buckets: Mutex<Buckets>,
+  pub purge_interval: Duration,
+}
+
+impl Default for MemoryStore {
+  fn default() -> Self {
+    Self {
+      buckets: Mutex::new(Buckets { map: HashMap::new(), purged: Instant::now() }),
+      purge_interval: Duration::from_secs(60),
+    }
+  }
+}
+
+impl MemoryStore {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Remove the buckets that are full again, done automatically while taking tokens
+  pub fn purge(&self) {
+    let now = Instant::now();
+    let mut buckets = self.buckets.lock().unwrap();
+    buckets.map.retain(|_, bucket| now.duration_since(bucket.updated) < bucket.ttl);
+    buckets.purged = now;
+  }
+
+  /// The number of buckets in memory
+  pub fn len(&self) -> usize {
+    self.buckets.lock().unwrap().map.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.len() == 0
+  }
+}
+
+#[async_trait]
+impl RateLimitStore for MemoryStore {
+  async fn take(&self, key: &str, limit: &RateLimit) -> Result<RateLimitDecision, RateLimitStoreError> {
+    if limit.denies_all() {
+      return Ok(RateLimitDecision::denied(limit));
+    }
+
+    let now = Instant::now();
+    if now.duration_since(self.buckets.lock().unwrap().purged) >= self.purge_interval {
+      self.purge();
+    }
+
+    let capacity = limit.requests as f64;
+    let rate = capacity / limit.per.as_secs_f64();
+
+    let mut buckets = self.buckets.lock().unwrap();
+    let bucket = buckets.map.entry(key.to_string()).or_insert(Bucket { tokens: capacity, updated: now, ttl: limit.per });
+    bucket.ttl = limit.per;
+
+    let elapsed = now.duration_since(bucket.updated).as_secs_f64();
+    bucket.tokens = (bucket.tokens + elapsed * rate).min(capacity);
+    bucket.updated = now;
+
+    let allowed = bucket.tokens >= 1.0;
+    if allowed {
+      bucket.tokens -= 1.0;
+    }
+
+    let reset = Duration::from_secs_f64((capacity - bucket.tokens) / rate);
+    let retry_after = if allowed {
+      None
+    } else {
+      Some(Duration::from_secs_f64((1.0 - bucket.tokens) / rate))
+    };
+
+    Ok(RateLimitDecision {
+      allowed,
+      limit: limit.requests,
+      remaining: bucket.tokens.floor() as u32,
+      reset,
+      retry_after,
+    })
+  }
+}
+
+/// The minimal command set needed from a Redis compatible server (Redis, Valkey, KeyDB, Dragonfly...)
+///
+/// Implement this over the client of your choice to use [`RedisStore`]
+#[async_trait]
+pub trait RedisEval: Send + Sync + 'static {
+  /// Run `EVAL script numkeys keys... args...` and return the integer array reply
+  async fn eval(&self, script: &str, keys: &[String], args: &[String]) -> Result<Vec<i64>, RateLimitStoreError>;
+}
+
+/// A [`RateLimitStore`] shared between server instances through a Redis compatible server
+pub struct RedisStore<C> {
+  pub client: C,
+  /// Prefix of the keys written by this store
+  pub prefix: String,
+}
+
+impl<C: RedisEval> RedisStore<C> {
+  pub fn new(client: C) -> Self {
+    Self {
+      client,
+      prefix: String::from("auto-api:ratelimit:"),
+    }
+  }
+}
+
+// token bucket with the state stored as a hash of { tokens, updated (ms) }
+// returns [allowed, remaining, reset_ms, retry_after_ms]
+const REDIS_TOKEN_BUCKET: &str = r#"
+local capacity = tonumber(ARGV[1])
+local per_ms = tonumber(ARGV[2])
+local time = redis.call("TIME")
+local now = tonumber(time[1]) * 1000 + math.floor(tonumber(time[2]) / 1000)
+local rate = capacity / per_ms
+local state = redis.call("HMGET", KEYS[1], "tokens", "updated")
+local tokens = tonumber(state[1]) or capacity
+local updated = tonumber(state[2]) or now
+tokens = math.min(capacity, tokens + (now - updated) * rate)
+local allowed = 0
+if tokens >= 1 then
+  tokens = tokens - 1
+  allowed = 1
+end
+redis.call("HSET", KEYS[1], "tokens", tostring(tokens), "updated", now)
+redis.call("PEXPIRE", KEYS[1], per_ms)
+local retry_after = 0
+if allowed == 0 then
+  retry_after = math.ceil((1 - tokens) / rate)
+end
+return { allowed, math.floor(tokens), math.ceil((capacity - tokens) / rate), retry_after }
+"#;
+
+#[async_trait]
+impl<C: RedisEval> RateLimitStore for RedisStore<C> {
+  async fn take(&self, key: &str, limit: &RateLimit) -> Result<RateLimitDecision, RateLimitStoreError> {
+    if limit.denies_all() {
+      return Ok(RateLimitDecision::denied(limit));
+    }
+
+    let keys = [format!("{}{key}", self.prefix)];
+    let args = [limit.requests.to_string(), limit.per.as_millis().to_string()];
+    let reply = self.client.eval(REDIS_TOKEN_BUCKET, &keys, &args).await?;
+
+    let [allowed, remaining, reset, retry_after] = reply[..] else {
+      return Err(RateLimitStoreError(format!("unexpected token bucket reply {reply:?}")));
+    };
+
+    Ok(RateLimitDecision {
+      allowed: allowed == 1,
+      limit: limit.requests,
+      remaining: remaining.max(0) as u32,
+      reset: Duration::from_millis(reset.max(0) as u64),
+      retry_after: (allowed != 1).then(|| Duration::from_millis(retry_after.max(0) as u64)),
+    })
+  }
+}
+
+/// Registry wide rate limiting configuration
+pub struct RateLimiter {
+  /// Limit applied to every endpoint that doesn't declare its own in [`crate::endpoint::Endpoint::rate_limit`]
+  pub default: Option<RateLimit>,
+  /// Limits applied to groups of endpoints by path prefix, the longest matching prefix wins \
+  /// Prefixes match whole segments, `/user` matches `/user/:id` but not `/users` \
+  /// The endpoints of a group share a single quota
+  pub groups: Vec<(String, RateLimit)>,
+  /// How the client is identified when the endpoint doesn't provide a key
+  pub key: RateLimitKey,
+  pub store: Arc<dyn RateLimitStore>,
+}
+
+impl Default for RateLimiter {
+  fn default() -> Self {
+    Self {
+      default: None,
+      groups: vec![],
+      key: RateLimitKey::Ip,
+      store: Arc::new(MemoryStore::new()),
+    }
+  }
+}
+
+impl RateLimiter {
+  /// The limit that applies to an endpoint with the bucket scope it is counted in, eg: `group:/admin` or `GET /users`
+  pub fn limit_for(&self, method: &Method, path: &str, endpoint: Option<RateLimit>) -> Option<(String, RateLimit)> {
+    let endpoint_scope = || format!("{method} {path}");
+    if let Some(limit) = endpoint {
+      return Some((endpoint_scope(), limit));
+    }
+
+    let group = self
+      .groups
+      .iter()
+      .filter(|(prefix, _)| in_group(path, prefix))
+      .max_by_key(|(prefix, _)| prefix.len());
+
+    match group {
+      Some((prefix, limit)) => Some((format!("group:{prefix}"), *limit)),
+      None => self.default.map(|limit| (endpoint_scope(), limit)),
+    }
+  }
+}
+
+fn in_group(path: &str, prefix: &str) -> bool {
+  let prefix = prefix.trim_end_matches('/');
+  match path.strip_prefix(prefix) {
+    Some(rest) => rest.is_empty() || rest.starts_with('/'),
+    None => false,
+  }
+}
+
+/// Passed to the endpoint pipeline in the request extensions
+#[derive(Clone)]
+pub struct RateLimitContext {
+  pub limiter: Arc<RateLimiter>,
+  pub method: Method,
+  pub path: Arc<str>,
+  decision: Arc<Mutex<Option<RateLimitDecision>>>,
+}
+
+impl RateLimitContext {
+  /// Take a token for the current request, `limit` and `key` are the endpoint overrides
+  pub async fn check(&self, limit: Option<RateLimit>, key: Option<String>, parts: &Parts) -> Result<(), ApiError> {
+    let (scope, limit) = match self.limiter.limit_for(&self.method, &self.path, limit) {
+      Some(limit) => limit,
+      None => return Ok(()),
+    };
+
+    let key = match key.or_else(|| self.limiter.key.key(parts)) {
+      Some(key) => key,
+      // unidentified clients are not limited
+      None => return Ok(()),
+    };
+
+    let decision = match self.limiter.store.take(&format!("{scope}:{key}"), &limit).await {
+      Ok(decision) => decision,
+      Err(err) => {
+        // fail open, an unavailable store should not take the api down
+        tracing::warn!("{err}");
+        return Ok(());
+      }
+    };
+
+    *self.decision.lock().unwrap() = Some(decision);
+
+    if decision.allowed {
+      Ok(())
+    } else {
+      Err(ApiError {
+        status: StatusCode::TOO_MANY_REQUESTS.as_u16(),
+        kind: ApiErrorKind::RateLimited,
+        message: String::from("too many requests, retry later"),
+      })
+    }
+  }
+}
+
+/// Run `handler` with a [`RateLimitContext`] for the registered `path` and `method` and add the `RateLimit-*` headers to the response
+pub async fn rate_limited<F: std::future::Future<Output = Response>>(
+  limiter: Option<&Arc<RateLimiter>>,
+  method: &Method,
+  path: &str,
+  mut req: Request,
+  handler: impl FnOnce(Request) -> F,
+) -> Response {
+  let limiter = match limiter {
+    Some(limiter) => limiter,
+    None => return handler(req).await,
+  };
+
+  let cx = RateLimitContext {
+    limiter: limiter.clone(),
+    method: method.clone(),
+    path: path.into(),
+    decision: Default::default(),
+  };
+
+  req.extensions_mut().insert(cx.clone());
+
+  let mut res = handler(req).await;
+
+  let decision = *cx.decision.lock().unwrap();
+  if let Some(decision) = decision {
+    let headers = res.headers_mut();
+    headers.insert(RATELIMIT_LIMIT, HeaderValue::from(decision.limit));
+    headers.insert(RATELIMIT_REMAINING, HeaderValue::from(decision.remaining));
+    headers.insert(RATELIMIT_RESET, HeaderValue::from(decision.reset.as_secs_f64().ceil() as u64));
+    if let Some(retry_after) = decision.retry_after {
+      headers.insert(axum::http::header::RETRY_AFTER, HeaderValue::from(retry_after.as_secs_f64().ceil() as u64));
+    }
+  }
+
+  res
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn limiter(groups: &[(&str, RateLimit)]) -> RateLimiter {
+    RateLimiter {
+      default: Some(RateLimit::per_second(10)),
+      groups: groups.iter().map(|(prefix, limit)| (prefix.to_string(), *limit)).collect(),
+      ..Default::default()
+    }
+  }
+
+  #[tokio::test]
+  async fn token_refill() {
+    let store = MemoryStore::new();
+    let limit = RateLimit::new(2, Duration::from_millis(100));
+
+    let first = store.take("key", &limit).await.unwrap();
+    assert!(first.allowed);
+    assert_eq!(first.remaining, 1);
+    assert!(store.take("key", &limit).await.unwrap().allowed);
+
+    let denied = store.take("key", &limit).await.unwrap();
+    assert!(!denied.allowed);
+    assert_eq!(denied.remaining, 0);
+    assert!(denied.retry_after.unwrap() <= limit.token_interval());
+
+    // other keys have their own bucket
+    assert!(store.take("other", &limit).await.unwrap().allowed);
+
+    tokio::time::sleep(limit.token_interval()).await;
+    let refilled = store.take("key", &limit).await.unwrap();
+    assert!(refilled.allowed);
+    assert_eq!(refilled.retry_after, None);
+  }
+
+  #[tokio::test]
+  async fn denies_all() {
+    let limit = RateLimit { requests: 0, per: Duration::from_secs(1) };
+    let store = MemoryStore::new();
+    assert!(!store.take("key", &limit).await.unwrap().allowed);
+    assert!(store.is_empty());
+  }
+
+  #[tokio::test]
+  async fn purge() {
+    let store = MemoryStore { purge_interval: Duration::from_millis(200), ..Default::default() };
+    let short = RateLimit::new(1, Duration::from_millis(10));
+    let long = RateLimit::new(1, Duration::from_secs(60));
+
+    store.take("short", &short).await.unwrap();
+    store.take("long", &long).await.unwrap();
+    assert_eq!(store.len(), 2);
+
+    // the idle bucket is full again but stays until the next purge
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    store.take("long", &long).await.unwrap();
+    assert_eq!(store.len(), 2);
+
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    store.take("long", &long).await.unwrap();
+    assert_eq!(store.len(), 1);
+
+    store.take("short", &short).await.unwrap();
+    store.purge();
+    assert_eq!(store.len(), 2);
+  }
+
+  #[test]
+  fn group_and_route_scopes() {
+    let admin = RateLimit::per_minute(5);
+    let user = RateLimit::per_minute(20);
+    let limiter = limiter(&[("/admin", admin), ("/admin/users/", user), ("/user", user)]);
+    let scope = |method: Method, path: &str, endpoint| limiter.limit_for(&method, path, endpoint);
+
+    // the endpoints of a group share a bucket
+    assert_eq!(scope(Method::GET, "/admin", None), Some((String::from("group:/admin"), admin)));
+    assert_eq!(scope(Method::POST, "/admin/settings", None), Some((String::from("group:/admin"), admin)));
+    assert_eq!(scope(Method::GET, "/admin/users/:id", None), Some((String::from("group:/admin/users/"), user)));
+    assert_eq!(scope(Method::GET, "/user/:id", None), Some((String::from("group:/user"), user)));
+
+    // prefixes only match whole segments, these get the per route default
+    let default = RateLimit::per_second(10);
+    assert_eq!(scope(Method::GET, "/users", None), Some((String::from("GET /users"), default)));
+    assert_eq!(scope(Method::GET, "/administrators", None), Some((String::from("GET /administrators"), default)));
+    assert_eq!(scope(Method::POST, "/users", None), Some((String::from("POST /users"), default)));
+
+    // an endpoint limit wins over its group
+    let own = RateLimit::per_hour(1);
+    assert_eq!(scope(Method::DELETE, "/admin/:id", Some(own)), Some((String::from("DELETE /admin/:id"), own)));
+
+    let limiter = RateLimiter::default();
+    assert_eq!(limiter.limit_for(&Method::GET, "/users", None), None);
+  }
+
+  #[tokio::test]
+  async fn headers() {
+    let limiter = Arc::new(RateLimiter {
+      default: Some(RateLimit::per_minute(2)),
+      key: RateLimitKey::Header(HeaderName::from_static("x-api-key")),
+      ..Default::default()
+    });
+
+    let call = |key: Option<&'static str>| {
+      let limiter = limiter.clone();
+      async move {
+        let mut req = Request::builder().uri("/users");
+        if let Some(key) = key {
+          req = req.header("x-api-key", key);
+        }
+
+        rate_limited(Some(&limiter), &Method::GET, "/users", req.body(axum::body::Body::empty()).unwrap(), |req| async move {
+          let cx = req.extensions().get::<RateLimitContext>().unwrap().clone();
+          let (parts, _) = req.into_parts();
+          match cx.check(None, None, &parts).await {
+            Ok(()) => Response::new(axum::body::Body::empty()),
+            Err(err) => Response::builder().status(err.status).body(axum::body::Body::empty()).unwrap(),
+          }
+        })
+        .await
+      }
+    };
+
+    let header = |res: &Response, name: &HeaderName| res.headers().get(name).map(|v| v.to_str().unwrap().to_string());
+
+    let res = call(Some("a")).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(header(&res, &RATELIMIT_LIMIT).as_deref(), Some("2"));
+    assert_eq!(header(&res, &RATELIMIT_REMAINING).as_deref(), Some("1"));
+    assert_eq!(header(&res, &RATELIMIT_RESET).as_deref(), Some("30"));
+    assert_eq!(header(&res, &axum::http::header::RETRY_AFTER), None);
+
+    call(Some("a")).await;
+    let res = call(Some("a")).await;
+    assert_eq!(res.status(), StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(header(&res, &RATELIMIT_REMAINING).as_deref(), Some("0"));
+    assert_eq!(header(&res, &axum::http::header::RETRY_AFTER).as_deref(), Some("30"));
+
+    // unidentified clients are not limited and get no headers
+    let res = call(None).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(header(&res, &RATELIMIT_LIMIT), None);
+  }
+}
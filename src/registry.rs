@@ -1,24 +1,46 @@
 use core::panic;
-use std::{convert::Infallible, sync::Arc};
-use axum::response::IntoResponse;
+use std::{any::TypeId, convert::Infallible, sync::Arc, time::Duration};
 use axum::routing::MethodRouter;
-use axum::{async_trait, extract::Request, http::Method, response::Response, routing::MethodFilter};
+use axum::body::HttpBody;
+use axum::http::header::{ALLOW, CONTENT_LENGTH};
+use axum::http::HeaderValue;
+use axum::response::IntoResponse;
+use axum::{async_trait, extract::Request, http::{Method, StatusCode}, response::Response, routing::MethodFilter};
+use garde::Validate;
 use indexmap::IndexMap;
+use tokio_util::sync::CancellationToken;
 use schemars::{generate::SchemaSettings, Schema as SchemarsSchema};
 use serde_json::json;
-use shape::{Shape, ShapeOptions, ToTypescript};
+use shape::ShapeOptions;
 
-use crate::response::into_json_response;
+use crate::error::{ApiError, ApiErrorKind};
+use crate::response::{into_json_response, json_response};
+use crate::conditional::{etag_for, is_not_modified, not_modified};
 use crate::endpoint::Endpoint;
 use crate::schema::Schema;
+use crate::trace::{traced, RequestId, TraceOptions};
+use crate::metrics::{measured, Metrics};
+use crate::otel::{otel_traced, Otel};
+use crate::rate_limit::{rate_limited, RateLimit, RateLimiter};
+use crate::cors::{cors, CorsOptions};
+use crate::compression::{compressed, CompressionOptions};
+use crate::cache::ResponseCache;
+use crate::idempotency::{idempotent, IdempotencyPolicy, IdempotencyStore};
+use crate::contract::{contract_checked, violation, ContractMode, ContractSchemas};
 
 #[async_trait]
 pub trait RegistryHandler: Send + Sync + 'static {
   async fn handle(&self, request: Request) -> Response;
+  /// Parse and validate the request like [`RegistryHandler::handle`] but respond with `output` instead of running the endpoint
+  async fn mock(&self, request: Request, output: Arc<serde_json::Value>) -> Response;
 }
 
 pub struct RegistryHandlerItem<T>(pub T);
 
+/// The registry default timeout, passed to the handlers in the request extensions
+#[derive(Debug, Clone, Copy)]
+pub struct DefaultTimeout(pub Option<Duration>);
+
 #[async_trait]
 impl<
   Ctx: Send,
@@ -36,9 +58,85 @@ impl<
 > RegistryHandler for RegistryHandlerItem<T> {
 
   async fn handle(&self, req: Request) -> Response {
-    match self.0.handle(req).await {
-      Ok(out) => into_json_response(out),
-      Err(err) => err.into_response(),
+    let request_id = req.extensions().get::<RequestId>().map(|id| id.0.clone());
+    let contract = req.extensions().get::<ContractMode>().copied();
+
+    let is_get = req.method() == Method::GET || req.method() == Method::HEAD;
+    let cache_policy = self.0.cache().filter(|_| is_get);
+    let conditional = self.0.conditional() && is_get;
+    let headers = conditional.then(|| req.headers().clone());
+
+    let timeout = self.0.timeout().or_else(|| {
+      req.extensions().get::<DefaultTimeout>().and_then(|timeout| timeout.0)
+    });
+
+    let result = match timeout {
+      None => self.0.handle(req).await,
+      Some(timeout) => match tokio::time::timeout(timeout, self.0.handle(req)).await {
+        Ok(result) => result,
+        // the handle future is dropped here, which cancels the request cancellation token
+        Err(_) => Err(ApiError {
+          status: StatusCode::GATEWAY_TIMEOUT.as_u16(),
+          kind: ApiErrorKind::Timeout,
+          message: format!("request timed out after {}ms", timeout.as_millis()),
+        }),
+      },
+    };
+
+    if let Some(mode) = contract {
+      let report = match &result {
+        Ok(out) => out.validate().err().map(|report| format!("output fails validation: {report}")),
+        Err(err) => err.validate().err().map(|report| format!("error fails validation: {report}")),
+      };
+      if let Some(report) = report {
+        violation(mode, &self.0.method(), &self.0.path(), &report);
+      }
+    }
+
+    let out = match result {
+      Ok(out) => out,
+      Err(err) => return err.into_response_with_request_id(request_id),
+    };
+
+    let headers = match headers {
+      Some(headers) => headers,
+      None => {
+        let mut res = into_json_response(out);
+        if let Some(policy) = cache_policy {
+          policy.write_headers(res.headers_mut());
+        }
+        return res;
+      }
+    };
+
+    let mut validators = self.0.validators(&out);
+    let body = match serde_json::to_vec(&out) {
+      Ok(body) => body,
+      Err(_) => return into_json_response(out),
+    };
+
+    validators.or_etag(|| etag_for(&body));
+
+    let mut res = if is_not_modified(&headers, &validators) {
+      not_modified(&validators)
+    } else {
+      json_response(body)
+    };
+
+    validators.write_headers(res.headers_mut());
+    if let Some(policy) = cache_policy {
+      policy.write_headers(res.headers_mut());
+    }
+
+    res
+  }
+
+  async fn mock(&self, req: Request, output: Arc<serde_json::Value>) -> Response {
+    let request_id = req.extensions().get::<RequestId>().map(|id| id.0.clone());
+    let (mut parts, body) = req.into_parts();
+    match self.0.parse(&mut parts, body, CancellationToken::new()).await {
+      Ok(_) => json_response(serde_json::to_vec(&*output).expect("error serializing mock output")),
+      Err(err) => err.into_response_with_request_id(request_id),
     }
   }
 }
@@ -60,6 +158,17 @@ pub struct RegistryItem {
   pub payload_shape: Option<shape::Type>,
   pub output_shape: shape::Type,
 
+  pub summary: Option<String>,
+  pub description: Option<String>,
+  pub deprecated: bool,
+
+  pub rate_limit: Option<RateLimit>,
+  pub timeout: Option<Duration>,
+  pub idempotency: Option<IdempotencyPolicy>,
+  pub max_payload_size: usize,
+
+  /// The [`TypeId`] of the endpoint, used to find it by type, eg: in [`crate::testing::TestClient`]
+  pub endpoint_type: TypeId,
   pub handler: Arc<dyn RegistryHandler>,
 }
 
@@ -103,8 +212,30 @@ fn shape_output_options() -> ShapeOptions {
 pub struct Registry {
   // { key: Path => { key: Method => Item }
   error_payload_schema: schemars::Schema,
-  error_payload_shape: shape::Type,
+  pub(crate) error_payload_shape: shape::Type,
   pub map: IndexMap<String, IndexMap<Method, RegistryItem>>,
+  pub trace: Arc<TraceOptions>,
+  /// Per endpoint request metrics, disabled if `None`
+  pub metrics: Option<Arc<Metrics>>,
+  /// OpenTelemetry server spans and trace context propagation, disabled if `None`
+  pub otel: Option<Arc<Otel>>,
+  /// Per endpoint and per group rate limiting, disabled if `None`
+  pub rate_limiter: Option<Arc<RateLimiter>>,
+  /// Max time to handle a request for endpoints that don't declare their own timeout, `None` (the default) for no timeout \
+  /// Timed out requests are answered with a 504
+  pub timeout: Option<Duration>,
+  /// CORS policy, preflight requests are answered automatically, disabled if `None`
+  pub cors: Option<Arc<CorsOptions>>,
+  /// Response compression negotiated with `Accept-Encoding`, disabled if `None` \
+  /// Compressed request bodies are always accepted
+  pub compression: Option<Arc<CompressionOptions>>,
+  /// Server side cache for the outputs of endpoints that declare a [`crate::cache::CachePolicy`], disabled if `None`
+  pub cache: Option<Arc<ResponseCache>>,
+  /// Storage of the responses of endpoints that declare an [`IdempotencyPolicy`], disabled if `None`
+  pub idempotency: Option<Arc<dyn IdempotencyStore>>,
+  /// Validate every response against the registered schemas and the output garde rules, disabled if `None` \
+  /// Disabled by default, [`crate::testing::TestClient`] uses [`ContractMode::Panic`] unless a mode is set here
+  pub contract: Option<ContractMode>,
 }
 
 
@@ -123,6 +254,16 @@ impl Registry {
       error_payload_schema,
       error_payload_shape,
       map: IndexMap::new(),
+      trace: Arc::new(TraceOptions::default()),
+      metrics: None,
+      otel: None,
+      rate_limiter: None,
+      timeout: None,
+      cors: None,
+      compression: None,
+      cache: None,
+      idempotency: None,
+      contract: None,
     }
   }
 
@@ -141,8 +282,20 @@ impl Registry {
     >
   >(&mut self, endpoint: T) {
     let path = endpoint.path();
-    let method_map = self.map.entry(path.to_string()).or_default();
     let method = endpoint.method();
+
+    // the exported schemas and generated types are named after the prefix, eg: `/users/:id` and `/users-id`
+    let prefix = crate::export::schema_name_prefix(method.as_str(), &path);
+    for (other_path, methods_map) in &self.map {
+      for other_method in methods_map.keys() {
+        let other = (other_path.as_str(), other_method);
+        if other != (&*path, &method) && crate::export::schema_name_prefix(other_method.as_str(), other_path) == prefix {
+          panic!("endpoint `{method} {path}` and `{other_method} {other_path}` have the same schema name prefix `{prefix}`");
+        }
+      }
+    }
+
+    let method_map = self.map.entry(path.to_string()).or_default();
     match method_map.entry(method.clone()) {
       indexmap::map::Entry::Occupied(_) => {
         panic!("duplicate endpoint registered for path `{path}` and method `{method}`", );
@@ -162,6 +315,10 @@ impl Registry {
           Some(Params::json_schema(&mut params_settings.into_generator()))
         };
 
+        if let Err(err) = crate::path::check_params(&path, params.as_ref()) {
+          panic!("invalid endpoint registered for path `{path}` and method `{method}`: {err}");
+        }
+
         let query = if Query::is_void() {
           None
         } else {
@@ -228,6 +385,14 @@ impl Registry {
           query_shape,
           payload_shape,
           output_shape,
+          summary: endpoint.summary().map(String::from),
+          description: endpoint.description().map(String::from),
+          deprecated: endpoint.deprecated(),
+          rate_limit: endpoint.rate_limit(),
+          timeout: endpoint.timeout(),
+          idempotency: endpoint.idempotency(),
+          max_payload_size: endpoint.max_payload_size(),
+          endpoint_type: TypeId::of::<T>(),
           handler: Arc::new(RegistryHandlerItem(endpoint)),
         };
 
@@ -236,264 +401,147 @@ impl Registry {
     }
   }
 
-  pub fn ts_definitions(&self) -> String {
-    let mut def = String::new();
-
-    #[derive(Shape)]
-    #[allow(clippy::upper_case_acronyms)]
-    #[allow(unused)]
-    enum Method {
-      HEAD,
-      GET,
-      POST,
-      PUT,
-      PATCH,
-      DELETE,
-      OPTIONS,
-      CONNECT,
-      TRACE,
-    }
-
-    let ts_method = Method::shape(&shape::ShapeOptions::for_serialize()).to_typescript();
-
-    let error_payload_src = self.error_payload_shape.to_typescript();
+  /// The schema of the error payload of every endpoint
+  pub fn error_payload_schema(&self) -> &SchemarsSchema {
+    &self.error_payload_schema
+  }
 
-    def.push_str(&format!(
-r#"/// this file is auto generated by its Rust definition, do not edit manually
+  /// The openapi spec serialized as YAML
+  pub fn openapi_yaml(&self) -> String {
+    crate::yaml::to_string(&self.openapi_spec())
+  }
 
-export type Method = {ts_method};
-type _Method = Method;
-
-export type Empty = Record<string, never>;
-
-export type Endpoint<
-  Method extends _Method,
-  Path extends string,
-  Params,
-  Query,
-  Payload,
-  Output
-> = {{
-  method: Method,
-  path: Path,
-  // this $ types are never constructed, only used as a template
-  $params?: Params,
-  $query?: Query,
-  $payload?: Payload
-  $output?: Output
-}};
-
-type ApiDefinition = Partial<Record<string, Partial<Record<Method, Endpoint<any, any, any, any, any, any>>>>>;
-
-export type ErrorPayload = {error_payload_src};
-
-export const Api = {{"#));
+  /// Every params, query, payload and output schema plus `ErrorPayload` as standalone JSON Schema documents
+  ///
+  /// Keyed by type name, eg: `GetUsersIdOutput`, each document has the `$id` `{base_id}{name}.schema.json`, \
+  /// `base_id` is usually empty or a url ending with a slash
+  pub fn json_schemas(&self, base_id: &str) -> IndexMap<String, serde_json::Value> {
+    let mut schemas = vec![(String::from("ErrorPayload"), &self.error_payload_schema)];
     for (path, methods_map) in &self.map {
-      let quoted_path = serde_json::to_string(&json!(path)).unwrap();
-      def.push_str(&format!("\n  {quoted_path}: {{"));
       for (method, item) in methods_map {
-        let quoted_method = serde_json::to_string(&json!(method.as_str())).unwrap();
-        def.push_str(
-          &format!(
-            "\n    {quoted_method}: {{ method: {method}, path: {path} }} as Endpoint<{quoted_method}, {quoted_path}, {params}, {query}, {payload}, {output}>,",
-            method=quoted_method,
-            path=quoted_path,
-            params=item.params_shape.as_ref().map(|v| v.to_typescript()).unwrap_or_else(|| String::from("Empty")),
-            query=item.query_shape.as_ref().map(|v| v.to_typescript()).unwrap_or_else(|| String::from("Empty")),
-            payload=item.payload_shape.as_ref().map(|v| v.to_typescript()).unwrap_or_else(|| String::from("Empty")),
-            output=item.output_shape.to_typescript(),
-      ))
+        let prefix = crate::export::schema_name_prefix(method.as_str(), path);
+        let item_schemas = [
+          ("Params", item.params.as_ref()),
+          ("Query", item.query.as_ref()),
+          ("Payload", item.payload.as_ref()),
+          ("Output", Some(&item.output)),
+        ];
+        for (suffix, schema) in item_schemas {
+          if let Some(schema) = schema {
+            schemas.push((format!("{prefix}{suffix}"), schema));
+          }
+        }
       }
-      def.push_str("\n  },");
     }
-    
-    def.push_str("\n} satisfies ApiDefinition;");
-
-    def
 
+    schemas
+      .into_iter()
+      .map(|(name, schema)| {
+        let mut document = serde_json::Map::new();
+        document.insert(String::from("$schema"), "https://json-schema.org/draft/2020-12/schema".into());
+        document.insert(String::from("$id"), format!("{base_id}{name}.schema.json").into());
+        document.insert(String::from("title"), name.clone().into());
+        if let serde_json::Value::Object(schema) = crate::schema::to_json_schema(schema.as_value()) {
+          for (key, value) in schema {
+            document.entry(key).or_insert(value);
+          }
+        }
+        (name, serde_json::Value::Object(document))
+      })
+      .collect()
   }
 
-  pub fn openapi_spec(&self) -> serde_json::Value {
-   
-    let schemas = json!({
-      "ErrorPayload": self.error_payload_schema,
-    });
-    
-    let mut paths = json!({});
-
-    for (path, methods_map) in &self.map {
-
-      let pattern = regex_static::static_regex!(":([a-zA-Z0-9_]+)");
-      
-      let path = pattern.replace_all(path, |caps: &regex::Captures<'_>| {
-        let name = caps.get(1).unwrap().as_str();
-        format!("{{{name}}}")
-      }).to_string();
-
-      let mut methods = json!({});
-
-      for (method, item) in methods_map {
-        let mut endpoint = json!({});
-        
-        let mut parameters = vec![];
-        
-        if let Some(schema) = &item.params {
-          let value = schema.as_value();
-          if !value["properties"].is_object() {
-            panic!("params schema properties must be an object");
-          }
+  pub fn ts_definitions(&self) -> String {
+    crate::codegen::ts::definitions(&self.ir())
+  }
 
-          for (name, param) in value["properties"].as_object().unwrap() {
-            let param = json!({
-              "in": "path",
-              "name": name,
-              "required": true,
-              "schema": param,
-            });
-            
-            parameters.push(param);
-          }
-        }
+  /// Zod validators for the input and output of every endpoint, including the garde rules
+  ///
+  /// Each schema is exported by name, eg: `GetUsersIdOutput`, and grouped by path and method in `ApiSchemas`
+  pub fn zod_definitions(&self) -> String {
+    let zod = |schema: &SchemarsSchema| crate::zod::schema(schema.as_value(), schema.as_value());
 
-        if let Some(schema) = &item.query {
-          let value = schema.as_value();
-          if !value["properties"].is_object() {
-            panic!("query schema properties must be an object");
-          }
+    let mut def = format!(
+r#"/// this file is auto generated by its Rust definition, do not edit manually
 
-          for (name, param) in value["properties"].as_object().unwrap() {
-            let param = json!({
-              "in": "query",
-              "name": name,
-              "style": "deepObject", 
-              "schema": param,
-            });
+import {{ z }} from "zod";
 
-            parameters.push(param);
-          }
-        }
+export const Empty = z.object({{}}).strict();
 
-        if !parameters.is_empty() {
-          endpoint["parameters"] = json!(parameters);  
-        }
+export const ErrorPayload = {};
+"#, zod(&self.error_payload_schema));
 
-        if let Some(payload) = &item.payload {
-          endpoint["requestBody"] = json!({
-            "content": {
-              "application/json": {
-                "schema": payload,
-              }
-            }
-          })
-        }
+    let mut api = String::from("\nexport const ApiSchemas = {");
+    for (path, methods_map) in &self.map {
+      let quoted_path = serde_json::to_string(&json!(path)).unwrap();
+      api.push_str(&format!("\n  {quoted_path}: {{"));
+      for (method, item) in methods_map {
+        let prefix = crate::export::schema_name_prefix(method.as_str(), path);
+        let quoted_method = serde_json::to_string(&json!(method.as_str())).unwrap();
 
-        endpoint["responses"] = json!({
-          "200": {
-            "description": "A successful response",
-            "content": {
-              "application/json": {
-                "schema": item.output
-              }
-            }
-          },
-
-          "4XX": {
-            "description": "A client error",
-            "content": {
-              "application/json": {
-                "schema": {
-                  "$ref": "#/components/schemas/ErrorPayload",
-                }
-              }
-            }
-          },
-
-          "5XX": {
-            "description": "A server error",
-            "content": {
-              "application/json": {
-                "schema": {
-                  "$ref": "#/components/schemas/ErrorPayload",
-                }
-              }
+        let mut names = vec![];
+        let schemas = [
+          ("params", "Params", item.params.as_ref()),
+          ("query", "Query", item.query.as_ref()),
+          ("payload", "Payload", item.payload.as_ref()),
+          ("output", "Output", Some(&item.output)),
+        ];
+        for (field, suffix, schema) in schemas {
+          match schema {
+            Some(schema) => {
+              def.push_str(&format!("\nexport const {prefix}{suffix} = {};\n", zod(schema)));
+              names.push(format!("{field}: {prefix}{suffix}"));
             }
+            None => names.push(format!("{field}: Empty")),
           }
-        });
-
-        methods[method.as_str().to_ascii_lowercase()] = endpoint;
-      };
+        }
 
-      paths[path] = methods;
+        api.push_str(&format!("\n    {quoted_method}: {{ {} }},", names.join(", ")));
+      }
+      api.push_str("\n  },");
     }
+    api.push_str("\n} as const;\n");
 
-    let openapi = json!("3.0.3");
-
-    let info = json!({
-      "title": "Some API",
-      "summary": "This is the Some API summary",
-      "description": "This is the Some API description",
-      "termsOfService": "http://example.test/terms/",
-      "contact": {
-        "name": "Some API Support",
-        "url": "http://example.test",
-        "email": "support@example.test",
-      },
-        // version of the API
-      "version": "0.1.0",
-      "license": {
-        "name": "Apache 2.0",
-        "identifier": "Apache-2.0",
-        // url is mutually exclusive with identifier
-        // "url": "https://www.apache.org/licenses/LICENSE-2.0.html",
-      },
-    });
+    def.push_str(&api);
+    def
+  }
+
+  pub fn openapi_spec(&self) -> serde_json::Value {
+    crate::codegen::openapi::spec(&self.ir())
+  }
 
-    // can interpolate variables in `servers` see https://swagger.io/specification/
 
-    let servers = json!([
-      {
-        "url": "/",
-        "description": "This server",
-      },
+  pub fn axum_router(&self) -> axum::Router {
+    self.router(false, self.contract)
+  }
 
-      // {
-      //   "url": "https://{username}.gigantic-server.com:{port}/{basePath}",
-      //   "description": "The production API server",
-      //   "variables": {
-      //     "username": {
-      //       "default": "demo",
-      //       "description": "this value is assigned by the service provider, in this example `gigantic-server.com`"
-      //     },
-      //     "port": {
-      //       "enum": [
-      //         "8443",
-      //         "443"
-      //       ],
-      //       "default": "8443"
-      //     },
-      //     "basePath": {
-      //       "default": "v2"
-      //     }
-      //   }
-      // }
-    ]);
-
-    json!({
-      "openapi": openapi,
-      "info": info,
-      "servers": servers,
-      "paths": paths,
-      "components": {
-        "schemas": schemas, 
-      }
-    })
+  /// Same as [`Registry::axum_router`] but every endpoint responds with an example of its output schema \
+  /// Inputs are still parsed and validated, the endpoints `ctx` runs but `run` is never called
+  pub fn mock_router(&self) -> axum::Router {
+    self.router(true, self.contract)
   }
 
+  /// Same as [`Registry::axum_router`] with another contract mode, eg: [`ContractMode::Panic`] in tests
+  pub fn axum_router_with_contract(&self, contract: Option<ContractMode>) -> axum::Router {
+    self.router(false, contract)
+  }
 
-  pub fn axum_router(&self) -> axum::Router {
+  fn router(&self, mock: bool, contract: Option<ContractMode>) -> axum::Router {
     let mut router = axum::Router::<()>::new();
     for (path, methods_map) in &self.map {
       let mut method_router = MethodRouter::<(), Infallible>::new();
+
+      // the methods answered for this path, used for the Allow and Access-Control-Allow-Methods headers
+      let mut allow = methods_map.keys().cloned().collect::<Vec<_>>();
+      let auto_head = methods_map.contains_key(&Method::GET) && !methods_map.contains_key(&Method::HEAD);
+      let auto_options = !methods_map.contains_key(&Method::OPTIONS);
+      if auto_head {
+        allow.push(Method::HEAD);
+      }
+      if auto_options {
+        allow.push(Method::OPTIONS);
+      }
+
       for (method, item) in methods_map {
         let method_filter = match method  {
           &Method::HEAD => MethodFilter::HEAD,
@@ -508,13 +556,152 @@ export const Api = {{"#));
           other => panic!("unsupported method {other}"),
         };
 
+        if let Some(metrics) = &self.metrics {
+          metrics.add_endpoint(path, method);
+        }
+
         let handler = item.handler.clone();
-        method_router = method_router.on(method_filter, move |req: Request| async move {
-          handler.handle(req).await
+        let trace = self.trace.clone();
+        let metrics = self.metrics.clone();
+        let otel = self.otel.clone();
+        let rate_limiter = self.rate_limiter.clone();
+        let timeout = DefaultTimeout(self.timeout);
+        let cache = self.cache.clone();
+        let cors_options = self.cors.clone();
+        let compression = self.compression.clone();
+        let idempotency_store = self.idempotency.clone();
+        let contract_schemas = ContractSchemas {
+          output: Arc::new(item.output.as_value().clone()),
+          error: Arc::new(self.error_payload_schema.as_value().clone()),
+        };
+        let idempotency = item.idempotency;
+        let max_payload_size = item.max_payload_size;
+        let example = mock.then(|| Arc::new(crate::mock::valid_example(item.output.as_value())));
+        let path = Arc::<str>::from(path.as_str());
+        let method = method.clone();
+        let endpoint_handler = move |mut req: Request| {
+          let handler = handler.clone();
+          let trace = trace.clone();
+          let metrics = metrics.clone();
+          let otel = otel.clone();
+          let rate_limiter = rate_limiter.clone();
+          let cors_options = cors_options.clone();
+          let compression = compression.clone();
+          let cache = cache.clone();
+          let idempotency_store = idempotency_store.clone();
+          let contract_schemas = contract_schemas.clone();
+          let example = example.clone();
+          let path = path.clone();
+          let method = method.clone();
+          async move {
+            req.extensions_mut().insert(timeout);
+            if let Some(cache) = cache {
+              req.extensions_mut().insert(cache);
+            }
+            if let Some(contract) = contract {
+              req.extensions_mut().insert(contract);
+            }
+            cors(cors_options.as_deref(), req, |req| async {
+              otel_traced(otel.as_deref(), &path, &method, req, |req| async {
+                traced(&trace, &path, &method, req, |req| async {
+                  measured(metrics.as_deref(), &path, &method, req, |req| async {
+                    compressed(compression.as_deref(), req, |req| async {
+                      contract_checked(contract, &contract_schemas, &method, &path, req, |req| async {
+                        rate_limited(rate_limiter.as_ref(), &method, &path, req, |req| async {
+                          idempotent(idempotency_store.as_ref(), idempotency.as_ref(), max_payload_size, req, |req| async move {
+                            match example {
+                              Some(example) => handler.mock(req, example).await,
+                              None => handler.handle(req).await,
+                            }
+                          }).await
+                        }).await
+                      }).await
+                    }).await
+                  }).await
+                }).await
+              }).await
+            }).await
+          }
+        };
+
+        if method_filter == MethodFilter::GET && auto_head {
+          let get_handler = endpoint_handler.clone();
+          method_router = method_router.on(MethodFilter::HEAD, move |req: Request| {
+            let res = get_handler(req);
+            async move { head_response(res.await) }
+          });
+        }
+
+        method_router = method_router.on(method_filter, endpoint_handler);
+      }
+
+      if auto_options {
+        let cors_options = self.cors.clone();
+        let allow = allow.clone();
+        method_router = method_router.on(MethodFilter::OPTIONS, move |req: Request| async move {
+          let mut res = match &cors_options {
+            Some(cors_options) => cors_options.preflight(&req, &allow),
+            None => {
+              let mut res = Response::new(Default::default());
+              *res.status_mut() = StatusCode::NO_CONTENT;
+              res
+            }
+          };
+          res.headers_mut().insert(ALLOW, allow_header(&allow));
+          res
         });
       }
+
+      let cors_options = self.cors.clone();
+      method_router = method_router.fallback(move |req: Request| async move {
+        cors(cors_options.as_deref(), req, |req| async move {
+          let mut res = ApiError {
+            status: StatusCode::METHOD_NOT_ALLOWED.as_u16(),
+            kind: ApiErrorKind::MethodNotAllowed,
+            message: format!("method {} not allowed, allowed methods are {}", req.method(), allow.iter().map(|m| m.as_str()).collect::<Vec<_>>().join(", ")),
+          }.into_response();
+          res.headers_mut().insert(ALLOW, allow_header(&allow));
+          res
+        }).await
+      });
+
       router = router.route(path, method_router);
     }
+
+    if let Some(metrics) = &self.metrics {
+      if let Some(metrics_path) = &metrics.options.path {
+        let metrics = metrics.clone();
+        router = router.route(metrics_path, axum::routing::get(move || async move {
+          metrics.response()
+        }));
+      }
+    }
+
+    let cors_options = self.cors.clone();
+    router = router.fallback(move |req: Request| async move {
+      cors(cors_options.as_deref(), req, |req| async move {
+        ApiError {
+          status: StatusCode::NOT_FOUND.as_u16(),
+          kind: ApiErrorKind::ResourceNotFound,
+          message: format!("no resource found at {}", req.uri().path()),
+        }.into_response()
+      }).await
+    });
+
     router
   }
-}
\ No newline at end of file
+}
+
+fn allow_header(methods: &[Method]) -> HeaderValue {
+  let list = methods.iter().map(|m| m.as_str()).collect::<Vec<_>>().join(", ");
+  HeaderValue::from_str(&list).expect("invalid allow header")
+}
+
+/// Turn a GET response into a HEAD one, keeping the headers and the Content-Length of the body
+fn head_response(res: Response) -> Response {
+  let (mut parts, body) = res.into_parts();
+  if let Some(len) = body.size_hint().exact() {
+    parts.headers.insert(CONTENT_LENGTH, HeaderValue::from(len));
+  }
+  Response::from_parts(parts, Default::default())
+}
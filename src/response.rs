@@ -15,8 +15,12 @@ pub fn into_json_response<T: Serialize>(v: T) -> Response {
     }
   };
 
+  json_response(body)
+}
+
+/// A response with an already serialized json body
+pub fn json_response(body: Vec<u8>) -> Response {
   let mut res = Response::new(body.into());
   res.headers_mut().insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
   res
-
 }
\ No newline at end of file
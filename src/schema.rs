@@ -2,9 +2,203 @@ use garde::Validate;
 use normalize::Normalize;
 use schemars::JsonSchema;
 use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
 use shape::Shape;
 
 use crate::void::Void;
 
+/// A type usable as endpoint params, query, payload or output
+///
+/// The `JsonSchema` derive reads the `#[garde(...)]` attributes, `email`, `url`, `ip`, `length`, `range`, `pattern`, \
+/// `contains`, `required` and `inner(...)` end up in the registry schemas without a matching `#[schemars(...)]` attribute. \
+/// Other rules like `ascii`, `alphanumeric`, `prefix`, `suffix` or `custom` are only enforced by `validate()`,
+/// add the equivalent `#[schemars(...)]` attribute when the spec should show them. \
+/// Note that garde's default `length` counts bytes while `maxLength` counts characters
 pub trait Schema: Serialize + DeserializeOwned + JsonSchema + Validate<Context = ()> + Shape + Normalize + Void + 'static {}
 impl<T: Serialize + DeserializeOwned + JsonSchema + Validate<Context = ()> + Shape + Normalize + Void + 'static> Schema for T {}
+
+/// Convert an OpenAPI 3.0 flavored schema, as stored in the registry, to a standard JSON Schema 2020-12 one
+///
+/// `nullable` becomes a `null` type or a `null` branch for untyped schemas, boolean `exclusiveMinimum/Maximum` become numbers,
+/// `example` becomes `examples` and `#/components/schemas/` references point to `#/$defs/`
+pub fn to_json_schema(schema: &Value) -> Value {
+  match schema {
+    Value::Array(items) => Value::Array(items.iter().map(to_json_schema).collect()),
+    Value::Object(map) => {
+      let mut out = serde_json::Map::new();
+      for (key, value) in map {
+        match key.as_str() {
+          "nullable" | "example" => {}
+          // data, not schemas, eg: an enum value or a default object with a `nullable` key
+          "enum" | "const" | "default" | "examples" => {
+            out.insert(key.clone(), value.clone());
+          }
+          "$ref" => {
+            let reference = value.as_str().unwrap_or_default().replace("#/components/schemas/", "#/$defs/");
+            out.insert(key.clone(), Value::String(reference));
+          }
+          // these hold schemas keyed by arbitrary names, the keys are not keywords
+          "properties" | "$defs" | "definitions" | "patternProperties" => {
+            let value = match value {
+              Value::Object(schemas) => Value::Object(schemas.iter().map(|(k, v)| (k.clone(), to_json_schema(v))).collect()),
+              other => other.clone(),
+            };
+            out.insert(key.clone(), value);
+          }
+          _ => {
+            out.insert(key.clone(), to_json_schema(value));
+          }
+        }
+      }
+
+      if let Some(example) = map.get("example") {
+        out.entry("examples").or_insert_with(|| Value::Array(vec![example.clone()]));
+      }
+
+      for (exclusive, bound) in [("exclusiveMinimum", "minimum"), ("exclusiveMaximum", "maximum")] {
+        if let Some(Value::Bool(flag)) = map.get(exclusive) {
+          out.remove(exclusive);
+          if *flag {
+            if let Some(bound) = out.remove(bound) {
+              out.insert(exclusive.to_string(), bound);
+            }
+          }
+        }
+      }
+
+      if map.get("nullable").and_then(|v| v.as_bool()) == Some(true) {
+        match out.get_mut("type") {
+          Some(Value::String(ty)) if ty != "null" => {
+            let ty = ty.clone();
+            out.insert(String::from("type"), serde_json::json!([ty, "null"]));
+          }
+          Some(Value::Array(types)) if !types.contains(&Value::from("null")) => types.push(Value::from("null")),
+          Some(_) => {}
+          // eg: a nullable `$ref` or `allOf`
+          None => return serde_json::json!({ "anyOf": [out, { "type": "null" }] }),
+        }
+      }
+
+      Value::Object(out)
+    }
+    other => other.clone(),
+  }
+}
+
+
+/// Follow the local `$ref`s of `schema` in `root`, unresolved references accept anything
+pub(crate) fn resolve<'a>(root: &'a Value, schema: &'a Value) -> &'a Value {
+  let mut schema = schema;
+  // chained references, bounded in case of a cycle
+  for _ in 0..16 {
+    match schema.get("$ref").and_then(|v| v.as_str()).and_then(|r| r.strip_prefix('#')) {
+      Some(pointer) => schema = root.pointer(pointer).unwrap_or(&Value::Bool(true)),
+      None => break,
+    }
+  }
+  schema
+}
+
+/// Merge the `allOf` subschemas of `schema` into a single schema, `None` if it has no `allOf`
+pub(crate) fn merge_all_of(root: &Value, schema: &Value) -> Option<Value> {
+  let all_of = schema.get("allOf")?.as_array()?;
+  let mut merged = schema.as_object().cloned().unwrap_or_default();
+  merged.remove("allOf");
+  for sub in all_of {
+    if let Some(sub) = resolve(root, sub).as_object() {
+      for (key, value) in sub {
+        match (merged.get_mut(key), value) {
+          (Some(Value::Object(target)), Value::Object(value)) => target.extend(value.clone()),
+          (Some(Value::Array(target)), Value::Array(value)) => target.extend(value.clone()),
+          (None, value) => {
+            merged.insert(key.clone(), value.clone());
+          }
+          _ => {}
+        }
+      }
+    }
+  }
+  Some(Value::Object(merged))
+}
+
+/// The `type` keyword of `schema` as a list, empty when any type is allowed
+pub(crate) fn types(schema: &Value) -> Vec<&str> {
+  match schema.get("type") {
+    Some(Value::String(ty)) => vec![ty.as_str()],
+    Some(Value::Array(types)) => types.iter().filter_map(|t| t.as_str()).collect(),
+    _ => vec![],
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use serde_json::json;
+
+  use super::*;
+
+  #[test]
+  fn nullable() {
+    assert_eq!(to_json_schema(&json!({ "type": "string", "nullable": true })), json!({ "type": ["string", "null"] }));
+    assert_eq!(to_json_schema(&json!({ "type": ["string", "null"], "nullable": true })), json!({ "type": ["string", "null"] }));
+    assert_eq!(to_json_schema(&json!({ "type": "string", "nullable": false })), json!({ "type": "string" }));
+    assert_eq!(
+      to_json_schema(&json!({ "allOf": [{ "$ref": "#/components/schemas/User" }], "nullable": true })),
+      json!({ "anyOf": [{ "allOf": [{ "$ref": "#/$defs/User" }] }, { "type": "null" }] })
+    );
+  }
+
+  #[test]
+  fn exclusive_bounds() {
+    assert_eq!(
+      to_json_schema(&json!({ "type": "integer", "minimum": 0, "exclusiveMinimum": true, "maximum": 10, "exclusiveMaximum": false })),
+      json!({ "type": "integer", "exclusiveMinimum": 0, "maximum": 10 })
+    );
+    assert_eq!(to_json_schema(&json!({ "exclusiveMaximum": 5 })), json!({ "exclusiveMaximum": 5 }));
+  }
+
+  #[test]
+  fn references() {
+    let schema = json!({
+      "type": "object",
+      "properties": {
+        "user": { "$ref": "#/components/schemas/User" },
+        "tags": { "type": "array", "items": { "$ref": "#/components/schemas/Tag" } },
+        "$ref": { "type": "string", "nullable": true },
+      },
+      "additionalProperties": { "$ref": "#/$defs/Other" },
+    });
+
+    assert_eq!(
+      to_json_schema(&schema),
+      json!({
+        "type": "object",
+        "properties": {
+          "user": { "$ref": "#/$defs/User" },
+          "tags": { "type": "array", "items": { "$ref": "#/$defs/Tag" } },
+          "$ref": { "type": ["string", "null"] },
+        },
+        "additionalProperties": { "$ref": "#/$defs/Other" },
+      })
+    );
+  }
+
+  #[test]
+  fn data_keywords() {
+    let schema = json!({
+      "type": "object",
+      "example": { "nullable": true },
+      "default": { "$ref": "#/components/schemas/User", "nullable": true },
+      "enum": [{ "example": 1 }],
+    });
+
+    assert_eq!(
+      to_json_schema(&schema),
+      json!({
+        "type": "object",
+        "default": { "$ref": "#/components/schemas/User", "nullable": true },
+        "enum": [{ "example": 1 }],
+        "examples": [{ "nullable": true }],
+      })
+    );
+  }
+}
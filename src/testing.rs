@@ -0,0 +1,276 @@
+use std::{any::TypeId, convert::Infallible, fmt::Debug, marker::PhantomData, net::SocketAddr};
+
+use axum::{
+  body::{Body, Bytes},
+  extract::{ConnectInfo, Request},
+  http::{header::CONTENT_TYPE, HeaderMap, HeaderName, HeaderValue, Method, StatusCode},
+  response::Response,
+  Router,
+};
+use indexmap::IndexMap;
+use serde::de::DeserializeOwned;
+use tower::ServiceExt;
+
+use crate::contract::ContractMode;
+use crate::endpoint::Endpoint;
+use crate::error::{ApiError, ApiErrorKind, ApiErrorPayload};
+use crate::registry::Registry;
+use crate::schema::Schema;
+use crate::void::Void;
+
+/// Calls the endpoints of a [`Registry`] in process, through the same router and layers used by the server \
+/// Responses are checked in [`ContractMode::Panic`] unless the registry sets another contract mode
+///
+/// ```ignore
+/// let client = TestClient::new(&api::registry());
+/// let user = client.call::<users::get::E>(params, (), ()).await?;
+/// ```
+#[derive(Clone)]
+pub struct TestClient {
+  router: Router,
+  // an endpoint type registered more than once can't be called by type
+  endpoints: IndexMap<TypeId, Vec<(String, Method)>>,
+  headers: HeaderMap,
+  remote_addr: SocketAddr,
+}
+
+impl TestClient {
+  pub fn new(registry: &Registry) -> Self {
+    let mut endpoints = IndexMap::new();
+    for (path, methods_map) in &registry.map {
+      for (method, item) in methods_map {
+        endpoints
+          .entry(item.endpoint_type)
+          .or_insert_with(Vec::new)
+          .push((path.clone(), method.clone()));
+      }
+    }
+
+    Self {
+      router: registry.axum_router_with_contract(registry.contract.or(Some(ContractMode::Panic))),
+      endpoints,
+      headers: HeaderMap::new(),
+      remote_addr: SocketAddr::from(([127, 0, 0, 1], 0)),
+    }
+  }
+
+  /// Send `value` in the `name` header of every request, eg: credentials needed by the endpoints `ctx`
+  pub fn header(mut self, name: HeaderName, value: HeaderValue) -> Self {
+    self.headers.insert(name, value);
+    self
+  }
+
+  /// The peer address seen by the endpoints, used as the default rate limit key
+  pub fn remote_addr(mut self, remote_addr: SocketAddr) -> Self {
+    self.remote_addr = remote_addr;
+    self
+  }
+
+  /// Start a request to the endpoint `E` \
+  /// Panics if `E` is not registered or if it is registered more than once, use [`TestClient::send`] for those
+  pub fn request<E: Endpoint>(&self, params: E::Params) -> TestRequest<'_, E> {
+    let (path, method) = match self.endpoints.get(&TypeId::of::<E>()).map(Vec::as_slice) {
+      Some([endpoint]) => endpoint.clone(),
+      Some(endpoints) => panic!(
+        "endpoint {} is registered {} times, at {}",
+        std::any::type_name::<E>(),
+        endpoints.len(),
+        endpoints.iter().map(|(path, method)| format!("{method} {path}")).collect::<Vec<_>>().join(", "),
+      ),
+      None => panic!("endpoint {} is not registered", std::any::type_name::<E>()),
+    };
+
+    TestRequest {
+      client: self,
+      path,
+      method,
+      params,
+      query: None,
+      payload: None,
+      headers: HeaderMap::new(),
+      endpoint: PhantomData,
+    }
+  }
+
+  /// Call the endpoint `E` and parse its output or error
+  pub async fn call<E: Endpoint>(
+    &self,
+    params: E::Params,
+    query: E::Query,
+    payload: E::Payload,
+  ) -> Result<E::Output, ApiError> {
+    self.request::<E>(params).query(query).payload(payload).send().await.result()
+  }
+
+  /// Send a raw request through the router
+  pub async fn send(&self, mut req: Request) -> Response {
+    for (name, value) in &self.headers {
+      if !req.headers().contains_key(name) {
+        req.headers_mut().insert(name.clone(), value.clone());
+      }
+    }
+    req.extensions_mut().insert(ConnectInfo(self.remote_addr));
+
+    let res: Result<Response, Infallible> = self.router.clone().oneshot(req).await;
+    match res {
+      Ok(res) => res,
+      Err(never) => match never {},
+    }
+  }
+}
+
+pub struct TestRequest<'a, E: Endpoint> {
+  client: &'a TestClient,
+  path: String,
+  method: Method,
+  params: E::Params,
+  query: Option<E::Query>,
+  payload: Option<E::Payload>,
+  headers: HeaderMap,
+  endpoint: PhantomData<E>,
+}
+
+impl<E: Endpoint> TestRequest<'_, E> {
+  pub fn query(mut self, query: E::Query) -> Self {
+    self.query = Some(query);
+    self
+  }
+
+  pub fn payload(mut self, payload: E::Payload) -> Self {
+    self.payload = Some(payload);
+    self
+  }
+
+  pub fn header(mut self, name: HeaderName, value: HeaderValue) -> Self {
+    self.headers.insert(name, value);
+    self
+  }
+
+  /// Override the method, eg: to send a HEAD request to a GET endpoint
+  pub fn method(mut self, method: Method) -> Self {
+    self.method = method;
+    self
+  }
+
+  pub async fn send(self) -> TestResponse<E::Output> {
+    let params = serde_json::to_value(&self.params).expect("error serializing params");
+    let mut uri = match crate::path::fill(&self.path, &params) {
+      Ok(uri) => uri,
+      Err(err) => panic!("invalid params for path `{}`: {err}", self.path),
+    };
+
+    if let Some(query) = self.query.filter(|_| !E::Query::is_void()) {
+      let query = serde_qs::to_string(&query).expect("error serializing query");
+      if !query.is_empty() {
+        uri.push('?');
+        uri.push_str(&query);
+      }
+    }
+
+    let body = match self.payload.filter(|_| !E::Payload::is_void()) {
+      Some(payload) => Body::from(serde_json::to_vec(&payload).expect("error serializing payload")),
+      None => Body::empty(),
+    };
+
+    let mut req = Request::builder()
+      .method(self.method)
+      .uri(uri)
+      .body(body)
+      .expect("error building request");
+
+    if !E::Payload::is_void() {
+      req.headers_mut().insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+    }
+
+    for (name, value) in self.headers {
+      if let Some(name) = name {
+        req.headers_mut().insert(name, value);
+      }
+    }
+
+    let res = self.client.send(req).await;
+    let (parts, body) = res.into_parts();
+    let body = axum::body::to_bytes(body, usize::MAX).await.expect("error reading response body");
+
+    TestResponse {
+      status: parts.status,
+      headers: parts.headers,
+      body,
+      output: PhantomData,
+    }
+  }
+}
+
+#[derive(Debug, Clone)]
+pub struct TestResponse<Output> {
+  pub status: StatusCode,
+  pub headers: HeaderMap,
+  pub body: Bytes,
+  output: PhantomData<Output>,
+}
+
+impl<Output: Schema> TestResponse<Output> {
+  /// Parse the body as `T`, panics if it is not valid json for `T`
+  pub fn json<T: DeserializeOwned>(&self) -> T {
+    match serde_json::from_slice(&self.body) {
+      Ok(value) => value,
+      Err(err) => panic!(
+        "error parsing response body with status {} as {}: {err}\n{}",
+        self.status,
+        std::any::type_name::<T>(),
+        String::from_utf8_lossy(&self.body),
+      ),
+    }
+  }
+
+  /// The endpoint output for success statuses or the [`ApiError`] otherwise \
+  /// Panics on responses without a body, like a 304, inspect `status` and `headers` instead
+  pub fn result(&self) -> Result<Output, ApiError> {
+    if self.status.is_client_error() || self.status.is_server_error() {
+      return Err(self.json::<ApiErrorPayload>().error);
+    }
+
+    if self.body.is_empty() {
+      if let Some(void) = Output::void() {
+        return Ok(void);
+      }
+      panic!("response with status {} has no body", self.status);
+    }
+
+    Ok(self.json::<Output>())
+  }
+
+  pub fn header(&self, name: HeaderName) -> Option<&str> {
+    self.headers.get(name).and_then(|v| v.to_str().ok())
+  }
+}
+
+/// Assert that `result` is an error of `kind` and return it
+#[track_caller]
+pub fn assert_error_kind<T: Debug>(result: &Result<T, ApiError>, kind: ApiErrorKind) -> &ApiError {
+  match result {
+    Ok(output) => panic!("expected an error of kind {kind:?}, got output {output:?}"),
+    Err(err) => {
+      assert_eq!(err.kind, kind, "unexpected error kind: {err}");
+      err
+    }
+  }
+}
+
+/// Assert that `result` is a validation error of `kind` reported for the field at `path`, eg: `id` or `items[0].name`
+#[track_caller]
+pub fn assert_validation_error<'a, T: Debug>(
+  result: &'a Result<T, ApiError>,
+  kind: ApiErrorKind,
+  path: &str,
+) -> &'a ApiError {
+  let err = assert_error_kind(result, kind);
+  // garde reports one `path: message` per line
+  let field = format!("{path}: ");
+  let found = err
+    .message
+    .lines()
+    .any(|line| line.starts_with(&field) || line.contains(&format!(": {field}")));
+  assert!(found, "expected a validation error for `{path}`, got: {}", err.message);
+  err
+}
@@ -0,0 +1,141 @@
+use std::time::Instant;
+
+use axum::{
+  extract::Request,
+  http::{HeaderName, HeaderValue, Method},
+  response::Response,
+};
+use tracing::{field::Empty, Instrument, Level};
+
+use crate::error::{ApiError, ApiErrorKind};
+
+pub const X_REQUEST_ID: HeaderName = HeaderName::from_static("x-request-id");
+
+/// The id of the current request, taken from the `X-Request-Id` header or generated if not present \
+/// It is available in the request extensions and it's included in the response headers and in error payloads
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct RequestId(pub String);
+
+#[derive(Debug, Clone)]
+pub struct TraceOptions {
+  /// Whether to trust the request id sent by the client in the request id header
+  pub trust_request_id: bool,
+  /// The header used to read and write the request id
+  pub request_id_header: HeaderName,
+  /// The level at which params, query and payload parse and validation failures are logged
+  pub client_error_level: Level,
+  /// The level at which server errors are logged
+  pub server_error_level: Level,
+  /// The level at which successful requests are logged
+  pub success_level: Level,
+}
+
+impl Default for TraceOptions {
+  fn default() -> Self {
+    Self {
+      trust_request_id: true,
+      request_id_header: X_REQUEST_ID,
+      client_error_level: Level::DEBUG,
+      server_error_level: Level::ERROR,
+      success_level: Level::DEBUG,
+    }
+  }
+}
+
+macro_rules! event_at {
+  ($level:expr, $($args:tt)*) => {
+    match $level {
+      Level::TRACE => tracing::event!(Level::TRACE, $($args)*),
+      Level::DEBUG => tracing::event!(Level::DEBUG, $($args)*),
+      Level::INFO => tracing::event!(Level::INFO, $($args)*),
+      Level::WARN => tracing::event!(Level::WARN, $($args)*),
+      Level::ERROR => tracing::event!(Level::ERROR, $($args)*),
+    }
+  };
+}
+
+/// Take the request id from the request headers or generate a new one
+pub fn request_id(options: &TraceOptions, req: &Request) -> RequestId {
+  if options.trust_request_id {
+    let header = req
+      .headers()
+      .get(&options.request_id_header)
+      .and_then(|v| v.to_str().ok())
+      .filter(|v| !v.is_empty() && v.len() <= 200);
+
+    if let Some(id) = header {
+      return RequestId(id.to_string());
+    }
+  }
+
+  RequestId(uuid::Uuid::new_v4().to_string())
+}
+
+/// Run `handler` inside a span tagged with the registered `path` template and `method`
+pub async fn traced<F: std::future::Future<Output = Response>>(
+  options: &TraceOptions,
+  path: &str,
+  method: &Method,
+  mut req: Request,
+  handler: impl FnOnce(Request) -> F,
+) -> Response {
+  let request_id = request_id(options, &req);
+  req.extensions_mut().insert(request_id.clone());
+
+  let span = tracing::info_span!(
+    "request",
+    http.method = %method,
+    http.route = %path,
+    request_id = %request_id.0,
+    http.status = Empty,
+    error.kind = Empty,
+  );
+
+  let start = Instant::now();
+  let mut res = handler(req).instrument(span.clone()).await;
+  let elapsed = start.elapsed();
+
+  let status = res.status().as_u16();
+  span.record("http.status", status);
+
+  let _enter = span.enter();
+  match res.extensions().get::<ApiError>() {
+    Some(err) => {
+      span.record("error.kind", tracing::field::debug(&err.kind));
+      let level = if is_client_input_error(&err.kind) {
+        options.client_error_level
+      } else if status >= 500 {
+        options.server_error_level
+      } else {
+        options.success_level
+      };
+
+      event_at!(level, status, kind = ?err.kind, message = %err.message, elapsed_ms = elapsed.as_secs_f64() * 1000.0, "request failed");
+    }
+
+    None => {
+      event_at!(options.success_level, status, elapsed_ms = elapsed.as_secs_f64() * 1000.0, "request completed");
+    }
+  }
+
+  if let Ok(value) = HeaderValue::from_str(&request_id.0) {
+    res.headers_mut().insert(options.request_id_header.clone(), value);
+  }
+
+  res
+}
+
+fn is_client_input_error(kind: &ApiErrorKind) -> bool {
+  matches!(
+    kind,
+    ApiErrorKind::InvalidParamsParse
+      | ApiErrorKind::InvalidParamsValidate
+      | ApiErrorKind::InvalidQueryParse
+      | ApiErrorKind::InvalidQueryValidate
+      | ApiErrorKind::PayloadRead
+      | ApiErrorKind::PayloadTooLarge
+      | ApiErrorKind::PayloadContentType
+      | ApiErrorKind::InvalidPayloadParse
+      | ApiErrorKind::InvalidPayloadValidate
+  )
+}
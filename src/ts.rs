@@ -1,53 +1,749 @@
-use std::collections::HashMap;
+use std::collections::HashSet;
 
+use indexmap::IndexMap;
 use ts_rs::{TypeVisitor, TS};
 
-pub fn inline<T: TS + 'static + ?Sized>() -> String {
-  let mut generics = Visitor { map: Default::default() };
-  let mut dependencies = Visitor { map: Default::default() };
-  T::visit_generics(&mut generics);
-  T::visit_dependencies(&mut dependencies);
+/// The TS of a ts-rs type with its dependencies inlined, recursive types are kept as references to [`Inlined::aliases`] \
+/// Use [`Inlined::declare`] to get the type together with the aliases it needs
+pub fn inline<T: TS + 'static + ?Sized>() -> Inlined {
+  let mut definitions = Definitions::default();
+  definitions.visit::<T>();
 
-  let mut target= T::inline();
+  let name = T::name();
+  let ty = TsType::parse(&name).unwrap_or(TsType::Reference(name, vec![]));
+  let mut inliner = Inliner {
+    definitions: &definitions.map,
+    stack: vec![],
+    recursive: HashSet::new(),
+    aliases: IndexMap::new(),
+  };
+  let ty = inliner.resolve(ty);
+  Inlined { ty, aliases: inliner.aliases }
+}
+
+/// A type expression and the aliases it references
+#[derive(Debug, Clone, PartialEq)]
+pub struct Inlined {
+  pub ty: TsType,
+  pub aliases: IndexMap<String, TsType>,
+}
+
+impl Inlined {
+  /// The `export type` declarations of the aliases, empty if there are none
+  pub fn declarations(&self) -> String {
+    self.aliases.iter().map(|(name, ty)| declaration(name, ty)).collect()
+  }
+
+  /// The declarations of the aliases followed by `export type {name} = ...;` for the type itself
+  pub fn declare(&self, name: &str) -> String {
+    format!("{}{}", self.declarations(), declaration(name, &self.ty))
+  }
+}
+
+fn declaration(name: &str, ty: &TsType) -> String {
+  match ty.print(0) {
+    printed if printed.starts_with('\n') => format!("export type {name} ={printed};\n"),
+    printed => format!("export type {name} = {printed};\n"),
+  }
+}
+
+/// The inline definitions of the declared types reachable from a type, keyed by their name, eg: `Page<User>`
+#[derive(Default)]
+struct Definitions {
+  map: IndexMap<String, TsType>,
+  seen: HashSet<String>,
+}
+
+impl TypeVisitor for Definitions {
+  fn visit<T: TS + 'static + ?Sized>(&mut self) {
+    let name = T::name();
+    if !self.seen.insert(name.clone()) {
+      return;
+    }
+
+    // only declared types can be inlined, eg: `inline` panics for tuples
+    if T::output_path().is_some() {
+      let key = TsType::parse(&name).and_then(|name| name.inline());
+      if let (Some(key), Some(ty)) = (key, TsType::parse(&T::inline())) {
+        self.map.insert(key, ty);
+      }
+    }
+
+    T::visit_dependencies(self);
+    T::visit_generics(self);
+  }
+}
+
+struct Inliner<'a> {
+  definitions: &'a IndexMap<String, TsType>,
+  /// The definitions being inlined, a reference to one of them is recursive
+  stack: Vec<String>,
+  recursive: HashSet<String>,
+  aliases: IndexMap<String, TsType>,
+}
+
+impl Inliner<'_> {
+  fn resolve(&mut self, ty: TsType) -> TsType {
+    match ty {
+      TsType::Reference(name, args) => {
+        let key = TsType::Reference(name.clone(), args.clone()).inline();
+        let key = match key.filter(|key| self.definitions.contains_key(key)) {
+          Some(key) => key,
+          None => return TsType::Reference(name, args.into_iter().map(|ty| self.resolve(ty)).collect()),
+        };
 
-  macro_rules! replace {
-    ($types:expr) => {{
-      for (name, inline) in $types.map.iter() {
-        // TODO: improve this
-        if(name == "Array") {
-          continue;
+        let alias = alias_name(&key);
+        if self.aliases.contains_key(&alias) || self.stack.contains(&key) {
+          self.recursive.insert(key);
+          return TsType::Reference(alias, vec![]);
         }
-        
-        // println!("replacing {} with {}", name, inline);
 
-        let re = regex::Regex::new(&format!("([<,:]?)(\\s*){name}(\\s*)([,><])", name=regex::escape(&name))).unwrap();
-        target = re.replace_all(&target, |caps: &regex::Captures<'_>| { 
-          format!(
-            "{}{}{}{}{}",
-            caps.get(1).unwrap().as_str(),
-            caps.get(2).unwrap().as_str(),
-            inline,
-            caps.get(3).unwrap().as_str(),
-            caps.get(4).unwrap().as_str(),
-          )
-        }).to_string();
+        self.stack.push(key.clone());
+        let ty = self.resolve(self.definitions[&key].clone());
+        self.stack.pop();
+
+        match self.recursive.contains(&key) {
+          true => {
+            self.aliases.insert(alias.clone(), ty);
+            TsType::Reference(alias, vec![])
+          }
+          false => ty,
+        }
       }
-    }}
+      TsType::Literal(value) => TsType::Literal(value),
+      TsType::Union(types) => TsType::Union(types.into_iter().map(|ty| self.resolve(ty)).collect()),
+      TsType::Intersection(types) => TsType::Intersection(types.into_iter().map(|ty| self.resolve(ty)).collect()),
+      TsType::Array(ty) => TsType::Array(Box::new(self.resolve(*ty))),
+      TsType::Tuple(types) => TsType::Tuple(types.into_iter().map(|ty| self.resolve(ty)).collect()),
+      TsType::Object(members) => TsType::Object(
+        members
+          .into_iter()
+          .map(|member| TsMember { value: self.resolve(member.value), ..member })
+          .collect(),
+      ),
+    }
   }
+}
 
-  replace!(generics);
-  replace!(dependencies);
+/// A type name for an alias, generic arguments are joined to the name, eg: `Tree<string>` => `TreeString`
+fn alias_name(key: &str) -> String {
+  key
+    .split(|c: char| !(c.is_alphanumeric() || c == '_' || c == '$'))
+    .filter(|word| !word.is_empty())
+    .enumerate()
+    .map(|(i, word)| match i {
+      0 => word.to_string(),
+      _ => crate::codegen::pascal(word),
+    })
+    .collect()
+}
 
-  target
+/// A typescript type expression
+#[derive(Debug, Clone, PartialEq)]
+pub enum TsType {
+  /// A type name with optional generic arguments, eg: `string`, `Array<User>`
+  Reference(String, Vec<TsType>),
+  /// A string, number or boolean literal as written in the source, eg: `"GET"`
+  Literal(String),
+  Union(Vec<TsType>),
+  Intersection(Vec<TsType>),
+  /// `T[]`
+  Array(Box<TsType>),
+  Tuple(Vec<TsType>),
+  Object(Vec<TsMember>),
 }
 
-pub struct Visitor {
-  pub map: HashMap<String, String>,
+/// A property or index signature of an object type
+#[derive(Debug, Clone, PartialEq)]
+pub struct TsMember {
+  /// The key as written in the source or an index signature like `[key: string]`, printed quoted if it needs to be
+  pub key: String,
+  pub optional: bool,
+  pub value: TsType,
+  /// The JSDoc of the member, without the comment delimiters
+  pub doc: Option<String>,
 }
 
-impl TypeVisitor for Visitor {
-  fn visit<T: TS + 'static + ?Sized>(&mut self) {
-    let name = T::name().split("<").next().unwrap().to_string();
-    self.map.insert(name, inline::<T>());
+const MAX_LINE: usize = 100;
+
+impl TsType {
+  /// Parse a type expression, `None` if the source uses syntax outside the supported subset
+  pub fn parse(source: &str) -> Option<Self> {
+    let tokens = tokenize(source)?;
+    let mut parser = Parser { tokens, pos: 0 };
+    let ty = parser.ty()?;
+    match parser.pos == parser.tokens.len() {
+      true => Some(ty),
+      false => None,
+    }
+  }
+
+  /// Print the type, nested lines are indented by `indent` levels and
+  /// objects with docs or that don't fit in a line are split one member per line
+  pub fn print(&self, indent: usize) -> String {
+    self.print_at(indent, indent * 2)
+  }
+
+  /// Print the type starting at `column` of the current line, eg: after the key of a member
+  fn print_at(&self, indent: usize, column: usize) -> String {
+    if let Some(inline) = self.inline() {
+      if column + inline.len() <= MAX_LINE {
+        return inline;
+      }
+    }
+
+    let pad = "  ".repeat(indent);
+    match self {
+      TsType::Object(members) if !members.is_empty() => {
+        let mut out = String::from("{\n");
+        for member in members {
+          if let Some(doc) = &member.doc {
+            out.push_str(&jsdoc(doc, indent + 1));
+          }
+          let key = format!("{}{}", key(&member.key), if member.optional { "?" } else { "" });
+          // the value is followed by `;`
+          let value = member.value.print_at(indent + 1, pad.len() + 2 + key.len() + 2 + 1);
+          let separator = if value.starts_with('\n') { ":" } else { ": " };
+          out.push_str(&format!("{pad}  {key}{separator}{value};\n"));
+        }
+        out.push_str(&pad);
+        out.push('}');
+        out
+      }
+      TsType::Union(types) | TsType::Intersection(types) => {
+        let separator = if matches!(self, TsType::Union(_)) { "|" } else { "&" };
+        let mut out = String::new();
+        for ty in types {
+          out.push_str(&format!("\n{pad}  {separator} {}", ty.print_operand(self, indent + 1)));
+        }
+        out
+      }
+      TsType::Array(ty) => format!("Array<{}>", ty.print_at(indent, column + "Array<".len())),
+      TsType::Tuple(types) => {
+        let types = types.iter().map(|ty| format!("{pad}  {},\n", ty.print(indent + 1))).collect::<String>();
+        format!("[\n{types}{pad}]")
+      }
+      TsType::Reference(name, args) => {
+        let args = args.iter().map(|ty| ty.print_at(indent, column + name.len() + 1)).collect::<Vec<_>>().join(", ");
+        format!("{name}<{args}>")
+      }
+      _ => self.inline().unwrap_or_default(),
+    }
+  }
+
+  /// Print a member of an union or intersection, wrapped in parens when needed
+  fn print_operand(&self, parent: &TsType, indent: usize) -> String {
+    let needs_parens = match (parent, self) {
+      (TsType::Union(_), TsType::Union(_)) => false,
+      (_, TsType::Union(_) | TsType::Intersection(_)) => true,
+      _ => false,
+    };
+    match needs_parens {
+      true => match self.print(indent) {
+        // multi line operands start with a newline
+        printed if printed.starts_with('\n') => format!("({printed}\n{})", "  ".repeat(indent)),
+        printed => format!("({printed})"),
+      },
+      false => self.print(indent),
+    }
+  }
+
+  /// The single line form, `None` if the type has docs
+  fn inline(&self) -> Option<String> {
+    let out = match self {
+      TsType::Reference(name, args) if args.is_empty() => name.clone(),
+      TsType::Reference(name, args) => {
+        let args = args.iter().map(|ty| ty.inline()).collect::<Option<Vec<_>>>()?;
+        format!("{name}<{}>", args.join(", "))
+      }
+      TsType::Literal(value) => value.clone(),
+      TsType::Union(types) | TsType::Intersection(types) => {
+        let separator = if matches!(self, TsType::Union(_)) { " | " } else { " & " };
+        let types = types
+          .iter()
+          .map(|ty| match ty {
+            TsType::Union(_) | TsType::Intersection(_) => ty.inline().map(|ty| format!("({ty})")),
+            ty => ty.inline(),
+          })
+          .collect::<Option<Vec<_>>>()?;
+        types.join(separator)
+      }
+      TsType::Array(ty) => match **ty {
+        TsType::Union(_) | TsType::Intersection(_) => format!("({})[]", ty.inline()?),
+        _ => format!("{}[]", ty.inline()?),
+      },
+      TsType::Tuple(types) => {
+        let types = types.iter().map(|ty| ty.inline()).collect::<Option<Vec<_>>>()?;
+        format!("[{}]", types.join(", "))
+      }
+      TsType::Object(members) if members.is_empty() => String::from("{}"),
+      TsType::Object(members) => {
+        let members = members
+          .iter()
+          .map(|member| match member.doc {
+            Some(_) => None,
+            None => Some(format!("{}{}: {}", key(&member.key), if member.optional { "?" } else { "" }, member.value.inline()?)),
+          })
+          .collect::<Option<Vec<_>>>()?;
+        format!("{{ {} }}", members.join("; "))
+      }
+    };
+    Some(out)
+  }
+
+  /// Attach the descriptions, defaults, examples and deprecations of `schema` to the object members as JSDoc
+  pub fn document(&mut self, root: &serde_json::Value, schema: &serde_json::Value) {
+    self.document_with(root, &expand(root, schema, 0))
+  }
+
+  fn document_with(&mut self, root: &serde_json::Value, schemas: &[&serde_json::Value]) {
+    match self {
+      TsType::Object(members) => {
+        for member in members {
+          let key = serde_json::from_str::<String>(&member.key).unwrap_or_else(|_| member.key.clone());
+          let property = schemas
+            .iter()
+            .find_map(|schema| schema.get("properties").and_then(|properties| properties.get(&key)));
+          if let Some(property) = property {
+            member.doc = doc(root, property);
+            member.value.document_with(root, &expand(root, property, 0));
+          }
+        }
+      }
+      TsType::Union(types) => {
+        // each variant of an enum is documented by its own branch, paired in order
+        let branches = schemas.iter().map(|schema| branches(root, schema)).find(|branches| branches.len() > 1);
+        let mut variants = types.iter_mut().filter(|ty| **ty != TsType::Reference(String::from("null"), vec![])).collect::<Vec<_>>();
+        match branches {
+          Some(branches) if branches.len() == variants.len() => {
+            for (ty, branch) in variants.iter_mut().zip(branches) {
+              let mut branch_schemas = expand(root, branch, 0);
+              // the properties shared by every variant, eg: an internally tagged enum with fields
+              branch_schemas.extend(schemas.iter().copied());
+              ty.document_with(root, &branch_schemas);
+            }
+          }
+          _ => {
+            for ty in variants {
+              ty.document_with(root, schemas);
+            }
+          }
+        }
+      }
+      TsType::Intersection(types) => {
+        for ty in types {
+          ty.document_with(root, schemas);
+        }
+      }
+      TsType::Array(ty) => ty.document_with(root, &children(root, schemas, "items")),
+      TsType::Reference(name, args) if name == "Array" && args.len() == 1 => {
+        args[0].document_with(root, &children(root, schemas, "items"))
+      }
+      TsType::Reference(name, args) if name == "Record" && args.len() == 2 => {
+        args[1].document_with(root, &children(root, schemas, "additionalProperties"))
+      }
+      _ => {}
+    }
+  }
+}
+
+/// A member key as written in the source, quoted if it isn't an identifier, a number or an index signature
+fn key(key: &str) -> String {
+  let identifier = key.starts_with(|c: char| c.is_alphabetic() || c == '_' || c == '$')
+    && key.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '$');
+  let number = key.parse::<f64>().is_ok_and(|_| key.chars().all(|c| c.is_ascii_digit() || c == '.'));
+  match identifier || number || key.starts_with(['[', '"', '\'']) {
+    true => key.to_string(),
+    false => serde_json::to_string(key).expect("error quoting key"),
+  }
+}
+
+const MAX_EXPAND: usize = 8;
+
+/// `schema` and the subschemas that describe the same value, where the docs of a type can be: \
+/// its `$ref`, its `allOf` members and the non-null branch of an `Option<T>` union, other union branches describe other values
+fn expand<'a>(root: &'a serde_json::Value, schema: &'a serde_json::Value, depth: usize) -> Vec<&'a serde_json::Value> {
+  if depth > MAX_EXPAND {
+    return vec![];
+  }
+
+  let schema = crate::schema::resolve(root, schema);
+  let mut out = vec![schema];
+  if let Some(subs) = schema.get("allOf").and_then(|v| v.as_array()) {
+    out.extend(subs.iter().flat_map(|sub| expand(root, sub, depth + 1)));
+  }
+  if let [sub] = branches(root, schema).as_slice() {
+    out.extend(expand(root, sub, depth + 1));
+  }
+  out
+}
+
+/// The non-null `anyOf` or `oneOf` branches of a schema
+fn branches<'a>(root: &'a serde_json::Value, schema: &'a serde_json::Value) -> Vec<&'a serde_json::Value> {
+  ["anyOf", "oneOf"]
+    .iter()
+    .filter_map(|key| schema.get(*key).and_then(|v| v.as_array()))
+    .flatten()
+    .filter(|sub| crate::schema::types(crate::schema::resolve(root, sub)) != ["null"])
+    .collect()
+}
+
+fn children<'a>(root: &'a serde_json::Value, schemas: &[&'a serde_json::Value], key: &str) -> Vec<&'a serde_json::Value> {
+  schemas
+    .iter()
+    .filter_map(|schema| schema.get(key).filter(|v| v.is_object()))
+    .flat_map(|child| expand(root, child, 0))
+    .collect()
+}
+
+/// The TS `source` of a type with the docs of `schema` as JSDoc, printed at `indent` levels \
+/// Falls back to `source` for syntax the [`TsType`] parser doesn't support
+pub(crate) fn documented(source: &str, schema: &serde_json::Value, indent: usize) -> String {
+  let mut ty = match TsType::parse(source) {
+    Some(ty) => ty,
+    None => return source.to_string(),
+  };
+  ty.document(schema, schema);
+
+  // multi line unions start on their own line, in argument position they start on the current one
+  match ty.print(indent) {
+    printed if printed.starts_with('\n') => ty.print(indent.saturating_sub(1)).trim_start().to_string(),
+    printed => printed,
+  }
+}
+
+/// The JSDoc text of a type from its root schema, eg: the doc comment of the Rust type
+pub fn type_doc(schema: &serde_json::Value) -> Option<String> {
+  doc(schema, schema)
+}
+
+/// The JSDoc text of a property schema: description, `@default`, `@example` and `@deprecated`
+fn doc(root: &serde_json::Value, schema: &serde_json::Value) -> Option<String> {
+  // docs of `$ref` or `Option<T>` properties can be on the property or on the referenced type
+  let schemas = expand(root, schema, 0);
+  let find = |key: &str| schemas.iter().find_map(|schema| schema.get(key));
+
+  let mut lines = vec![];
+  if let Some(description) = find("description").and_then(|v| v.as_str()) {
+    lines.extend(description.lines().map(String::from));
+  }
+  if let Some(default) = find("default") {
+    lines.push(format!("@default {default}"));
+  }
+  let examples = find("examples").and_then(|v| v.as_array()).cloned().unwrap_or_default();
+  for example in examples.iter().chain(find("example")).take(1) {
+    lines.push(format!("@example {example}"));
+  }
+  if find("deprecated").and_then(|v| v.as_bool()) == Some(true) {
+    lines.push(String::from("@deprecated"));
+  }
+
+  match lines.is_empty() {
+    true => None,
+    false => Some(lines.join("\n")),
+  }
+}
+
+/// A JSDoc comment for `doc` indented by `indent` levels, ending with a newline
+pub fn jsdoc(doc: &str, indent: usize) -> String {
+  let pad = "  ".repeat(indent);
+  let doc = doc.replace("*/", "*\\/");
+  let lines = doc.lines().collect::<Vec<_>>();
+  match lines.as_slice() {
+    [line] => format!("{pad}/** {line} */\n"),
+    lines => {
+      let mut out = format!("{pad}/**\n");
+      for line in lines {
+        match line.is_empty() {
+          true => out.push_str(&format!("{pad} *\n")),
+          false => out.push_str(&format!("{pad} * {line}\n")),
+        }
+      }
+      out.push_str(&format!("{pad} */\n"));
+      out
+    }
+  }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+  Punct(char),
+  /// An identifier, keyword or number
+  Word(String),
+  /// A quoted string with its quotes
+  Str(String),
+}
+
+fn tokenize(source: &str) -> Option<Vec<Token>> {
+  let mut tokens = vec![];
+  let mut chars = source.chars().peekable();
+  while let Some(&c) = chars.peek() {
+    if c.is_whitespace() {
+      chars.next();
+    } else if "{}()[]<>|&;:,?".contains(c) {
+      tokens.push(Token::Punct(c));
+      chars.next();
+    } else if c == '"' || c == '\'' || c == '`' {
+      let mut value = String::from(c);
+      chars.next();
+      loop {
+        let next = chars.next()?;
+        value.push(next);
+        if next == '\\' {
+          value.push(chars.next()?);
+        } else if next == c {
+          break;
+        }
+      }
+      tokens.push(Token::Str(value));
+    } else if c.is_alphanumeric() || "_$.-".contains(c) {
+      let mut value = String::new();
+      while let Some(&c) = chars.peek() {
+        if !(c.is_alphanumeric() || "_$.-".contains(c)) {
+          break;
+        }
+        value.push(c);
+        chars.next();
+      }
+      tokens.push(Token::Word(value));
+    } else {
+      return None;
+    }
+  }
+  Some(tokens)
+}
+
+struct Parser {
+  tokens: Vec<Token>,
+  pos: usize,
+}
+
+impl Parser {
+  fn peek(&self) -> Option<&Token> {
+    self.tokens.get(self.pos)
+  }
+
+  fn eat(&mut self, c: char) -> bool {
+    if self.peek() == Some(&Token::Punct(c)) {
+      self.pos += 1;
+      return true;
+    }
+    false
+  }
+
+  fn expect(&mut self, c: char) -> Option<()> {
+    self.eat(c).then_some(())
+  }
+
+  fn ty(&mut self) -> Option<TsType> {
+    self.eat('|');
+    let mut types = vec![self.intersection()?];
+    while self.eat('|') {
+      types.push(self.intersection()?);
+    }
+    Some(match types.len() {
+      1 => types.remove(0),
+      _ => TsType::Union(types),
+    })
+  }
+
+  fn intersection(&mut self) -> Option<TsType> {
+    self.eat('&');
+    let mut types = vec![self.postfix()?];
+    while self.eat('&') {
+      types.push(self.postfix()?);
+    }
+    Some(match types.len() {
+      1 => types.remove(0),
+      _ => TsType::Intersection(types),
+    })
+  }
+
+  fn postfix(&mut self) -> Option<TsType> {
+    let mut ty = self.primary()?;
+    while self.peek() == Some(&Token::Punct('[')) && self.tokens.get(self.pos + 1) == Some(&Token::Punct(']')) {
+      self.pos += 2;
+      ty = TsType::Array(Box::new(ty));
+    }
+    Some(ty)
+  }
+
+  fn primary(&mut self) -> Option<TsType> {
+    match self.peek()?.clone() {
+      Token::Punct('(') => {
+        self.pos += 1;
+        let ty = self.ty()?;
+        self.expect(')')?;
+        Some(ty)
+      }
+      Token::Punct('[') => {
+        self.pos += 1;
+        let mut types = vec![];
+        while !self.eat(']') {
+          types.push(self.ty()?);
+          if !self.eat(',') {
+            self.expect(']')?;
+            break;
+          }
+        }
+        Some(TsType::Tuple(types))
+      }
+      Token::Punct('{') => {
+        self.pos += 1;
+        self.object()
+      }
+      Token::Str(value) => {
+        self.pos += 1;
+        Some(TsType::Literal(value))
+      }
+      Token::Word(word) => {
+        self.pos += 1;
+        if word.starts_with(|c: char| c.is_ascii_digit() || c == '-') || word == "true" || word == "false" {
+          return Some(TsType::Literal(word));
+        }
+        let mut args = vec![];
+        if self.eat('<') {
+          loop {
+            args.push(self.ty()?);
+            if !self.eat(',') {
+              break;
+            }
+          }
+          self.expect('>')?;
+        }
+        Some(TsType::Reference(word, args))
+      }
+      Token::Punct(_) => None,
+    }
+  }
+
+  fn object(&mut self) -> Option<TsType> {
+    let mut members = vec![];
+    loop {
+      if self.eat('}') {
+        return Some(TsType::Object(members));
+      }
+
+      let key = match self.peek()?.clone() {
+        Token::Word(word) => {
+          self.pos += 1;
+          word
+        }
+        Token::Str(value) => {
+          self.pos += 1;
+          value
+        }
+        // index signature or mapped type, eg: `[key: string]`, `[key in string]`
+        Token::Punct('[') => {
+          self.pos += 1;
+          let name = match self.peek()?.clone() {
+            Token::Word(name) => name,
+            _ => return None,
+          };
+          self.pos += 1;
+          let separator = match self.peek()?.clone() {
+            Token::Punct(':') => ":",
+            Token::Word(word) if word == "in" => " in",
+            _ => return None,
+          };
+          self.pos += 1;
+          let ty = self.ty()?;
+          self.expect(']')?;
+          format!("[{name}{separator} {}]", ty.inline()?)
+        }
+        _ => return None,
+      };
+
+      let optional = self.eat('?');
+      self.expect(':')?;
+      let value = self.ty()?;
+      members.push(TsMember { key, optional, value, doc: None });
+
+      if !self.eat(';') && !self.eat(',') {
+        self.expect('}')?;
+        return Some(TsType::Object(members));
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+#[allow(dead_code)]
+mod tests {
+  use std::collections::HashMap;
+
+  use ts_rs::TS;
+
+  use super::*;
+
+  #[derive(TS)]
+  struct User {
+    id: UserId,
+    name: String,
+  }
+
+  #[derive(TS)]
+  struct UserId(String);
+
+  #[derive(TS)]
+  struct Page<T> {
+    total: u32,
+    items: Vec<T>,
+  }
+
+  #[derive(TS)]
+  struct Tree {
+    value: u32,
+    children: Vec<Tree>,
+  }
+
+  #[derive(TS)]
+  struct Forest {
+    trees: Vec<Tree>,
+  }
+
+  #[derive(TS)]
+  struct Keys {
+    #[ts(rename = "content-type")]
+    content_type: String,
+    tags: HashMap<String, u32>,
+  }
+
+  fn inlined<T: TS + 'static>() -> String {
+    inline::<T>().ty.print(0)
+  }
+
+  #[test]
+  fn prefix_names() {
+    // `User` is a prefix of `UserId`, each reference is replaced as a whole name
+    assert_eq!(inlined::<User>(), "{ id: string; name: string }");
+  }
+
+  #[test]
+  fn array() {
+    assert_eq!(inlined::<Vec<User>>(), "Array<{ id: string; name: string }>");
+  }
+
+  #[test]
+  fn nested_generics() {
+    assert_eq!(
+      inlined::<Page<Page<User>>>(),
+      "{ total: number; items: Array<{ total: number; items: Array<{ id: string; name: string }> }> }",
+    );
+  }
+
+  #[test]
+  fn recursion_through_aliases() {
+    let forest = inline::<Forest>();
+    assert_eq!(forest.ty.print(0), "{ trees: Array<Tree> }");
+    assert_eq!(
+      forest.declare("Forest"),
+      "export type Tree = { value: number; children: Array<Tree> };\nexport type Forest = { trees: Array<Tree> };\n",
+    );
+  }
+
+  #[test]
+  fn quoted_keys_and_maps() {
+    assert_eq!(inlined::<Keys>(), r#"{ "content-type": string; tags: { [key in string]?: number } }"#);
   }
 }
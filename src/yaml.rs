@@ -0,0 +1,201 @@
+use serde_json::Value;
+
+/// Serialize a json value as a block style YAML document
+///
+/// The output is deterministic, keys keep their order and strings
+/// are only quoted when a plain scalar would be read back differently
+pub fn to_string(value: &Value) -> String {
+  let mut out = String::new();
+  match value {
+    Value::Object(map) if !map.is_empty() => write_block(&mut out, value, 0),
+    Value::Array(items) if !items.is_empty() => write_block(&mut out, value, 0),
+    scalar => {
+      out.push_str(&to_scalar(scalar));
+      out.push('\n');
+    }
+  }
+  out
+}
+
+fn pad(out: &mut String, indent: usize) {
+  out.push_str(&" ".repeat(indent));
+}
+
+fn is_block(value: &Value) -> bool {
+  match value {
+    Value::Object(map) => !map.is_empty(),
+    Value::Array(items) => !items.is_empty(),
+    _ => false,
+  }
+}
+
+/// Write a non empty object or array, every line indented by `indent`
+fn write_block(out: &mut String, value: &Value, indent: usize) {
+  match value {
+    Value::Object(map) => {
+      for (key, value) in map {
+        pad(out, indent);
+        out.push_str(&to_string_scalar(key));
+        out.push(':');
+        if is_block(value) {
+          out.push('\n');
+          write_block(out, value, indent + 2);
+        } else {
+          out.push(' ');
+          out.push_str(&to_scalar(value));
+          out.push('\n');
+        }
+      }
+    }
+
+    Value::Array(items) => {
+      for item in items {
+        pad(out, indent);
+        out.push('-');
+        if is_block(item) {
+          // the first line of the nested block goes right after the dash
+          let mut nested = String::new();
+          write_block(&mut nested, item, indent + 2);
+          out.push(' ');
+          out.push_str(&nested[indent + 2..]);
+        } else {
+          out.push(' ');
+          out.push_str(&to_scalar(item));
+          out.push('\n');
+        }
+      }
+    }
+
+    _ => unreachable!("write_block called with a scalar"),
+  }
+}
+
+fn to_scalar(value: &Value) -> String {
+  match value {
+    Value::Null => String::from("null"),
+    Value::Bool(value) => value.to_string(),
+    Value::Number(value) => value.to_string(),
+    Value::String(value) => to_string_scalar(value),
+    Value::Object(_) => String::from("{}"),
+    Value::Array(_) => String::from("[]"),
+  }
+}
+
+fn to_string_scalar(value: &str) -> String {
+  if is_plain_safe(value) {
+    value.to_string()
+  } else {
+    // json escapes are a subset of the YAML double quoted escapes
+    serde_json::to_string(value).expect("error quoting yaml string")
+  }
+}
+
+/// Whether `value` can be written unquoted and still be read back as the same string
+fn is_plain_safe(value: &str) -> bool {
+  let first = match value.chars().next() {
+    Some(first) => first,
+    None => return false,
+  };
+
+  if !(first.is_ascii_alphabetic() || first == '_' || first == '/' || first == '$') {
+    return false;
+  }
+
+  if value.ends_with(' ') || value.contains(": ") || value.contains(" #") || value.ends_with(':') {
+    return false;
+  }
+
+  if !value.chars().all(|c| c.is_ascii_alphanumeric() || " _-./$(){}".contains(c)) {
+    return false;
+  }
+
+  // words YAML 1.1 parsers read as booleans or null
+  const RESERVED: &[&str] = &["true", "false", "null", "yes", "no", "on", "off", "y", "n"];
+  !RESERVED.contains(&value.to_ascii_lowercase().as_str())
+}
+
+
+#[cfg(test)]
+mod tests {
+  use serde_json::json;
+
+  use super::*;
+
+  #[test]
+  fn scalars() {
+    assert_eq!(to_string(&json!(null)), "null\n");
+    assert_eq!(to_string(&json!(1.5)), "1.5\n");
+    assert_eq!(to_string(&json!("plain text")), "plain text\n");
+    assert_eq!(to_string(&json!({})), "{}\n");
+    assert_eq!(to_string(&json!([])), "[]\n");
+  }
+
+  #[test]
+  fn quoting() {
+    let quoted = |s: &str| to_scalar(&json!(s));
+
+    for reserved in ["true", "False", "null", "yes", "No", "on", "OFF", "y", "n"] {
+      assert_eq!(quoted(reserved), format!("\"{reserved}\""));
+    }
+
+    for number in ["1", "1.0", "-1", ".5", "1e3", "0x1F", "1_000", "+1"] {
+      assert_eq!(quoted(number), format!("\"{number}\""));
+    }
+
+    assert_eq!(quoted(""), "\"\"");
+    assert_eq!(quoted("a: b"), "\"a: b\"");
+    assert_eq!(quoted("a #b"), "\"a #b\"");
+    assert_eq!(quoted("key:"), "\"key:\"");
+    assert_eq!(quoted("trailing "), "\"trailing \"");
+    assert_eq!(quoted("- item"), "\"- item\"");
+    assert_eq!(quoted("*alias"), "\"*alias\"");
+    assert_eq!(quoted("line\nbreak"), "\"line\\nbreak\"");
+    assert_eq!(quoted("é"), "\"é\"");
+
+    assert_eq!(quoted("/users/{id}"), "/users/{id}");
+    assert_eq!(quoted("$ref"), "$ref");
+    assert_eq!(quoted("application/json"), "application/json");
+    assert_eq!(quoted("yesterday"), "yesterday");
+  }
+
+  #[test]
+  fn blocks() {
+    let value = json!({
+      "paths": {
+        "/users": {
+          "parameters": [
+            { "name": "limit", "required": false, "schema": { "type": "integer" } },
+            { "name": "tags", "schema": { "type": "array", "items": { "enum": ["on", "off"] } } },
+          ],
+        },
+      },
+      "matrix": [[1, 2], [], [{ "a": 1 }]],
+      "empty": {},
+    });
+
+    assert_eq!(
+      to_string(&value),
+      r#"paths:
+  /users:
+    parameters:
+      - name: limit
+        required: false
+        schema:
+          type: integer
+      - name: tags
+        schema:
+          type: array
+          items:
+            enum:
+              - "on"
+              - "off"
+matrix:
+  - - 1
+    - 2
+  - []
+  - - a: 1
+empty: {}
+"#
+    );
+  }
+}
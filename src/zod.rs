@@ -0,0 +1,265 @@
+use serde_json::Value;
+
+use crate::schema::{merge_all_of, resolve, types};
+
+/// How many times a reference is expanded inside itself, recursive types can't be inlined forever
+const MAX_RECURSION: usize = 2;
+
+/// A Zod schema expression validating the same values as the json `schema`
+///
+/// The registry schemas carry the garde rules, eg: `pattern`, `email` and `length` become `.regex()`, `.email()` and `.max()`, \
+/// references are inlined and recursive types become `z.any()` past a few levels
+pub fn schema(root: &Value, schema: &Value) -> String {
+  schema_at(root, schema, 0, &[])
+}
+
+fn schema_at(root: &Value, schema: &Value, indent: usize, refs: &[String]) -> String {
+  let mut refs = refs.to_vec();
+  if let Some(reference) = schema.get("$ref").and_then(|v| v.as_str()) {
+    if refs.iter().filter(|r| **r == reference).count() >= MAX_RECURSION {
+      return String::from("z.any()");
+    }
+    refs.push(reference.to_string());
+  }
+  let refs = &refs[..];
+  let schema = resolve(root, schema);
+
+  let object = match schema {
+    Value::Bool(false) => return String::from("z.never()"),
+    Value::Object(object) => object,
+    _ => return String::from("z.unknown()"),
+  };
+
+  let nullable = object.get("nullable").and_then(|v| v.as_bool()) == Some(true)
+    || types(schema).contains(&"null")
+    || object.get("enum").and_then(|v| v.as_array()).is_some_and(|v| v.contains(&Value::Null));
+
+  let mut out = base(root, schema, indent, refs);
+  if nullable && out != "z.null()" && !out.ends_with(".nullable()") {
+    out.push_str(".nullable()");
+  }
+
+  if let Some(description) = object.get("description").and_then(|v| v.as_str()) {
+    out.push_str(&format!(".describe({})", quote(description)));
+  }
+
+  out
+}
+
+fn base(root: &Value, schema: &Value, indent: usize, refs: &[String]) -> String {
+  if let Some(value) = schema.get("const") {
+    return literal(value);
+  }
+
+  if let Some(variants) = schema.get("enum").and_then(|v| v.as_array()) {
+    let variants = variants.iter().filter(|v| !v.is_null()).collect::<Vec<_>>();
+    if !variants.is_empty() && variants.iter().all(|v| v.is_string()) {
+      let variants = variants.iter().map(|v| v.to_string()).collect::<Vec<_>>().join(", ");
+      return format!("z.enum([{variants}])");
+    }
+    return union(variants.iter().map(|v| literal(v)).collect());
+  }
+
+  for key in ["oneOf", "anyOf"] {
+    if let Some(subs) = schema.get(key).and_then(|v| v.as_array()) {
+      // `Option<T>` is an union with null, expressed as `.nullable()`
+      let null = |sub: &&Value| types(resolve(root, sub)) == ["null"];
+      let nullable = subs.iter().any(|sub| null(&sub));
+      let mut out = union(subs.iter().filter(|sub| !null(sub)).map(|sub| schema_at(root, sub, indent, refs)).collect());
+
+      // internally tagged enums have the shared properties next to the variants
+      if schema.get("properties").is_some() {
+        let mut shared = schema.clone();
+        if let Some(shared) = shared.as_object_mut() {
+          shared.remove(key);
+        }
+        out = format!("{}.and({out})", object(root, &shared, indent, refs));
+      }
+
+      if nullable {
+        out.push_str(".nullable()");
+      }
+      return out;
+    }
+  }
+
+  if let Some(merged) = merge_all_of(root, schema) {
+    return base(root, &merged, indent, refs);
+  }
+
+  let types = types(schema);
+  let ty = match types.iter().find(|ty| **ty != "null") {
+    Some(ty) => *ty,
+    None if types.contains(&"null") => "null",
+    None if schema.get("properties").is_some() => "object",
+    None if schema.get("items").is_some() || schema.get("prefixItems").is_some() => "array",
+    None => return String::from("z.unknown()"),
+  };
+
+  let number = |key: &str| schema.get(key).filter(|v| v.is_number());
+  let flag = |key: &str| schema.get(key).and_then(|v| v.as_bool()) == Some(true);
+
+  match ty {
+    "null" => String::from("z.null()"),
+    "boolean" => String::from("z.boolean()"),
+    "integer" | "number" => {
+      let mut out = String::from("z.number()");
+      if ty == "integer" {
+        out.push_str(".int()");
+      }
+      // openapi 3.0 uses boolean exclusive flags, json schema uses the bound itself
+      match (number("minimum"), number("exclusiveMinimum")) {
+        (Some(min), _) if flag("exclusiveMinimum") => out.push_str(&format!(".gt({min})")),
+        (Some(min), _) => out.push_str(&format!(".gte({min})")),
+        (None, Some(min)) => out.push_str(&format!(".gt({min})")),
+        (None, None) => {}
+      }
+      match (number("maximum"), number("exclusiveMaximum")) {
+        (Some(max), _) if flag("exclusiveMaximum") => out.push_str(&format!(".lt({max})")),
+        (Some(max), _) => out.push_str(&format!(".lte({max})")),
+        (None, Some(max)) => out.push_str(&format!(".lt({max})")),
+        (None, None) => {}
+      }
+      if let Some(multiple) = number("multipleOf") {
+        out.push_str(&format!(".multipleOf({multiple})"));
+      }
+      out
+    }
+    "string" => {
+      let mut out = String::from("z.string()");
+      match schema.get("format").and_then(|v| v.as_str()) {
+        Some("email") => out.push_str(".email()"),
+        Some("uuid") => out.push_str(".uuid()"),
+        Some("uri") | Some("url") => out.push_str(".url()"),
+        Some("date-time") => out.push_str(".datetime({ offset: true })"),
+        Some("date") => out.push_str(".date()"),
+        Some("time") => out.push_str(".time()"),
+        Some("ipv4") => out.push_str(r#".ip({ version: "v4" })"#),
+        Some("ipv6") => out.push_str(r#".ip({ version: "v6" })"#),
+        _ => {}
+      }
+      if let Some(min) = number("minLength") {
+        out.push_str(&format!(".min({min})"));
+      }
+      if let Some(max) = number("maxLength") {
+        out.push_str(&format!(".max({max})"));
+      }
+      if let Some(pattern) = schema.get("pattern").and_then(|v| v.as_str()) {
+        out.push_str(&format!(".regex(new RegExp({}))", quote(pattern)));
+      }
+      out
+    }
+    "array" => {
+      let tuple = match (schema.get("prefixItems"), schema.get("items")) {
+        (Some(Value::Array(items)), _) | (None, Some(Value::Array(items))) => Some(items),
+        _ => None,
+      };
+      if let Some(items) = tuple {
+        let items = items.iter().map(|item| schema_at(root, item, indent, refs)).collect::<Vec<_>>();
+        return format!("z.tuple([{}])", items.join(", "));
+      }
+
+      let items = match schema.get("items") {
+        Some(items) => schema_at(root, items, indent, refs),
+        None => String::from("z.unknown()"),
+      };
+      let mut out = format!("z.array({items})");
+      if let Some(min) = number("minItems") {
+        out.push_str(&format!(".min({min})"));
+      }
+      if let Some(max) = number("maxItems") {
+        out.push_str(&format!(".max({max})"));
+      }
+      out
+    }
+    _ => object(root, schema, indent, refs),
+  }
+}
+
+fn object(root: &Value, schema: &Value, indent: usize, refs: &[String]) -> String {
+  let additional = schema.get("additionalProperties");
+  let properties = match schema.get("properties").and_then(|v| v.as_object()) {
+    Some(properties) => properties,
+    None => {
+      return match additional {
+        Some(Value::Bool(false)) => String::from("z.object({}).strict()"),
+        Some(additional @ Value::Object(_)) => {
+          format!("z.record(z.string(), {})", schema_at(root, additional, indent, refs))
+        }
+        _ => String::from("z.record(z.string(), z.unknown())"),
+      };
+    }
+  };
+
+  let required = schema
+    .get("required")
+    .and_then(|v| v.as_array())
+    .map(|required| required.iter().filter_map(|v| v.as_str()).collect::<Vec<_>>())
+    .unwrap_or_default();
+
+  let pad = "  ".repeat(indent + 1);
+  let mut out = String::from("z.object({\n");
+  for (name, property) in properties {
+    let mut value = schema_at(root, property, indent + 1, refs);
+    if !required.contains(&name.as_str()) {
+      value.push_str(".optional()");
+    }
+    out.push_str(&format!("{pad}{}: {value},\n", key(name)));
+  }
+  out.push_str(&"  ".repeat(indent));
+  out.push_str("})");
+
+  match additional {
+    Some(Value::Bool(false)) => out.push_str(".strict()"),
+    Some(additional @ Value::Object(_)) => {
+      out.push_str(&format!(".catchall({})", schema_at(root, additional, indent, refs)));
+    }
+    _ => {}
+  }
+
+  out
+}
+
+/// A schema accepting exactly `value`, `z.literal` only takes primitives so objects and arrays are matched by structure
+fn literal(value: &Value) -> String {
+  match value {
+    Value::Null => String::from("z.null()"),
+    Value::Array(items) => {
+      let items = items.iter().map(literal).collect::<Vec<_>>().join(", ");
+      format!("z.tuple([{items}])")
+    }
+    Value::Object(properties) if properties.is_empty() => String::from("z.object({}).strict()"),
+    Value::Object(properties) => {
+      let properties = properties
+        .iter()
+        .map(|(name, value)| format!("{}: {}", key(name), literal(value)))
+        .collect::<Vec<_>>()
+        .join(", ");
+      format!("z.object({{ {properties} }}).strict()")
+    }
+    primitive => format!("z.literal({primitive})"),
+  }
+}
+
+fn union(mut variants: Vec<String>) -> String {
+  match variants.len() {
+    0 => String::from("z.never()"),
+    1 => variants.remove(0),
+    _ => format!("z.union([{}])", variants.join(", ")),
+  }
+}
+
+fn quote(value: &str) -> String {
+  serde_json::to_string(value).expect("error quoting string")
+}
+
+/// An object key, quoted unless it is a valid identifier
+fn key(name: &str) -> String {
+  let mut chars = name.chars();
+  let identifier = chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_' || c == '$')
+    && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$');
+  match identifier {
+    true => name.to_string(),
+    false => quote(name),
+  }
+}
@@ -0,0 +1,173 @@
+use std::sync::Arc;
+
+use auto_api::{
+  api::{self, users},
+  contract::validate,
+  error::ApiErrorKind,
+  fuzz::Fuzzer,
+  otel::Otel,
+  testing::{assert_error_kind, assert_validation_error, TestClient},
+};
+use axum::{
+  body::Body,
+  extract::Request,
+  http::{header::{ETAG, IF_NONE_MATCH}, HeaderValue, Method, StatusCode},
+};
+use opentelemetry::{trace::SpanKind, KeyValue, Value as OtelValue};
+use opentelemetry_sdk::testing::trace::InMemorySpanExporter;
+use serde_json::{json, Value};
+use tower::ServiceExt;
+
+fn client() -> TestClient {
+  TestClient::new(&api::registry())
+}
+
+fn get(uri: &str) -> Request {
+  Request::builder().uri(uri).body(Body::empty()).unwrap()
+}
+
+fn user_params(id: &str) -> users::get::Params {
+  serde_json::from_value(json!({ "id": id })).unwrap()
+}
+
+#[tokio::test]
+async fn get_user() {
+  let client = client();
+  let res = client.request::<users::get::E>(user_params("123")).send().await;
+  assert_eq!(res.status, StatusCode::OK);
+  assert!(res.result().is_ok());
+
+  let etag = res.header(ETAG).expect("conditional endpoint without etag").to_string();
+  let res = client
+    .request::<users::get::E>(user_params("123"))
+    .header(IF_NONE_MATCH, HeaderValue::from_str(&etag).unwrap())
+    .send()
+    .await;
+  assert_eq!(res.status, StatusCode::NOT_MODIFIED);
+  assert!(res.body.is_empty());
+}
+
+#[tokio::test]
+async fn get_user_invalid_id() {
+  let result = client().call::<users::get::E>(user_params("NOT-AN-ID"), (), ()).await;
+  assert_validation_error(&result, ApiErrorKind::InvalidParamsValidate, "id");
+}
+
+#[tokio::test]
+async fn list_users() {
+  let client = client();
+  let res = client.send(get("/users?skip=10&limit=20")).await;
+  assert_eq!(res.status(), StatusCode::OK);
+  let body = axum::body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+  let page: Value = serde_json::from_slice(&body).unwrap();
+  assert_eq!(page, json!({ "skip": 10, "limit": 20, "total": 0, "items": [] }));
+
+  let res = client.send(get("/users?limit=0")).await;
+  assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn not_found() {
+  let res = client().send(get("/missing")).await;
+  assert_eq!(res.status(), StatusCode::NOT_FOUND);
+  let body = axum::body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+  let payload: auto_api::error::ApiErrorPayload = serde_json::from_slice(&body).unwrap();
+  assert_error_kind::<()>(&Err(payload.error), ApiErrorKind::ResourceNotFound);
+}
+
+
+#[tokio::test]
+async fn otel_span() {
+  let exporter = InMemorySpanExporter::default();
+  let mut registry = api::registry();
+  registry.otel = Some(Arc::new(Otel::with_simple_exporter("test", exporter.clone())));
+
+  let req = Request::builder()
+    .uri("/users/123")
+    .header("traceparent", "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01")
+    .body(Body::empty())
+    .unwrap();
+  let res = TestClient::new(&registry).send(req).await;
+  assert_eq!(res.status(), StatusCode::OK);
+
+  let spans = exporter.get_finished_spans().unwrap();
+  assert_eq!(spans.len(), 1);
+  let span = &spans[0];
+  assert_eq!(span.name, "GET /users/:id");
+  assert_eq!(span.span_kind, SpanKind::Server);
+  assert_eq!(span.span_context.trace_id().to_string(), "4bf92f3577b34da6a3ce929d0e0e4736");
+  assert_eq!(span.parent_span_id.to_string(), "00f067aa0ba902b7");
+
+  let attribute = |key: &str| {
+    span.attributes.iter().find(|KeyValue { key: k, .. }| k.as_str() == key).map(|kv| kv.value.clone())
+  };
+  assert_eq!(attribute("http.request.method"), Some(OtelValue::from("GET")));
+  assert_eq!(attribute("http.route"), Some(OtelValue::from("/users/:id")));
+  assert_eq!(attribute("url.path"), Some(OtelValue::from("/users/123")));
+  assert_eq!(attribute("http.response.status_code"), Some(OtelValue::I64(200)));
+  assert_eq!(attribute("error.type"), None);
+}
+
+#[tokio::test]
+async fn fuzz() {
+  Fuzzer::new(&api::registry()).run().await.assert_ok();
+}
+
+#[tokio::test]
+async fn mock_router() {
+  let registry = api::registry();
+  let router = registry.mock_router();
+
+  for (uri, path) in [("/users/123", "/users/:id"), ("/users?limit=5", "/users")] {
+    let res = router.clone().oneshot(get(uri)).await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK, "{uri}");
+
+    let body = axum::body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+    let body: Value = serde_json::from_slice(&body).unwrap();
+    let output = registry.map[path][&Method::GET].output.as_value();
+    let errors = validate(output, &body);
+    assert!(errors.is_empty(), "{uri} mock body {body} doesn't match the output schema: {errors:?}");
+  }
+
+  // inputs are still validated
+  let res = router.oneshot(get("/users?limit=0")).await.unwrap();
+  assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+}
+
+#[test]
+fn json_schemas() {
+  fn walk<'a>(value: &'a Value, refs: &mut Vec<&'a str>) {
+    match value {
+      Value::Object(map) => {
+        assert!(!map.contains_key("nullable"), "OpenAPI keyword left in {value}");
+        if let Some(Value::String(reference)) = map.get("$ref") {
+          refs.push(reference);
+        }
+        map.values().for_each(|value| walk(value, refs));
+      }
+      Value::Array(items) => items.iter().for_each(|value| walk(value, refs)),
+      _ => {}
+    }
+  }
+
+  let schemas = api::registry().json_schemas("https://example.test/schemas/");
+  assert_eq!(schemas.keys().next().map(String::as_str), Some("ErrorPayload"));
+  assert!(schemas.contains_key("GetUsersIdParams"));
+  assert!(schemas.contains_key("GetUsersIdOutput"));
+  assert!(schemas.contains_key("GetUsersQuery"));
+  assert!(schemas.contains_key("GetUsersOutput"));
+
+  for (name, schema) in &schemas {
+    assert_eq!(schema["$schema"], "https://json-schema.org/draft/2020-12/schema");
+    assert_eq!(schema["$id"], format!("https://example.test/schemas/{name}.schema.json"));
+    assert_eq!(schema["title"], name.as_str());
+
+    let mut refs = vec![];
+    walk(schema, &mut refs);
+    for reference in refs {
+      let pointer = reference.strip_prefix('#').unwrap_or_else(|| panic!("{name} has a non local reference {reference}"));
+      assert!(pointer.starts_with("/$defs/"), "{name} reference {reference}");
+      assert!(schema.pointer(pointer).is_some(), "{name} has a dangling reference {reference}");
+    }
+  }
+}
@@ -0,0 +1,88 @@
+mod common;
+
+use std::time::Duration;
+
+use auto_api::{
+  api,
+  codegen::{kotlin::Kotlin, openapi::OpenApi, python::Python, swift::Swift, ts::Ts, Generator},
+};
+use common::assert_snapshot;
+use serde_json::Value;
+
+/// The single file written by a generator for the sample api
+fn generate(generator: &dyn Generator) -> String {
+  let mut files = generator.generate(&api::registry().ir());
+  assert_eq!(files.len(), 1);
+  files.remove(0).contents
+}
+
+#[test]
+fn python() {
+  let python = generate(&Python);
+
+  for expected in [
+    "    kind: Literal[\"RESOURCE_NOT_FOUND\"]",
+    "    request_id: Optional[str] = None",
+    "ErrorPayloadError = Annotated[Union[",
+    "def get_users_id(",
+  ] {
+    assert!(python.contains(expected), "missing `{expected}` in\n{python}");
+  }
+
+  assert_snapshot("api.py", &python);
+}
+
+#[test]
+fn kotlin() {
+  let kotlin = generate(&Kotlin::default());
+
+  for expected in ["package api", "@JsonClassDiscriminator(\"kind\")", "val requestId: String? = null,"] {
+    assert!(kotlin.contains(expected), "missing `{expected}` in\n{kotlin}");
+  }
+
+  assert_snapshot("Api.kt", &kotlin);
+}
+
+#[test]
+fn swift() {
+  let swift = generate(&Swift);
+
+  for expected in ["import Foundation", "public var requestId: String?", "case requestId = \"request_id\""] {
+    assert!(swift.contains(expected), "missing `{expected}` in\n{swift}");
+  }
+
+  assert_snapshot("Api.swift", &swift);
+}
+
+#[test]
+fn openapi() {
+  let mut registry = api::registry();
+  registry.timeout = Some(Duration::from_secs(5));
+  assert!(registry.ir().operations.iter().all(|operation| operation.timeout == Some(Duration::from_secs(5))));
+
+  let files = OpenApi { yaml: false }.generate(&registry.ir());
+  assert_eq!(files[0].path.to_str(), Some("openapi.json"));
+  let spec = serde_json::from_str::<Value>(&files[0].contents).unwrap();
+  assert_eq!(spec, registry.openapi_spec());
+  assert_eq!(spec["paths"]["/users/{id}"]["get"]["responses"]["504"]["description"], "The request took longer than 5 seconds");
+
+  let files = OpenApi { yaml: true }.generate(&registry.ir());
+  assert_eq!(files[0].path.to_str(), Some("openapi.yaml"));
+  assert_eq!(files[0].contents, registry.openapi_yaml());
+}
+
+#[test]
+fn ts() {
+  let registry = api::registry();
+
+  let files = Ts { client: false }.generate(&registry.ir());
+  assert_eq!(files[0].path.to_str(), Some("api.ts"));
+  for expected in ["    /** @returns A user record */", "    \"GET\": { method: \"GET\", path: \"/users/:id\" } as Endpoint<"] {
+    assert!(files[0].contents.contains(expected), "missing `{expected}` in\n{}", files[0].contents);
+  }
+
+  let files = Ts { client: true }.generate(&registry.ir());
+  assert_eq!(files[0].path.to_str(), Some("client.ts"));
+  assert!(files[0].contents.starts_with(&auto_api::export::text(&registry.ts_definitions())));
+  assert!(files[0].contents.contains("export type ApiResult<Output> ="));
+}
@@ -0,0 +1,17 @@
+use std::path::Path;
+
+/// Compare `actual` with the snapshot file, `UPDATE_SNAPSHOTS=1` rewrites it instead
+pub fn assert_snapshot(name: &str, actual: &str) {
+  let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/snapshots").join(name);
+  if std::env::var_os("UPDATE_SNAPSHOTS").is_some() {
+    std::fs::write(&path, actual).unwrap();
+    return;
+  }
+
+  let expected = std::fs::read_to_string(&path).unwrap_or_default();
+  assert!(
+    expected == actual,
+    "snapshot {} is out of date, rerun with UPDATE_SNAPSHOTS=1 and review the diff\n\n{actual}",
+    path.display(),
+  );
+}
@@ -0,0 +1,205 @@
+use std::{borrow::Cow, marker::PhantomData};
+
+use auto_api::{
+  diff::{diff, Severity},
+  endpoint::{Endpoint, EndpointError, ParsedRequest},
+  error::ApiErrorPayload,
+  registry::Registry,
+  schema::Schema,
+};
+use axum::{async_trait, http::{request::Parts, Method}};
+use garde::Validate;
+use normalize::Normalize;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use shape::Shape;
+
+#[derive(Serialize, Deserialize, JsonSchema, Validate, Shape, Normalize)]
+struct OptionalQuery {
+  #[normalize(skip)]
+  #[garde(skip)]
+  q: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Validate, Shape, Normalize)]
+struct RequiredQuery {
+  #[normalize(skip)]
+  #[garde(skip)]
+  q: String,
+}
+
+struct Search<Query>(PhantomData<Query>);
+
+#[async_trait]
+impl<Query: Schema + Send + Sync> Endpoint for Search<Query> {
+  type Ctx = ();
+  type Params = ();
+  type Query = Query;
+  type Payload = ();
+  type Output = ();
+
+  fn path(&self) -> Cow<'static, str> {
+    "/search".into()
+  }
+
+  fn method(&self) -> Method {
+    Method::GET
+  }
+
+  async fn ctx(&self, _parts: &mut Parts) -> Result<Self::Ctx, Box<dyn EndpointError>> {
+    Ok(())
+  }
+
+  async fn run(
+    &self,
+    _request: ParsedRequest<Self::Ctx, Self::Params, Self::Query, Self::Payload>,
+  ) -> Result<Self::Output, Box<dyn EndpointError>> {
+    Ok(())
+  }
+}
+
+fn spec<Query: Schema + Send + Sync>() -> serde_json::Value {
+  let mut registry = Registry::new::<ApiErrorPayload>();
+  registry.register(Search::<Query>(PhantomData));
+  registry.openapi_spec()
+}
+
+#[test]
+fn query_param_required() {
+  let optional = spec::<OptionalQuery>();
+  let required = spec::<RequiredQuery>();
+
+  let param = |spec: &serde_json::Value| spec["paths"]["/search"]["get"]["parameters"][0]["required"].clone();
+  assert_eq!(param(&optional), false);
+  assert_eq!(param(&required), true);
+
+  let changes = diff(&optional, &required);
+  assert!(
+    changes.iter().any(|change| change.severity == Severity::Breaking && change.message == "parameter became required"),
+    "{changes:?}",
+  );
+
+  let changes = diff(&required, &optional);
+  assert!(
+    changes.iter().any(|change| change.severity == Severity::NonBreaking && change.message == "parameter became optional"),
+    "{changes:?}",
+  );
+}
+
+/// A spec with a single `POST /users` endpoint
+fn users_spec(payload: serde_json::Value, output: serde_json::Value) -> serde_json::Value {
+  json!({
+    "paths": {
+      "/users": {
+        "post": {
+          "requestBody": { "content": { "application/json": { "schema": payload } } },
+          "responses": { "200": { "content": { "application/json": { "schema": output } } } },
+        },
+      },
+    },
+  })
+}
+
+fn object(properties: serde_json::Value, required: &[&str]) -> serde_json::Value {
+  json!({ "type": "object", "properties": properties, "required": required })
+}
+
+fn changes(old: &serde_json::Value, new: &serde_json::Value) -> Vec<(Severity, String, String)> {
+  diff(old, new).into_iter().map(|change| (change.severity, change.location, change.message)).collect()
+}
+
+fn change(severity: Severity, location: &str, message: &str) -> (Severity, String, String) {
+  (severity, location.to_string(), message.to_string())
+}
+
+#[test]
+fn removed_endpoint() {
+  let spec = users_spec(object(json!({}), &[]), object(json!({}), &[]));
+  let empty = json!({ "paths": {} });
+
+  assert_eq!(changes(&spec, &empty), [change(Severity::Breaking, "POST /users", "endpoint removed")]);
+  assert_eq!(changes(&empty, &spec), [change(Severity::NonBreaking, "POST /users", "endpoint added")]);
+}
+
+#[test]
+fn new_required_field() {
+  let name = json!({ "name": { "type": "string" } });
+  let name_and_email = json!({ "name": { "type": "string" }, "email": { "type": "string" } });
+
+  // clients don't send the new field yet
+  let old = users_spec(object(name.clone(), &["name"]), object(json!({}), &[]));
+  let new = users_spec(object(name_and_email.clone(), &["name", "email"]), object(json!({}), &[]));
+  assert_eq!(changes(&old, &new), [change(Severity::Breaking, "POST /users payload.email", "required field added")]);
+
+  // clients ignore a new field in the response
+  let old = users_spec(object(json!({}), &[]), object(name, &["name"]));
+  let new = users_spec(object(json!({}), &[]), object(name_and_email, &["name", "email"]));
+  assert_eq!(changes(&old, &new), [change(Severity::NonBreaking, "POST /users response.email", "field added")]);
+}
+
+#[test]
+fn narrowed_enum() {
+  let role = |values: serde_json::Value| object(json!({ "role": { "type": "string", "enum": values } }), &["role"]);
+  let wide = role(json!(["admin", "user"]));
+  let narrow = role(json!(["admin"]));
+
+  // clients may still send the removed value
+  let old = users_spec(wide.clone(), object(json!({}), &[]));
+  let new = users_spec(narrow.clone(), object(json!({}), &[]));
+  assert_eq!(changes(&old, &new), [change(Severity::Breaking, "POST /users payload.role", "enum values removed: \"user\"")]);
+  assert_eq!(changes(&new, &old), [change(Severity::NonBreaking, "POST /users payload.role", "enum values added: \"user\"")]);
+
+  // clients never receive the removed value but can't handle a new one
+  let old = users_spec(object(json!({}), &[]), wide);
+  let new = users_spec(object(json!({}), &[]), narrow);
+  assert_eq!(changes(&old, &new), [change(Severity::NonBreaking, "POST /users response.role", "enum values removed: \"user\"")]);
+  assert_eq!(changes(&new, &old), [change(Severity::Breaking, "POST /users response.role", "enum values added: \"user\"")]);
+}
+
+#[test]
+fn removed_field() {
+  let with_email = object(json!({ "name": { "type": "string" }, "email": { "type": "string" } }), &["name"]);
+  let without_email = object(json!({ "name": { "type": "string" } }), &["name"]);
+
+  // clients may rely on the field
+  let old = users_spec(object(json!({}), &[]), with_email.clone());
+  let new = users_spec(object(json!({}), &[]), without_email.clone());
+  assert_eq!(changes(&old, &new), [change(Severity::Breaking, "POST /users response.email", "field removed")]);
+
+  // the server ignores the field clients still send, unless the object is closed
+  let old = users_spec(with_email.clone(), object(json!({}), &[]));
+  let new = users_spec(without_email.clone(), object(json!({}), &[]));
+  assert_eq!(changes(&old, &new), [change(Severity::NonBreaking, "POST /users payload.email", "field removed")]);
+
+  let mut closed = without_email;
+  closed["additionalProperties"] = json!(false);
+  let new = users_spec(closed, object(json!({}), &[]));
+  assert!(changes(&old, &new).contains(&change(Severity::Breaking, "POST /users payload.email", "field removed")));
+}
+
+#[test]
+fn recursive_schema() {
+  let tree = |value: &str| {
+    let mut spec = users_spec(json!({ "$ref": "#/components/schemas/Tree" }), json!({ "$ref": "#/components/schemas/Tree" }));
+    spec["components"] = json!({
+      "schemas": {
+        "Tree": object(json!({
+          "value": { "type": value },
+          "children": { "type": "array", "items": { "$ref": "#/components/schemas/Tree" } },
+          "parent": { "$ref": "#/components/schemas/Tree" },
+        }), &["value"]),
+      },
+    });
+    spec
+  };
+
+  assert!(changes(&tree("string"), &tree("string")).is_empty());
+  assert_eq!(
+    changes(&tree("string"), &tree("integer")),
+    [
+      change(Severity::Breaking, "POST /users payload.value", "type changed from string to integer"),
+      change(Severity::Breaking, "POST /users response.value", "type changed from string to integer"),
+    ]
+  );
+}
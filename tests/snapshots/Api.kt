@@ -0,0 +1,204 @@
+// this file is auto generated by its Rust definition, do not edit manually
+
+@file:OptIn(ExperimentalSerializationApi::class)
+
+package api
+
+import kotlinx.serialization.ExperimentalSerializationApi
+import kotlinx.serialization.SerialName
+import kotlinx.serialization.Serializable
+import kotlinx.serialization.json.JsonClassDiscriminator
+import kotlinx.serialization.json.JsonElement
+
+@Serializable
+@JsonClassDiscriminator("kind")
+sealed interface ErrorPayloadError {
+    val status: Long
+    val message: String
+}
+
+@Serializable
+@SerialName("INTERNAL")
+data class ErrorPayloadErrorInternal(
+    override val status: Long,
+    override val message: String,
+) : ErrorPayloadError
+
+@Serializable
+@SerialName("RESOURCE_NOT_FOUND")
+data class ErrorPayloadErrorResourceNotFound(
+    override val status: Long,
+    override val message: String,
+) : ErrorPayloadError
+
+@Serializable
+@SerialName("RECORD_NOT_FOUND")
+data class ErrorPayloadErrorRecordNotFound(
+    override val status: Long,
+    override val message: String,
+) : ErrorPayloadError
+
+@Serializable
+@SerialName("METHOD_NOT_ALLOWED")
+data class ErrorPayloadErrorMethodNotAllowed(
+    override val status: Long,
+    override val message: String,
+) : ErrorPayloadError
+
+@Serializable
+@SerialName("PRECONDITION_FAILED")
+data class ErrorPayloadErrorPreconditionFailed(
+    override val status: Long,
+    override val message: String,
+) : ErrorPayloadError
+
+@Serializable
+@SerialName("INVALID_PARAMS_PARSE")
+data class ErrorPayloadErrorInvalidParamsParse(
+    override val status: Long,
+    override val message: String,
+) : ErrorPayloadError
+
+@Serializable
+@SerialName("INVALID_PARAMS_VALIDATE")
+data class ErrorPayloadErrorInvalidParamsValidate(
+    override val status: Long,
+    override val message: String,
+) : ErrorPayloadError
+
+@Serializable
+@SerialName("INVALID_QUERY_PARSE")
+data class ErrorPayloadErrorInvalidQueryParse(
+    override val status: Long,
+    override val message: String,
+) : ErrorPayloadError
+
+@Serializable
+@SerialName("INVALID_QUERY_VALIDATE")
+data class ErrorPayloadErrorInvalidQueryValidate(
+    override val status: Long,
+    override val message: String,
+) : ErrorPayloadError
+
+@Serializable
+@SerialName("PAYLOAD_READ")
+data class ErrorPayloadErrorPayloadRead(
+    override val status: Long,
+    override val message: String,
+) : ErrorPayloadError
+
+@Serializable
+@SerialName("PAYLOAD_TOO_LARGE")
+data class ErrorPayloadErrorPayloadTooLarge(
+    override val status: Long,
+    override val message: String,
+) : ErrorPayloadError
+
+@Serializable
+@SerialName("PAYLOAD_CONTENT_TYPE")
+data class ErrorPayloadErrorPayloadContentType(
+    override val status: Long,
+    override val message: String,
+) : ErrorPayloadError
+
+@Serializable
+@SerialName("PAYLOAD_CONTENT_ENCODING")
+data class ErrorPayloadErrorPayloadContentEncoding(
+    override val status: Long,
+    override val message: String,
+) : ErrorPayloadError
+
+@Serializable
+@SerialName("INVALID_PAYLOAD_PARSE")
+data class ErrorPayloadErrorInvalidPayloadParse(
+    override val status: Long,
+    override val message: String,
+) : ErrorPayloadError
+
+@Serializable
+@SerialName("INVALID_PAYLOAD_VALIDATE")
+data class ErrorPayloadErrorInvalidPayloadValidate(
+    override val status: Long,
+    override val message: String,
+) : ErrorPayloadError
+
+@Serializable
+@SerialName("RATE_LIMITED")
+data class ErrorPayloadErrorRateLimited(
+    override val status: Long,
+    override val message: String,
+) : ErrorPayloadError
+
+@Serializable
+@SerialName("IDEMPOTENCY_KEY_INVALID")
+data class ErrorPayloadErrorIdempotencyKeyInvalid(
+    override val status: Long,
+    override val message: String,
+) : ErrorPayloadError
+
+@Serializable
+@SerialName("IDEMPOTENCY_KEY_IN_PROGRESS")
+data class ErrorPayloadErrorIdempotencyKeyInProgress(
+    override val status: Long,
+    override val message: String,
+) : ErrorPayloadError
+
+@Serializable
+@SerialName("IDEMPOTENCY_KEY_MISMATCH")
+data class ErrorPayloadErrorIdempotencyKeyMismatch(
+    override val status: Long,
+    override val message: String,
+) : ErrorPayloadError
+
+@Serializable
+@SerialName("TIMEOUT")
+data class ErrorPayloadErrorTimeout(
+    override val status: Long,
+    override val message: String,
+) : ErrorPayloadError
+
+/** The body of every error response */
+@Serializable
+data class ErrorPayload(
+    val error: ErrorPayloadError,
+    /** The id of the request that produced this error, see [`crate::trace::RequestId`] */
+    @SerialName("request_id")
+    val requestId: String? = null,
+)
+
+@Serializable
+data class GetUsersQuery(
+    /** How many records to skip for the current query */
+    val skip: Long? = null,
+    /** How many records to return as maximum for the current query */
+    val limit: Long? = null,
+)
+
+/** A user record */
+@Serializable
+data class User(
+    /** The unique id of the user */
+    val id: String,
+    /** The email address of the user */
+    val email: String,
+)
+
+/**
+ * A page of items starting from `skip` and limited by `limit`
+ * with the total number of records present in `total`
+ */
+@Serializable
+data class Page(
+    val skip: Long,
+    val limit: Long,
+    val total: Long,
+    val items: List<User>,
+)
+
+@Serializable
+data class GetUsersIdParams(
+    val id: String,
+)
+
+typealias GetUsersOutput = Page
+typealias GetUsersIdOutput = User
@@ -0,0 +1,392 @@
+// this file is auto generated by its Rust definition, do not edit manually
+
+import Foundation
+
+public enum ErrorPayloadError: Codable, Hashable {
+    case `internal`(ErrorPayloadErrorInternal)
+    case resourceNotFound(ErrorPayloadErrorResourceNotFound)
+    case recordNotFound(ErrorPayloadErrorRecordNotFound)
+    case methodNotAllowed(ErrorPayloadErrorMethodNotAllowed)
+    case preconditionFailed(ErrorPayloadErrorPreconditionFailed)
+    case invalidParamsParse(ErrorPayloadErrorInvalidParamsParse)
+    case invalidParamsValidate(ErrorPayloadErrorInvalidParamsValidate)
+    case invalidQueryParse(ErrorPayloadErrorInvalidQueryParse)
+    case invalidQueryValidate(ErrorPayloadErrorInvalidQueryValidate)
+    case payloadRead(ErrorPayloadErrorPayloadRead)
+    case payloadTooLarge(ErrorPayloadErrorPayloadTooLarge)
+    case payloadContentType(ErrorPayloadErrorPayloadContentType)
+    case payloadContentEncoding(ErrorPayloadErrorPayloadContentEncoding)
+    case invalidPayloadParse(ErrorPayloadErrorInvalidPayloadParse)
+    case invalidPayloadValidate(ErrorPayloadErrorInvalidPayloadValidate)
+    case rateLimited(ErrorPayloadErrorRateLimited)
+    case idempotencyKeyInvalid(ErrorPayloadErrorIdempotencyKeyInvalid)
+    case idempotencyKeyInProgress(ErrorPayloadErrorIdempotencyKeyInProgress)
+    case idempotencyKeyMismatch(ErrorPayloadErrorIdempotencyKeyMismatch)
+    case timeout(ErrorPayloadErrorTimeout)
+
+    private enum TagKeys: String, CodingKey {
+        case tag = "kind"
+    }
+
+    public init(from decoder: Decoder) throws {
+        let container = try decoder.container(keyedBy: TagKeys.self)
+        switch try container.decode(String.self, forKey: .tag) {
+        case "INTERNAL": self = .`internal`(try ErrorPayloadErrorInternal(from: decoder))
+        case "RESOURCE_NOT_FOUND": self = .resourceNotFound(try ErrorPayloadErrorResourceNotFound(from: decoder))
+        case "RECORD_NOT_FOUND": self = .recordNotFound(try ErrorPayloadErrorRecordNotFound(from: decoder))
+        case "METHOD_NOT_ALLOWED": self = .methodNotAllowed(try ErrorPayloadErrorMethodNotAllowed(from: decoder))
+        case "PRECONDITION_FAILED": self = .preconditionFailed(try ErrorPayloadErrorPreconditionFailed(from: decoder))
+        case "INVALID_PARAMS_PARSE": self = .invalidParamsParse(try ErrorPayloadErrorInvalidParamsParse(from: decoder))
+        case "INVALID_PARAMS_VALIDATE": self = .invalidParamsValidate(try ErrorPayloadErrorInvalidParamsValidate(from: decoder))
+        case "INVALID_QUERY_PARSE": self = .invalidQueryParse(try ErrorPayloadErrorInvalidQueryParse(from: decoder))
+        case "INVALID_QUERY_VALIDATE": self = .invalidQueryValidate(try ErrorPayloadErrorInvalidQueryValidate(from: decoder))
+        case "PAYLOAD_READ": self = .payloadRead(try ErrorPayloadErrorPayloadRead(from: decoder))
+        case "PAYLOAD_TOO_LARGE": self = .payloadTooLarge(try ErrorPayloadErrorPayloadTooLarge(from: decoder))
+        case "PAYLOAD_CONTENT_TYPE": self = .payloadContentType(try ErrorPayloadErrorPayloadContentType(from: decoder))
+        case "PAYLOAD_CONTENT_ENCODING": self = .payloadContentEncoding(try ErrorPayloadErrorPayloadContentEncoding(from: decoder))
+        case "INVALID_PAYLOAD_PARSE": self = .invalidPayloadParse(try ErrorPayloadErrorInvalidPayloadParse(from: decoder))
+        case "INVALID_PAYLOAD_VALIDATE": self = .invalidPayloadValidate(try ErrorPayloadErrorInvalidPayloadValidate(from: decoder))
+        case "RATE_LIMITED": self = .rateLimited(try ErrorPayloadErrorRateLimited(from: decoder))
+        case "IDEMPOTENCY_KEY_INVALID": self = .idempotencyKeyInvalid(try ErrorPayloadErrorIdempotencyKeyInvalid(from: decoder))
+        case "IDEMPOTENCY_KEY_IN_PROGRESS": self = .idempotencyKeyInProgress(try ErrorPayloadErrorIdempotencyKeyInProgress(from: decoder))
+        case "IDEMPOTENCY_KEY_MISMATCH": self = .idempotencyKeyMismatch(try ErrorPayloadErrorIdempotencyKeyMismatch(from: decoder))
+        case "TIMEOUT": self = .timeout(try ErrorPayloadErrorTimeout(from: decoder))
+        case let other: throw DecodingError.dataCorruptedError(forKey: .tag, in: container, debugDescription: "unknown kind \(other)")
+        }
+    }
+
+    public func encode(to encoder: Encoder) throws {
+        var container = encoder.container(keyedBy: TagKeys.self)
+        switch self {
+        case .`internal`(let value):
+            try container.encode("INTERNAL", forKey: .tag)
+            try value.encode(to: encoder)
+        case .resourceNotFound(let value):
+            try container.encode("RESOURCE_NOT_FOUND", forKey: .tag)
+            try value.encode(to: encoder)
+        case .recordNotFound(let value):
+            try container.encode("RECORD_NOT_FOUND", forKey: .tag)
+            try value.encode(to: encoder)
+        case .methodNotAllowed(let value):
+            try container.encode("METHOD_NOT_ALLOWED", forKey: .tag)
+            try value.encode(to: encoder)
+        case .preconditionFailed(let value):
+            try container.encode("PRECONDITION_FAILED", forKey: .tag)
+            try value.encode(to: encoder)
+        case .invalidParamsParse(let value):
+            try container.encode("INVALID_PARAMS_PARSE", forKey: .tag)
+            try value.encode(to: encoder)
+        case .invalidParamsValidate(let value):
+            try container.encode("INVALID_PARAMS_VALIDATE", forKey: .tag)
+            try value.encode(to: encoder)
+        case .invalidQueryParse(let value):
+            try container.encode("INVALID_QUERY_PARSE", forKey: .tag)
+            try value.encode(to: encoder)
+        case .invalidQueryValidate(let value):
+            try container.encode("INVALID_QUERY_VALIDATE", forKey: .tag)
+            try value.encode(to: encoder)
+        case .payloadRead(let value):
+            try container.encode("PAYLOAD_READ", forKey: .tag)
+            try value.encode(to: encoder)
+        case .payloadTooLarge(let value):
+            try container.encode("PAYLOAD_TOO_LARGE", forKey: .tag)
+            try value.encode(to: encoder)
+        case .payloadContentType(let value):
+            try container.encode("PAYLOAD_CONTENT_TYPE", forKey: .tag)
+            try value.encode(to: encoder)
+        case .payloadContentEncoding(let value):
+            try container.encode("PAYLOAD_CONTENT_ENCODING", forKey: .tag)
+            try value.encode(to: encoder)
+        case .invalidPayloadParse(let value):
+            try container.encode("INVALID_PAYLOAD_PARSE", forKey: .tag)
+            try value.encode(to: encoder)
+        case .invalidPayloadValidate(let value):
+            try container.encode("INVALID_PAYLOAD_VALIDATE", forKey: .tag)
+            try value.encode(to: encoder)
+        case .rateLimited(let value):
+            try container.encode("RATE_LIMITED", forKey: .tag)
+            try value.encode(to: encoder)
+        case .idempotencyKeyInvalid(let value):
+            try container.encode("IDEMPOTENCY_KEY_INVALID", forKey: .tag)
+            try value.encode(to: encoder)
+        case .idempotencyKeyInProgress(let value):
+            try container.encode("IDEMPOTENCY_KEY_IN_PROGRESS", forKey: .tag)
+            try value.encode(to: encoder)
+        case .idempotencyKeyMismatch(let value):
+            try container.encode("IDEMPOTENCY_KEY_MISMATCH", forKey: .tag)
+            try value.encode(to: encoder)
+        case .timeout(let value):
+            try container.encode("TIMEOUT", forKey: .tag)
+            try value.encode(to: encoder)
+        }
+    }
+}
+
+public struct ErrorPayloadErrorInternal: Codable, Hashable {
+    public var status: Int64
+    public var message: String
+
+    public init(status: Int64, message: String) {
+        self.status = status
+        self.message = message
+    }
+}
+
+public struct ErrorPayloadErrorResourceNotFound: Codable, Hashable {
+    public var status: Int64
+    public var message: String
+
+    public init(status: Int64, message: String) {
+        self.status = status
+        self.message = message
+    }
+}
+
+public struct ErrorPayloadErrorRecordNotFound: Codable, Hashable {
+    public var status: Int64
+    public var message: String
+
+    public init(status: Int64, message: String) {
+        self.status = status
+        self.message = message
+    }
+}
+
+public struct ErrorPayloadErrorMethodNotAllowed: Codable, Hashable {
+    public var status: Int64
+    public var message: String
+
+    public init(status: Int64, message: String) {
+        self.status = status
+        self.message = message
+    }
+}
+
+public struct ErrorPayloadErrorPreconditionFailed: Codable, Hashable {
+    public var status: Int64
+    public var message: String
+
+    public init(status: Int64, message: String) {
+        self.status = status
+        self.message = message
+    }
+}
+
+public struct ErrorPayloadErrorInvalidParamsParse: Codable, Hashable {
+    public var status: Int64
+    public var message: String
+
+    public init(status: Int64, message: String) {
+        self.status = status
+        self.message = message
+    }
+}
+
+public struct ErrorPayloadErrorInvalidParamsValidate: Codable, Hashable {
+    public var status: Int64
+    public var message: String
+
+    public init(status: Int64, message: String) {
+        self.status = status
+        self.message = message
+    }
+}
+
+public struct ErrorPayloadErrorInvalidQueryParse: Codable, Hashable {
+    public var status: Int64
+    public var message: String
+
+    public init(status: Int64, message: String) {
+        self.status = status
+        self.message = message
+    }
+}
+
+public struct ErrorPayloadErrorInvalidQueryValidate: Codable, Hashable {
+    public var status: Int64
+    public var message: String
+
+    public init(status: Int64, message: String) {
+        self.status = status
+        self.message = message
+    }
+}
+
+public struct ErrorPayloadErrorPayloadRead: Codable, Hashable {
+    public var status: Int64
+    public var message: String
+
+    public init(status: Int64, message: String) {
+        self.status = status
+        self.message = message
+    }
+}
+
+public struct ErrorPayloadErrorPayloadTooLarge: Codable, Hashable {
+    public var status: Int64
+    public var message: String
+
+    public init(status: Int64, message: String) {
+        self.status = status
+        self.message = message
+    }
+}
+
+public struct ErrorPayloadErrorPayloadContentType: Codable, Hashable {
+    public var status: Int64
+    public var message: String
+
+    public init(status: Int64, message: String) {
+        self.status = status
+        self.message = message
+    }
+}
+
+public struct ErrorPayloadErrorPayloadContentEncoding: Codable, Hashable {
+    public var status: Int64
+    public var message: String
+
+    public init(status: Int64, message: String) {
+        self.status = status
+        self.message = message
+    }
+}
+
+public struct ErrorPayloadErrorInvalidPayloadParse: Codable, Hashable {
+    public var status: Int64
+    public var message: String
+
+    public init(status: Int64, message: String) {
+        self.status = status
+        self.message = message
+    }
+}
+
+public struct ErrorPayloadErrorInvalidPayloadValidate: Codable, Hashable {
+    public var status: Int64
+    public var message: String
+
+    public init(status: Int64, message: String) {
+        self.status = status
+        self.message = message
+    }
+}
+
+public struct ErrorPayloadErrorRateLimited: Codable, Hashable {
+    public var status: Int64
+    public var message: String
+
+    public init(status: Int64, message: String) {
+        self.status = status
+        self.message = message
+    }
+}
+
+public struct ErrorPayloadErrorIdempotencyKeyInvalid: Codable, Hashable {
+    public var status: Int64
+    public var message: String
+
+    public init(status: Int64, message: String) {
+        self.status = status
+        self.message = message
+    }
+}
+
+public struct ErrorPayloadErrorIdempotencyKeyInProgress: Codable, Hashable {
+    public var status: Int64
+    public var message: String
+
+    public init(status: Int64, message: String) {
+        self.status = status
+        self.message = message
+    }
+}
+
+public struct ErrorPayloadErrorIdempotencyKeyMismatch: Codable, Hashable {
+    public var status: Int64
+    public var message: String
+
+    public init(status: Int64, message: String) {
+        self.status = status
+        self.message = message
+    }
+}
+
+public struct ErrorPayloadErrorTimeout: Codable, Hashable {
+    public var status: Int64
+    public var message: String
+
+    public init(status: Int64, message: String) {
+        self.status = status
+        self.message = message
+    }
+}
+
+/// The body of every error response
+public struct ErrorPayload: Codable, Hashable {
+    public var error: ErrorPayloadError
+    /// The id of the request that produced this error, see [`crate::trace::RequestId`]
+    public var requestId: String?
+
+    enum CodingKeys: String, CodingKey {
+        case error
+        case requestId = "request_id"
+    }
+
+    public init(error: ErrorPayloadError, requestId: String? = nil) {
+        self.error = error
+        self.requestId = requestId
+    }
+}
+
+public struct GetUsersQuery: Codable, Hashable {
+    /// How many records to skip for the current query
+    public var skip: Int64?
+    /// How many records to return as maximum for the current query
+    public var limit: Int64?
+
+    public init(skip: Int64? = nil, limit: Int64? = nil) {
+        self.skip = skip
+        self.limit = limit
+    }
+}
+
+/// A user record
+public struct User: Codable, Hashable {
+    /// The unique id of the user
+    public var id: String
+    /// The email address of the user
+    public var email: String
+
+    public init(id: String, email: String) {
+        self.id = id
+        self.email = email
+    }
+}
+
+/// A page of items starting from `skip` and limited by `limit`
+/// with the total number of records present in `total`
+public struct Page: Codable, Hashable {
+    public var skip: Int64
+    public var limit: Int64
+    public var total: Int64
+    public var items: [User]
+
+    public init(skip: Int64, limit: Int64, total: Int64, items: [User]) {
+        self.skip = skip
+        self.limit = limit
+        self.total = total
+        self.items = items
+    }
+}
+
+public struct GetUsersIdParams: Codable, Hashable {
+    public var id: String
+
+    public init(id: String) {
+        self.id = id
+    }
+}
+
+public typealias GetUsersOutput = Page
+public typealias GetUsersIdOutput = User
@@ -0,0 +1,251 @@
+# this file is auto generated by its Rust definition, do not edit manually
+
+from __future__ import annotations
+
+import re
+from enum import Enum
+from typing import Annotated, Any, Literal, Optional, Union
+from urllib.parse import quote
+
+import httpx
+from pydantic import BaseModel, ConfigDict, Field, TypeAdapter
+
+
+class ErrorPayloadErrorInternal(BaseModel):
+    kind: Literal["INTERNAL"]
+    status: int
+    message: str
+
+
+class ErrorPayloadErrorResourceNotFound(BaseModel):
+    kind: Literal["RESOURCE_NOT_FOUND"]
+    status: int
+    message: str
+
+
+class ErrorPayloadErrorRecordNotFound(BaseModel):
+    kind: Literal["RECORD_NOT_FOUND"]
+    status: int
+    message: str
+
+
+class ErrorPayloadErrorMethodNotAllowed(BaseModel):
+    kind: Literal["METHOD_NOT_ALLOWED"]
+    status: int
+    message: str
+
+
+class ErrorPayloadErrorPreconditionFailed(BaseModel):
+    kind: Literal["PRECONDITION_FAILED"]
+    status: int
+    message: str
+
+
+class ErrorPayloadErrorInvalidParamsParse(BaseModel):
+    kind: Literal["INVALID_PARAMS_PARSE"]
+    status: int
+    message: str
+
+
+class ErrorPayloadErrorInvalidParamsValidate(BaseModel):
+    kind: Literal["INVALID_PARAMS_VALIDATE"]
+    status: int
+    message: str
+
+
+class ErrorPayloadErrorInvalidQueryParse(BaseModel):
+    kind: Literal["INVALID_QUERY_PARSE"]
+    status: int
+    message: str
+
+
+class ErrorPayloadErrorInvalidQueryValidate(BaseModel):
+    kind: Literal["INVALID_QUERY_VALIDATE"]
+    status: int
+    message: str
+
+
+class ErrorPayloadErrorPayloadRead(BaseModel):
+    kind: Literal["PAYLOAD_READ"]
+    status: int
+    message: str
+
+
+class ErrorPayloadErrorPayloadTooLarge(BaseModel):
+    kind: Literal["PAYLOAD_TOO_LARGE"]
+    status: int
+    message: str
+
+
+class ErrorPayloadErrorPayloadContentType(BaseModel):
+    kind: Literal["PAYLOAD_CONTENT_TYPE"]
+    status: int
+    message: str
+
+
+class ErrorPayloadErrorPayloadContentEncoding(BaseModel):
+    kind: Literal["PAYLOAD_CONTENT_ENCODING"]
+    status: int
+    message: str
+
+
+class ErrorPayloadErrorInvalidPayloadParse(BaseModel):
+    kind: Literal["INVALID_PAYLOAD_PARSE"]
+    status: int
+    message: str
+
+
+class ErrorPayloadErrorInvalidPayloadValidate(BaseModel):
+    kind: Literal["INVALID_PAYLOAD_VALIDATE"]
+    status: int
+    message: str
+
+
+class ErrorPayloadErrorRateLimited(BaseModel):
+    kind: Literal["RATE_LIMITED"]
+    status: int
+    message: str
+
+
+class ErrorPayloadErrorIdempotencyKeyInvalid(BaseModel):
+    kind: Literal["IDEMPOTENCY_KEY_INVALID"]
+    status: int
+    message: str
+
+
+class ErrorPayloadErrorIdempotencyKeyInProgress(BaseModel):
+    kind: Literal["IDEMPOTENCY_KEY_IN_PROGRESS"]
+    status: int
+    message: str
+
+
+class ErrorPayloadErrorIdempotencyKeyMismatch(BaseModel):
+    kind: Literal["IDEMPOTENCY_KEY_MISMATCH"]
+    status: int
+    message: str
+
+
+class ErrorPayloadErrorTimeout(BaseModel):
+    kind: Literal["TIMEOUT"]
+    status: int
+    message: str
+
+
+ErrorPayloadError = Annotated[Union[ErrorPayloadErrorInternal, ErrorPayloadErrorResourceNotFound, ErrorPayloadErrorRecordNotFound, ErrorPayloadErrorMethodNotAllowed, ErrorPayloadErrorPreconditionFailed, ErrorPayloadErrorInvalidParamsParse, ErrorPayloadErrorInvalidParamsValidate, ErrorPayloadErrorInvalidQueryParse, ErrorPayloadErrorInvalidQueryValidate, ErrorPayloadErrorPayloadRead, ErrorPayloadErrorPayloadTooLarge, ErrorPayloadErrorPayloadContentType, ErrorPayloadErrorPayloadContentEncoding, ErrorPayloadErrorInvalidPayloadParse, ErrorPayloadErrorInvalidPayloadValidate, ErrorPayloadErrorRateLimited, ErrorPayloadErrorIdempotencyKeyInvalid, ErrorPayloadErrorIdempotencyKeyInProgress, ErrorPayloadErrorIdempotencyKeyMismatch, ErrorPayloadErrorTimeout], Field(discriminator="kind")]
+
+
+class ErrorPayload(BaseModel):
+    """The body of every error response"""
+
+    error: ErrorPayloadError
+    request_id: Optional[str] = None
+    """The id of the request that produced this error, see [`crate::trace::RequestId`]"""
+
+
+class GetUsersQuery(BaseModel):
+    skip: Optional[int] = None
+    """How many records to skip for the current query"""
+    limit: Optional[int] = None
+    """How many records to return as maximum for the current query"""
+
+
+class User(BaseModel):
+    """A user record"""
+
+    id: str
+    """The unique id of the user"""
+    email: str
+    """The email address of the user"""
+
+
+class Page(BaseModel):
+    """
+    A page of items starting from `skip` and limited by `limit`
+    with the total number of records present in `total`
+    """
+
+    skip: int
+    limit: int
+    total: int
+    items: list[User]
+
+
+class GetUsersIdParams(BaseModel):
+    id: str
+
+
+GetUsersOutput = Page
+GetUsersIdOutput = User
+
+
+class ApiError(Exception):
+    """An error response, `payload` holds the error kind and message"""
+
+    def __init__(self, status: int, payload: ErrorPayload) -> None:
+        super().__init__(f"request failed with status {status}")
+        self.status = status
+        self.payload = payload
+
+
+def _dump(value: Any) -> Any:
+    return TypeAdapter(type(value)).dump_python(value, mode="json", by_alias=True, exclude_none=True)
+
+
+def _fill_path(path: str, params: dict[str, Any]) -> str:
+    def replace(match: re.Match[str]) -> str:
+        # wildcards keep their slashes
+        safe = "/" if match.group(0).startswith("*") else ""
+        return quote(str(params[match.group(1)]), safe=safe)
+
+    return re.sub(r"[:*]([A-Za-z0-9_]+)", replace, path)
+
+
+def _query_items(value: Any, key: str = "") -> list[tuple[str, str]]:
+    """Nested values use the bracket notation parsed by the server, eg: `filter[name]=x&ids[0]=1`"""
+    if value is None:
+        return []
+    if isinstance(value, dict):
+        items = [_query_items(item, f"{key}[{name}]" if key else name) for name, item in value.items()]
+        return [item for nested in items for item in nested]
+    if isinstance(value, list):
+        items = [_query_items(item, f"{key}[{i}]") for i, item in enumerate(value)]
+        return [item for nested in items for item in nested]
+    if isinstance(value, bool):
+        return [(key, "true" if value else "false")]
+    return [(key, str(value))]
+
+
+class Client:
+    """A client for every endpoint of the API"""
+
+    def __init__(self, base_url: str, client: Optional[httpx.Client] = None) -> None:
+        self.base_url = base_url.rstrip("/")
+        self.client = client or httpx.Client()
+
+    def _request(
+        self,
+        method: str,
+        path: str,
+        output: Any,
+        params: Any = None,
+        query: Any = None,
+        payload: Any = None,
+    ) -> Any:
+        url = self.base_url + _fill_path(path, _dump(params) if params is not None else {})
+        res = self.client.request(
+            method,
+            url,
+            params=_query_items(_dump(query)) if query is not None else None,
+            json=_dump(payload) if payload is not None else None,
+        )
+        if res.is_error:
+            raise ApiError(res.status_code, TypeAdapter(ErrorPayload).validate_python(res.json()))
+        if res.status_code == 204 or method == "HEAD":
+            return None
+        return TypeAdapter(output).validate_python(res.json())
+
+    def get_users(self, *, query: Optional[GetUsersQuery] = None) -> GetUsersOutput:
+        return self._request("GET", "/users", GetUsersOutput, query=query)
+
+    def get_users_id(self, params: GetUsersIdParams) -> GetUsersIdOutput:
+        return self._request("GET", "/users/:id", GetUsersIdOutput, params=params)
@@ -0,0 +1,86 @@
+/// this file is auto generated by its Rust definition, do not edit manually
+
+import { z } from "zod";
+
+export const Empty = z.object({}).strict();
+
+export const ErrorPayload = z.object({
+  error: z.object({
+    status: z.number().int().gte(400).lte(599),
+    message: z.string(),
+  }).and(z.union([z.object({
+    kind: z.literal("INTERNAL"),
+  }), z.object({
+    kind: z.literal("RESOURCE_NOT_FOUND"),
+  }), z.object({
+    kind: z.literal("RECORD_NOT_FOUND"),
+  }), z.object({
+    kind: z.literal("METHOD_NOT_ALLOWED"),
+  }), z.object({
+    kind: z.literal("PRECONDITION_FAILED"),
+  }), z.object({
+    kind: z.literal("INVALID_PARAMS_PARSE"),
+  }), z.object({
+    kind: z.literal("INVALID_PARAMS_VALIDATE"),
+  }), z.object({
+    kind: z.literal("INVALID_QUERY_PARSE"),
+  }), z.object({
+    kind: z.literal("INVALID_QUERY_VALIDATE"),
+  }), z.object({
+    kind: z.literal("PAYLOAD_READ"),
+  }), z.object({
+    kind: z.literal("PAYLOAD_TOO_LARGE"),
+  }), z.object({
+    kind: z.literal("PAYLOAD_CONTENT_TYPE"),
+  }), z.object({
+    kind: z.literal("PAYLOAD_CONTENT_ENCODING"),
+  }), z.object({
+    kind: z.literal("INVALID_PAYLOAD_PARSE"),
+  }), z.object({
+    kind: z.literal("INVALID_PAYLOAD_VALIDATE"),
+  }), z.object({
+    kind: z.literal("RATE_LIMITED"),
+  }), z.object({
+    kind: z.literal("IDEMPOTENCY_KEY_INVALID"),
+  }), z.object({
+    kind: z.literal("IDEMPOTENCY_KEY_IN_PROGRESS"),
+  }), z.object({
+    kind: z.literal("IDEMPOTENCY_KEY_MISMATCH"),
+  }), z.object({
+    kind: z.literal("TIMEOUT"),
+  })])),
+  request_id: z.string().nullable().describe("The id of the request that produced this error, see [`crate::trace::RequestId`]").optional(),
+}).describe("The body of every error response");
+
+export const GetUsersQuery = z.object({
+  skip: z.number().int().gte(0).nullable().describe("How many records to skip for the current query").optional(),
+  limit: z.number().int().gte(1).lte(200).nullable().describe("How many records to return as maximum for the current query").optional(),
+});
+
+export const GetUsersOutput = z.object({
+  skip: z.number().int().gte(0),
+  limit: z.number().int().gte(1),
+  total: z.number().int().gte(0),
+  items: z.array(z.object({
+    id: z.string().regex(new RegExp("^[a-z0-9]+$")).describe("The unique id of the user"),
+    email: z.string().email().max(100).describe("The email address of the user"),
+  }).describe("A user record")),
+}).describe("A page of items starting from `skip` and limited by `limit`\nwith the total number of records present in `total`");
+
+export const GetUsersIdParams = z.object({
+  id: z.string().regex(new RegExp("^[a-z0-9]+$")),
+});
+
+export const GetUsersIdOutput = z.object({
+  id: z.string().regex(new RegExp("^[a-z0-9]+$")).describe("The unique id of the user"),
+  email: z.string().email().max(100).describe("The email address of the user"),
+}).describe("A user record");
+
+export const ApiSchemas = {
+  "/users": {
+    "GET": { params: Empty, query: GetUsersQuery, payload: Empty, output: GetUsersOutput },
+  },
+  "/users/:id": {
+    "GET": { params: GetUsersIdParams, query: Empty, payload: Empty, output: GetUsersIdOutput },
+  },
+} as const;
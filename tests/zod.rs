@@ -0,0 +1,23 @@
+mod common;
+
+use auto_api::api;
+use common::assert_snapshot;
+
+/// The error payload covers the tagged enum and a nullable field,
+/// the users endpoints the email, length, range and pattern rules
+#[test]
+fn zod_definitions() {
+  let zod = api::registry().zod_definitions();
+
+  for expected in [
+    "kind: z.literal(\"RESOURCE_NOT_FOUND\"),",
+    "request_id: z.string().nullable()",
+    "email: z.string().email().max(100)",
+    "limit: z.number().int().gte(1).lte(200)",
+    "id: z.string().regex(new RegExp(\"^[a-z0-9]+$\"))",
+  ] {
+    assert!(zod.contains(expected), "missing `{expected}` in\n{zod}");
+  }
+
+  assert_snapshot("zod.ts", &zod);
+}